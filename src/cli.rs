@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use is_terminal::IsTerminal;
 use std::io::{stdin, Read};
+use std::path::PathBuf;
 
 use crate::router::TurnPolicy;
 
@@ -100,6 +101,70 @@ pub struct Cli {
     text: Vec<String>,
 }
 
+/// `fio resolver <command>`: offline tooling for the intent resolver.
+#[derive(Parser, Debug)]
+#[command(name = "fio resolver")]
+pub struct ResolverCli {
+    #[command(subcommand)]
+    pub command: ResolverCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ResolverCommand {
+    /// Score the resolver against a labelled JSONL corpus; exits non-zero on regressions
+    Eval(ResolverEvalArgs),
+    /// Print the resolver vocabulary (aliases, workspaces, examples) as YAML or JSON
+    Export(ResolverExportArgs),
+    /// Load a vocabulary file into the resolver store, showing what changes
+    Import(ResolverImportArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ResolverEvalArgs {
+    /// JSONL corpus of {"text", "provider", "workspace", "action"} lines
+    pub corpus: PathBuf,
+    /// Ask the configured model when the deterministic pass is unsure
+    #[clap(long, conflicts_with = "replay")]
+    pub ai: bool,
+    /// Save the model's answers as JSONL for --replay
+    #[clap(long, value_name = "FILE", requires = "ai")]
+    pub record: Option<PathBuf>,
+    /// Answer from recorded model output instead of calling the model
+    #[clap(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Tolerate utterances that already fail in this saved report
+    #[clap(long, value_name = "REPORT")]
+    pub baseline: Option<PathBuf>,
+    /// Save the report as JSON, e.g. to use as a later baseline
+    #[clap(long, value_name = "REPORT")]
+    pub save: Option<PathBuf>,
+    /// Print the report as JSON
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ResolverExportArgs {
+    /// Write to this file instead of stdout; a `.json` file is written as JSON
+    #[clap(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Write JSON instead of YAML
+    #[clap(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ResolverImportArgs {
+    /// YAML vocabulary file, or JSON when it ends in `.json`
+    pub file: PathBuf,
+    /// Remove entries, aliases and examples the file doesn't list instead of merging
+    #[clap(long)]
+    pub replace: bool,
+    /// Show what would change without saving
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
 impl Cli {
     pub fn turn_policy(&self, default_policy: TurnPolicy) -> TurnPolicy {
         if self.auto {
@@ -115,6 +180,12 @@ impl Cli {
         }
     }
 
+    /// Whether `text` is only what was typed as arguments. Piped stdin and `--file`
+    /// content are data, so such a turn is never split into several requests.
+    pub fn is_typed_only(&self, text: &str) -> bool {
+        self.file.is_empty() && text == self.text.join(" ")
+    }
+
     pub fn text(&self) -> Result<Option<String>> {
         let mut stdin_text = String::new();
         if !stdin().is_terminal() {
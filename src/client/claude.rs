@@ -86,6 +86,15 @@ pub async fn claude_chat_completions_streaming(
         debug!("stream-data: {data}");
         if let Some(typ) = data["type"].as_str() {
             match typ {
+                "message_start" => {
+                    handler.usage(
+                        data["message"]["usage"]["input_tokens"].as_u64(),
+                        data["message"]["usage"]["output_tokens"].as_u64(),
+                    );
+                }
+                "message_delta" => {
+                    handler.usage(None, data["usage"]["output_tokens"].as_u64());
+                }
                 "content_block_start" => {
                     if let (Some("tool_use"), Some(name), Some(id)) = (
                         data["content_block"]["type"].as_str(),
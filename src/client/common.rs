@@ -4,6 +4,7 @@ use crate::{
     config::{Config, GlobalConfig, Input, RoleLike},
     function::{eval_tool_calls, FunctionDeclaration, ToolCall, ToolResult},
     render::render_stream,
+    telemetry::{in_span, set_attribute, AttrValue, SpanKind},
     utils::*,
 };
 
@@ -17,7 +18,7 @@ use reqwest::{Client as ReqwestClient, RequestBuilder};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::sync::LazyLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::unbounded_channel;
 
 const MODELS_YAML: &str = include_str!("../../models.yaml");
@@ -72,9 +73,31 @@ pub trait Client: Sync + Send {
         }
         let client = self.build_client()?;
         let data = input.prepare_completion_data(self.model(), false)?;
-        self.chat_completions_inner(&client, data)
-            .await
-            .with_context(|| "Failed to call chat-completions api")
+        let model = self.model().id();
+        let start = Instant::now();
+        let ret = in_span(
+            "chat_completions",
+            SpanKind::Client,
+            span_attributes(&model, false),
+            async {
+                let output = self
+                    .chat_completions_inner(&client, data)
+                    .await
+                    .with_context(|| "Failed to call chat-completions api")?;
+                if let Some(v) = output.input_tokens {
+                    set_attribute("gen_ai.usage.input_tokens", v);
+                }
+                if let Some(v) = output.output_tokens {
+                    set_attribute("gen_ai.usage.output_tokens", v);
+                }
+                Ok(output)
+            },
+        )
+        .await;
+        log_chat_completion(&model, false, start, ret.as_ref().err());
+        let output = ret?;
+        crate::metrics::record_tokens(&model, output.input_tokens, output.output_tokens);
+        Ok(output)
     }
 
     async fn chat_completions_streaming(
@@ -93,7 +116,21 @@ pub trait Client: Sync + Send {
                 }
                 let client = self.build_client()?;
                 let data = input.prepare_completion_data(self.model(), true)?;
-                self.chat_completions_streaming_inner(&client, handler, data).await
+                let model = self.model().id();
+                let start = Instant::now();
+                let ret = in_span(
+                    "chat_completions",
+                    SpanKind::Client,
+                    span_attributes(&model, true),
+                    self.chat_completions_streaming_inner(&client, handler, data),
+                )
+                .await;
+                log_chat_completion(&model, true, start, ret.as_ref().err());
+                if ret.is_ok() {
+                    let (input_tokens, output_tokens) = handler.tokens();
+                    crate::metrics::record_tokens(&model, input_tokens, output_tokens);
+                }
+                ret
             } => {
                 handler.done();
                 ret.with_context(|| "Failed to call chat-completions api")
@@ -434,21 +471,30 @@ pub async fn call_chat_completions(
                     client.global_config().read().print_markdown(&text)?;
                 }
             }
-            Ok((
-                text,
-                eval_tool_calls(
-                    client.global_config(),
-                    tool_calls,
-                    input.role().tool_call_permission(),
-                    input.role().tool_permissions(),
-                )
-                .await?,
-            ))
+            Ok((text, eval_turn_tool_calls(input, client, tool_calls).await?))
         }
         Err(err) => Err(err),
     }
 }
 
+/// Run a turn without a terminal, feeding tool results back until the model answers.
+/// The per-turn tool budget guarantees this ends.
+pub async fn call_chat_completions_headless(
+    mut input: Input,
+    abort_signal: AbortSignal,
+) -> Result<String> {
+    loop {
+        let client = input.create_client()?;
+        let (output, tool_results) =
+            call_chat_completions(&input, false, false, client.as_ref(), abort_signal.clone())
+                .await?;
+        if tool_results.is_empty() {
+            return Ok(output);
+        }
+        input = input.merge_tool_results(output, tool_results);
+    }
+}
+
 pub async fn call_chat_completions_streaming(
     input: &Input,
     client: &dyn Client,
@@ -474,16 +520,7 @@ pub async fn call_chat_completions_streaming(
             if !text.is_empty() && !text.ends_with('\n') {
                 println!();
             }
-            Ok((
-                text,
-                eval_tool_calls(
-                    client.global_config(),
-                    tool_calls,
-                    input.role().tool_call_permission(),
-                    input.role().tool_permissions(),
-                )
-                .await?,
-            ))
+            Ok((text, eval_turn_tool_calls(input, client, tool_calls).await?))
         }
         Err(err) => {
             if !text.is_empty() {
@@ -494,6 +531,51 @@ pub async fn call_chat_completions_streaming(
     }
 }
 
+async fn eval_turn_tool_calls(
+    input: &Input,
+    client: &dyn Client,
+    tool_calls: Vec<ToolCall>,
+) -> Result<Vec<ToolResult>> {
+    if let Some(reason) = input.tool_budget_exceeded() {
+        if !tool_calls.is_empty() {
+            bail!(
+                "Tool budget exhausted ({reason}), but the model still requested {} tool call(s); \
+                 no final answer was produced",
+                tool_calls.len()
+            );
+        }
+        return Ok(vec![]);
+    }
+    eval_tool_calls(
+        client.global_config(),
+        tool_calls,
+        input.role().tool_call_permission(),
+        input.role().tool_permissions(),
+    )
+    .await
+}
+
+fn span_attributes(model: &str, stream: bool) -> Vec<(&'static str, AttrValue)> {
+    vec![
+        ("gen_ai.request.model", model.into()),
+        ("fiochat.stream", stream.into()),
+    ]
+}
+
+fn log_chat_completion(model: &str, stream: bool, start: Instant, err: Option<&anyhow::Error>) {
+    let duration_ms = start.elapsed().as_millis() as u64;
+    match err {
+        None => info!(
+            model = model, stream = stream, duration_ms = duration_ms, outcome = "ok";
+            "chat completion {model} in {duration_ms}ms"
+        ),
+        Some(err) => warn!(
+            model = model, stream = stream, duration_ms = duration_ms, outcome = "error";
+            "chat completion {model} failed after {duration_ms}ms: {err:#}"
+        ),
+    }
+}
+
 pub fn noop_prepare_embeddings<T>(_client: &T, _data: &EmbeddingsData) -> Result<RequestData> {
     bail!("The client doesn't support embeddings api")
 }
@@ -50,7 +50,10 @@ fn prepare_chat_completions(
 
     let url = format!("{}/chat/completions", api_base.trim_end_matches('/'));
 
-    let body = openai_build_chat_completions_body(data, &self_.model);
+    let mut body = openai_build_chat_completions_body(data, &self_.model);
+    if body["stream"] == true {
+        body["stream_options"] = json!({ "include_usage": true });
+    }
 
     let mut request_data = RequestData::new(url, body);
 
@@ -126,6 +129,12 @@ pub async fn openai_chat_completions_streaming(
         }
         let data: Value = serde_json::from_str(&message.data)?;
         debug!("stream-data: {data}");
+        if data["usage"].is_object() {
+            handler.usage(
+                data["usage"]["prompt_tokens"].as_u64(),
+                data["usage"]["completion_tokens"].as_u64(),
+            );
+        }
         if let Some(text) = data["choices"][0]["delta"]["content"]
             .as_str()
             .filter(|v| !v.is_empty())
@@ -13,6 +13,8 @@ pub struct SseHandler {
     abort_signal: AbortSignal,
     buffer: String,
     tool_calls: Vec<ToolCall>,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
 }
 
 impl SseHandler {
@@ -22,6 +24,8 @@ impl SseHandler {
             abort_signal,
             buffer: String::new(),
             tool_calls: Vec::new(),
+            input_tokens: None,
+            output_tokens: None,
         }
     }
 
@@ -61,6 +65,20 @@ impl SseHandler {
         Ok(())
     }
 
+    /// Token counts reported by the stream; later reports replace earlier ones.
+    pub fn usage(&mut self, input_tokens: Option<u64>, output_tokens: Option<u64>) {
+        if input_tokens.is_some() {
+            self.input_tokens = input_tokens;
+        }
+        if output_tokens.is_some() {
+            self.output_tokens = output_tokens;
+        }
+    }
+
+    pub fn tokens(&self) -> (Option<u64>, Option<u64>) {
+        (self.input_tokens, self.output_tokens)
+    }
+
     pub fn abort(&self) -> AbortSignal {
         self.abort_signal.clone()
     }
@@ -293,4 +311,13 @@ mod tests {
 {"key": "value3"}"#;
         assert_json_stream!(input, output);
     }
+
+    #[test]
+    fn test_usage_keeps_earlier_counts() {
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut handler = SseHandler::new(tx, crate::utils::create_abort_signal());
+        handler.usage(Some(12), Some(1));
+        handler.usage(None, Some(40));
+        assert_eq!(handler.tokens(), (Some(12), Some(40)));
+    }
 }
@@ -208,6 +208,12 @@ pub async fn gemini_chat_completions_streaming(
         let handle = |value: &str| -> Result<()> {
             let data: Value = serde_json::from_str(value)?;
             debug!("stream-data: {data}");
+            if data["usageMetadata"].is_object() {
+                handler.usage(
+                    data["usageMetadata"]["promptTokenCount"].as_u64(),
+                    data["usageMetadata"]["candidatesTokenCount"].as_u64(),
+                );
+            }
             if let Some(parts) = data["candidates"][0]["content"]["parts"].as_array() {
                 for (i, part) in parts.iter().enumerate() {
                     if let Some(text) = part["text"].as_str() {
@@ -4,7 +4,7 @@ use crate::client::{
     init_client, patch_messages, ChatCompletionsData, Client, ImageUrl, Message, MessageContent,
     MessageContentPart, MessageContentToolCalls, MessageRole, Model,
 };
-use crate::function::ToolResult;
+use crate::function::{ToolBudget, ToolResult};
 use crate::utils::{base64_encode, is_loader_protocol, sha256, AbortSignal};
 
 use anyhow::{bail, Context, Result};
@@ -27,6 +27,7 @@ pub struct Input {
     medias: Vec<String>,
     data_urls: HashMap<String, String>,
     tool_calls: Option<MessageContentToolCalls>,
+    tool_budget: ToolBudget,
     role: Role,
     rag_name: Option<String>,
     with_session: bool,
@@ -47,6 +48,7 @@ impl Input {
             medias: Default::default(),
             data_urls: Default::default(),
             tool_calls: None,
+            tool_budget: Default::default(),
             role,
             rag_name: None,
             with_session,
@@ -114,6 +116,7 @@ impl Input {
             medias,
             data_urls,
             tool_calls: Default::default(),
+            tool_budget: Default::default(),
             role,
             rag_name: None,
             with_session,
@@ -190,6 +193,7 @@ impl Input {
         }
         self.regenerate = true;
         self.tool_calls = None;
+        self.tool_budget = Default::default();
     }
 
     pub async fn use_embeddings(&mut self, abort_signal: AbortSignal) -> Result<()> {
@@ -210,6 +214,30 @@ impl Input {
     }
 
     pub fn merge_tool_results(mut self, output: String, tool_results: Vec<ToolResult>) -> Self {
+        let model = self.role().model().clone();
+        let input_tokens = self
+            .build_messages()
+            .map(|messages| model.total_tokens(&messages))
+            .unwrap_or_default();
+        let output_tokens = estimate_token_length(&output);
+        let budget_config = self.config.read().tool_budget.clone();
+        if let Some(reason) = self.tool_budget.record_round(
+            &budget_config,
+            &model,
+            input_tokens,
+            output_tokens,
+            &tool_results,
+        ) {
+            warn!("Tool budget exhausted: {reason}");
+            if *IS_STDOUT_TERMINAL {
+                println!(
+                    "{}",
+                    dimmed_text(&format!(
+                        "Tool budget exhausted ({reason}); asking for a final answer without tools."
+                    ))
+                );
+            }
+        }
         match self.tool_calls.as_mut() {
             Some(exist_tool_results) => {
                 exist_tool_results.merge(tool_results, output);
@@ -219,6 +247,11 @@ impl Input {
         self
     }
 
+    /// Set once the turn's tool budget is exhausted; the next round must answer without tools.
+    pub fn tool_budget_exceeded(&self) -> Option<&str> {
+        self.tool_budget.exceeded()
+    }
+
     pub fn create_client(&self) -> Result<Box<dyn Client>> {
         init_client(&self.config, Some(self.role().model().clone()))
     }
@@ -239,7 +272,11 @@ impl Input {
         patch_messages(&mut messages, model);
         model.guard_max_input_tokens(&messages)?;
         let (temperature, top_p) = (self.role().temperature(), self.role().top_p());
-        let functions = self.config.read().select_functions(self.role());
+        // The final answer after an exhausted tool budget is requested without tools.
+        let functions = match self.tool_budget_exceeded() {
+            Some(_) => None,
+            None => self.config.read().select_functions(self.role()),
+        };
         Ok(ChatCompletionsData {
             messages,
             temperature,
@@ -261,6 +298,12 @@ impl Input {
                 MessageContent::ToolCalls(tool_calls.clone()),
             ))
         }
+        if let Some(notice) = self.tool_budget.final_answer_notice() {
+            messages.push(Message::new(
+                MessageRole::User,
+                MessageContent::Text(notice),
+            ));
+        }
         Ok(messages)
     }
 
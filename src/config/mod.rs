@@ -2,6 +2,7 @@ mod agent;
 mod input;
 mod role;
 mod session;
+mod user;
 
 pub use self::agent::{complete_agent_variables, list_agents, Agent, AgentVariables};
 pub use self::input::Input;
@@ -9,18 +10,29 @@ pub use self::role::{
     Role, RoleLike, CODE_ROLE, CREATE_TITLE_ROLE, EXPLAIN_SHELL_ROLE, SHELL_ROLE,
 };
 use self::session::Session;
+pub use self::user::{UserIdentity, UserProfile};
 
 use crate::client::{
     create_client_config, list_client_types, list_models, ClientConfig, MessageContentToolCalls,
     Model, ModelType, ProviderModels, OPENAI_COMPATIBLE_PROVIDERS,
 };
-use crate::function::{FunctionDeclaration, Functions, ToolResult};
+use crate::function::{
+    BuiltinTools, BuiltinToolsConfig, FunctionDeclaration, Functions, ToolBudgetConfig,
+    ToolOutputConfig, ToolResult,
+};
+use crate::gateway::telegram::TelegramConfig;
+use crate::hooks::HookConfig;
 use crate::interactive::{run_interactive_command, split_args_text};
 use crate::mcp::auth::{DeviceCodeStart, OAuthStatus};
 use crate::mcp::{McpAuthConfig, McpManager, McpServerConfig};
+use crate::notify::NotifySinks;
 use crate::rag::Rag;
 use crate::render::{MarkdownRender, RenderOptions};
+use crate::resolver::adapters::{self, LinearAdapter, ProviderAdapter, ProviderSyncConfig};
 use crate::resolver::Resolver;
+use crate::router::RoutingRules;
+use crate::scheduler::JobConfig;
+use crate::telemetry::OtelConfig;
 use crate::utils::*;
 
 use anyhow::{anyhow, bail, Context, Result};
@@ -68,6 +80,7 @@ const CONFIG_FILE_NAME: &str = "config.yaml";
 const ROLES_DIR_NAME: &str = "roles";
 const MACROS_DIR_NAME: &str = "macros";
 const ENV_FILE_NAME: &str = ".env";
+const ROUTING_FILE_NAME: &str = "routing.yaml";
 const MESSAGES_FILE_NAME: &str = "messages.md";
 const SESSIONS_DIR_NAME: &str = "sessions";
 const RAGS_DIR_NAME: &str = "rags";
@@ -139,9 +152,15 @@ pub struct Config {
     pub tool_permissions: Option<ToolPermissions>,
     #[serde(default)]
     pub verbose_tool_calls: bool,
+    pub tool_output: ToolOutputConfig,
+    pub tool_budget: ToolBudgetConfig,
+    pub builtin_tools: BuiltinToolsConfig,
 
     #[serde(default)]
     pub mcp_servers: Vec<McpServerConfig>,
+    /// Providers whose workspaces are discovered from MCP servers for the resolver.
+    #[serde(default)]
+    pub resolver_providers: Vec<ProviderSyncConfig>,
 
     pub interactive_prelude: Option<String>,
     pub cmd_prelude: Option<String>,
@@ -168,6 +187,12 @@ pub struct Config {
     pub right_prompt: Option<String>,
 
     pub serve_addr: Option<String>,
+    pub hooks: IndexMap<String, HookConfig>,
+    pub jobs: IndexMap<String, JobConfig>,
+    pub notify_sinks: NotifySinks,
+    pub telegram: TelegramConfig,
+    pub users: IndexMap<String, UserProfile>,
+    pub otel: OtelConfig,
     pub user_agent: Option<String>,
     pub save_shell_history: bool,
     pub sync_models_url: Option<String>,
@@ -191,6 +216,9 @@ pub struct Config {
     pub working_mode: WorkingMode,
     #[serde(skip)]
     pub last_message: Option<LastMessage>,
+    /// Who the current request runs as; `None` for the local user.
+    #[serde(skip)]
+    pub user: Option<UserIdentity>,
 
     #[serde(skip)]
     pub role: Option<Role>,
@@ -206,6 +234,8 @@ pub struct Config {
     #[serde(skip)]
     pub resolver: Option<Resolver>,
     #[serde(skip)]
+    pub routing_rules: RoutingRules,
+    #[serde(skip)]
     pub current_linear_profile: Option<String>,
 }
 
@@ -233,6 +263,9 @@ impl Default for Config {
             tool_call_permission: None,
             tool_permissions: None,
             verbose_tool_calls: false,
+            tool_output: Default::default(),
+            tool_budget: Default::default(),
+            builtin_tools: Default::default(),
 
             mcp_servers: vec![],
 
@@ -260,12 +293,20 @@ impl Default for Config {
             right_prompt: None,
 
             serve_addr: None,
+            hooks: Default::default(),
+            jobs: Default::default(),
+            notify_sinks: Default::default(),
+            telegram: Default::default(),
+            users: Default::default(),
+            otel: Default::default(),
             user_agent: None,
             save_shell_history: true,
             sync_models_url: None,
 
             clients: vec![],
 
+            resolver_providers: vec![],
+
             macro_flag: false,
             info_flag: false,
             agent_variables: None,
@@ -275,6 +316,7 @@ impl Default for Config {
             mcp_manager: None,
             working_mode: WorkingMode::Cmd,
             last_message: None,
+            user: None,
 
             role: None,
             session: None,
@@ -282,6 +324,7 @@ impl Default for Config {
             agent: None,
             conversation_tool_permissions: HashSet::new(),
             resolver: None,
+            routing_rules: RoutingRules::default(),
             current_linear_profile: None,
         }
     }
@@ -327,13 +370,11 @@ impl Config {
             config.setup_document_loaders();
             config.setup_user_agent();
 
-            match Resolver::load(&Self::config_dir()) {
-                Ok(mut r) => {
-                    r.sync_builtin_profiles(&config.mcp_servers);
-                    config.resolver = Some(r);
-                }
+            match config.load_resolver() {
+                Ok(r) => config.resolver = Some(r),
                 Err(e) => warn!("Resolver: failed to load store: {e}"),
             }
+            config.routing_rules = RoutingRules::load(&Self::routing_file())?;
 
             Ok(())
         };
@@ -344,6 +385,16 @@ impl Config {
         Ok(config)
     }
 
+    /// Load the resolver store and register the built-in and configured providers.
+    pub fn load_resolver(&self) -> Result<Resolver> {
+        let mut resolver = Resolver::load(&Self::config_dir())?;
+        resolver.sync_builtin_profiles(&self.mcp_servers);
+        for adapter in &self.resolver_providers {
+            resolver.register_adapter(adapter, &self.mcp_servers);
+        }
+        Ok(resolver)
+    }
+
     pub fn config_dir() -> PathBuf {
         if let Ok(v) = env::var(get_env_name("config_dir")) {
             PathBuf::from(v)
@@ -415,6 +466,13 @@ impl Config {
         }
     }
 
+    pub fn routing_file() -> PathBuf {
+        match env::var(get_env_name("routing_file")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(ROUTING_FILE_NAME),
+        }
+    }
+
     pub fn messages_file(&self) -> PathBuf {
         match &self.agent {
             None => match env::var(get_env_name("messages_file")) {
@@ -685,6 +743,13 @@ impl Config {
                 format_option_value(&self.tool_call_permission),
             ),
             ("verbose_tool_calls", self.verbose_tool_calls.to_string()),
+            (
+                "tool_output_max_chars",
+                self.tool_output.max_chars.to_string(),
+            ),
+            ("tool_output_redact", self.tool_output.redact.to_string()),
+            ("tool_max_rounds", self.tool_budget.max_rounds.to_string()),
+            ("builtin_tools", self.builtin_tools.enabled.to_string()),
             ("stream", self.stream.to_string()),
             ("save", self.save.to_string()),
             ("keybindings", self.keybindings.clone()),
@@ -694,6 +759,7 @@ impl Config {
             ("theme", format_option_value(&self.theme)),
             ("config_file", display_path(&Self::config_file())),
             ("env_file", display_path(&Self::env_file())),
+            ("routing_file", display_path(&Self::routing_file())),
             ("roles_dir", display_path(&Self::roles_dir())),
             ("sessions_dir", display_path(&self.sessions_dir())),
             ("rags_dir", display_path(&Self::rags_dir())),
@@ -1000,6 +1066,9 @@ impl Config {
     }
 
     pub fn use_role(&mut self, name: &str) -> Result<()> {
+        if let Some(user) = &self.user {
+            user.guard_role(name)?;
+        }
         let role = self.retrieve_role(name)?;
         self.use_role_obj(role)
     }
@@ -1629,6 +1698,9 @@ impl Config {
         if config.read().agent.is_some() {
             bail!("Already in a agent, please run '/exit agent' first to exit the current agent.");
         }
+        if let Some(user) = &config.read().user {
+            user.guard_agent(agent_name)?;
+        }
         let agent = Agent::init(config, agent_name, abort_signal).await?;
         let session = session_name.map(|v| v.to_string()).or_else(|| {
             if config.read().macro_flag {
@@ -1647,6 +1719,86 @@ impl Config {
         Ok(())
     }
 
+    /// Prepare a copy of the config and a role for a turn that nobody can confirm tool calls
+    /// for, such as an alert triage or a scheduled job. Only read-only built-in tools, trusted
+    /// MCP servers and `allowed_tools` may run; everything else is refused instead of prompting.
+    pub async fn init_unattended(
+        config: &GlobalConfig,
+        role: Option<&str>,
+        agent: Option<&str>,
+        use_tools: Option<&str>,
+        allowed_tools: &[String],
+        abort_signal: AbortSignal,
+    ) -> Result<(GlobalConfig, Role)> {
+        let config: GlobalConfig = Arc::new(RwLock::new(config.read().clone()));
+        if let Some(agent) = agent {
+            Config::use_agent(&config, agent, None, abort_signal).await?;
+        } else if let Some(role) = role {
+            config.write().use_role(role)?;
+        }
+
+        let (read_only, denied) = {
+            let cfg = config.read();
+            let mut read_only = vec![];
+            let mut denied = cfg
+                .tool_permissions
+                .as_ref()
+                .and_then(|v| v.denied.clone())
+                .unwrap_or_default();
+            for (name, tool) in cfg.functions.builtin_tools().iter() {
+                if tool.read_only() {
+                    read_only.push(name.to_string());
+                } else if !allowed_tools.iter().any(|v| v == name) {
+                    denied.push(name.to_string());
+                }
+            }
+            (read_only, denied)
+        };
+        // Read-only built-ins are allowed by name: the "never" default below would
+        // otherwise refuse them too.
+        let mut allowed = allowed_tools.to_vec();
+        allowed.extend(read_only.iter().cloned());
+        let tool_permissions = ToolPermissions {
+            allowed: Some(allowed),
+            denied: Some(denied),
+            ask: None,
+        };
+        {
+            let mut cfg = config.write();
+            cfg.tool_call_permission = Some("never".into());
+            cfg.tool_permissions = Some(tool_permissions.clone());
+        }
+
+        let mut role = config.read().extract_role();
+        if let Some(use_tools) = use_tools {
+            role.set_use_tools(Some(use_tools.to_string()));
+        } else if role.use_tools().is_none() && !read_only.is_empty() {
+            role.set_use_tools(Some(read_only.join(",")));
+        }
+        role.set_tool_call_permission(Some("never".into()));
+        role.set_tool_permissions(Some(tool_permissions));
+        Ok((config, role))
+    }
+
+    /// API keys are required by the HTTP API once any user profile defines one.
+    pub fn requires_api_key(&self) -> bool {
+        self.users.values().any(|v| !v.api_keys.is_empty())
+    }
+
+    pub fn user_by_api_key(&self, key: &str) -> Option<UserIdentity> {
+        self.users
+            .iter()
+            .find(|(_, v)| v.api_keys.iter().any(|v| v == key))
+            .map(|(name, v)| UserIdentity::new(name, v))
+    }
+
+    pub fn user_by_telegram_id(&self, id: i64) -> Option<UserIdentity> {
+        self.users
+            .iter()
+            .find(|(_, v)| v.telegram_user_ids.contains(&id))
+            .map(|(name, v)| UserIdentity::new(name, v))
+    }
+
     pub fn agent_info(&self) -> Result<String> {
         if let Some(agent) = &self.agent {
             agent.export()
@@ -1835,6 +1987,12 @@ impl Config {
                 functions = agent_functions;
             }
         };
+        if let Some(user) = &self.user {
+            functions.retain(|v| {
+                let read_only = self.functions.builtin_tool(&v.name).map(|v| v.read_only());
+                user.allows_tool(&v.name, read_only)
+            });
+        }
         if functions.is_empty() {
             None
         } else {
@@ -1897,6 +2055,7 @@ impl Config {
                 ".rag" => map_completion_values(Self::list_rags()),
                 ".agent" => map_completion_values(list_agents()),
                 ".macro" => map_completion_values(Self::list_macros()),
+                ".notify" => map_completion_values(self.notify_sinks.keys().collect()),
                 ".starter" => match &self.agent {
                     Some(agent) => agent
                         .conversation_staters()
@@ -2508,6 +2667,21 @@ impl Config {
         if let Some(Some(v)) = read_env_bool(&get_env_name("verbose_tool_calls")) {
             self.verbose_tool_calls = v;
         }
+        if let Ok(v) = env::var(get_env_name("tool_output")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.tool_output = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("tool_budget")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.tool_budget = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("builtin_tools")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.builtin_tools = v;
+            }
+        }
 
         if let Some(v) = read_env_value::<String>(&get_env_name("interactive_prelude")) {
             self.interactive_prelude = v;
@@ -2586,6 +2760,36 @@ impl Config {
         if let Some(v) = read_env_value::<String>(&get_env_name("serve_addr")) {
             self.serve_addr = v;
         }
+        if let Ok(v) = env::var(get_env_name("hooks")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.hooks = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("jobs")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.jobs = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("notify_sinks")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.notify_sinks = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("telegram")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.telegram = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("users")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.users = v;
+            }
+        }
+        if let Ok(v) = env::var(get_env_name("otel")) {
+            if let Ok(v) = serde_json::from_str(&v) {
+                self.otel = v;
+            }
+        }
         if let Some(v) = read_env_value::<String>(&get_env_name("user_agent")) {
             self.user_agent = v;
         }
@@ -2603,7 +2807,8 @@ impl Config {
         } else {
             None
         };
-        self.functions = Functions::init(&Self::functions_file(), mcp_tools)?;
+        self.functions = Functions::init(&Self::functions_file(), mcp_tools)?
+            .with_builtin_tools(BuiltinTools::new(&self.builtin_tools, &self.notify_sinks));
         Ok(())
     }
 
@@ -2646,6 +2851,9 @@ impl Config {
     }
 
     pub async fn mcp_connect_server(config: &GlobalConfig, server_name: &str) -> Result<()> {
+        if let Some(user) = &config.read().user {
+            user.guard_mcp_server(server_name)?;
+        }
         let manager = { config.read().mcp_manager.clone() };
         match manager {
             Some(manager) => manager.connect(server_name).await,
@@ -2654,6 +2862,9 @@ impl Config {
     }
 
     pub async fn mcp_disconnect_server(config: &GlobalConfig, server_name: &str) -> Result<()> {
+        if let Some(user) = &config.read().user {
+            user.guard_mcp_server(server_name)?;
+        }
         let manager = { config.read().mcp_manager.clone() };
         match manager {
             Some(manager) => manager.disconnect(server_name).await,
@@ -2870,58 +3081,71 @@ impl Config {
         config: &GlobalConfig,
         server_name: &str,
     ) -> Result<Vec<String>> {
-        log::info!("Fetching Linear team aliases for '{}'", server_name);
+        Self::sync_provider_workspaces(config, server_name, &LinearAdapter).await
+    }
+
+    /// List `server_name`'s workspaces with `adapter` and add them to the resolver,
+    /// targeting that server. Returns the workspace names.
+    pub async fn sync_provider_workspaces(
+        config: &GlobalConfig,
+        server_name: &str,
+        adapter: &dyn ProviderAdapter,
+    ) -> Result<Vec<String>> {
+        let provider = adapter.provider();
+        log::info!("Fetching {} workspaces for '{}'", provider, server_name);
         let manager = config
             .read()
             .mcp_manager
             .clone()
             .ok_or_else(|| anyhow!("MCP is not configured"))?;
-        let tool_name = format!("mcp__{}__list_teams", server_name);
+        let tool_name = format!("mcp__{}__{}", server_name, adapter.list_tool());
         let raw = manager
-            .call_tool(&tool_name, Value::Object(Default::default()))
+            .call_tool(&tool_name, adapter.list_arguments())
             .await?;
-        let teams = extract_linear_teams(&raw)?;
-        if teams.is_empty() {
-            log::info!("No Linear team aliases returned for '{}'", server_name);
-            return Ok(vec![]);
-        }
+        let workspaces = adapters::discover(adapter, &raw)?;
 
         let mut resolver = config
             .read()
             .resolver
             .clone()
             .ok_or_else(|| anyhow!("Resolver not initialized"))?;
-        let mut learned = Vec::new();
-
-        for team in teams {
-            let canonical = team
-                .key
-                .clone()
-                .unwrap_or_else(|| team.name.to_ascii_uppercase());
-            let mut aliases = vec![canonical.to_ascii_lowercase()];
-            if !team.name.eq_ignore_ascii_case(&canonical) {
-                aliases.push(team.name.to_ascii_lowercase());
-            }
-            aliases.sort();
-            aliases.dedup();
-
-            resolver.add_workspace("linear", &canonical, Some(server_name), None)?;
-            for alias in aliases {
-                resolver.add_workspace("linear", &canonical, Some(server_name), Some(&alias))?;
-            }
-            learned.push(canonical);
-        }
-
-        learned.sort();
-        learned.dedup();
+        let discovered = resolver.add_discovered(provider, server_name, &workspaces)?;
         log::info!(
-            "Learned {} Linear team aliases for '{}'",
-            learned.len(),
+            "Discovered {} {} workspaces for '{}'",
+            discovered.len(),
+            provider,
             server_name
         );
         resolver.save()?;
         config.write().resolver = Some(resolver);
-        Ok(learned)
+        Ok(discovered)
+    }
+
+    /// Sync workspaces from every connected MCP server (or just `server`) that the
+    /// configured `resolver_providers` match. Returns `(provider, server, workspaces)`.
+    pub async fn sync_resolver_providers(
+        config: &GlobalConfig,
+        server: Option<&str>,
+    ) -> Result<Vec<(String, String, Vec<String>)>> {
+        let (manager, providers) = {
+            let cfg = config.read();
+            let manager = cfg
+                .mcp_manager
+                .clone()
+                .ok_or_else(|| anyhow!("MCP is not configured"))?;
+            (manager, cfg.resolver_providers.clone())
+        };
+        let mut synced = vec![];
+        for (server_name, connected, _) in manager.list_servers().await {
+            if !connected || server.is_some_and(|v| v != server_name) {
+                continue;
+            }
+            for adapter in providers.iter().filter(|v| v.matches_server(&server_name)) {
+                let learned = Self::sync_provider_workspaces(config, &server_name, adapter).await?;
+                synced.push((adapter.provider.clone(), server_name.clone(), learned));
+            }
+        }
+        Ok(synced)
     }
 
     /// Refresh the in-memory function declarations (local functions + currently connected MCP tools).
@@ -2935,7 +3159,12 @@ impl Config {
             None
         };
 
-        let new_functions = Functions::init(&Self::functions_file(), mcp_tools)?;
+        let builtin_tools = {
+            let cfg = config.read();
+            BuiltinTools::new(&cfg.builtin_tools, &cfg.notify_sinks)
+        };
+        let new_functions =
+            Functions::init(&Self::functions_file(), mcp_tools)?.with_builtin_tools(builtin_tools);
         config.write().functions = new_functions;
         Ok(())
     }
@@ -3012,13 +3241,24 @@ impl WorkingMode {
     }
 }
 
-#[async_recursion::async_recursion]
 pub async fn macro_execute(
     config: &GlobalConfig,
     name: &str,
     args: Option<&str>,
     abort_signal: AbortSignal,
 ) -> Result<()> {
+    macro_run(config, name, args, abort_signal).await?;
+    Ok(())
+}
+
+/// Run a macro and return the config its steps ran with, so callers can read `last_message`.
+#[async_recursion::async_recursion]
+pub async fn macro_run(
+    config: &GlobalConfig,
+    name: &str,
+    args: Option<&str>,
+    abort_signal: AbortSignal,
+) -> Result<GlobalConfig> {
     let macro_value = Config::load_macro(name)?;
     let (mut new_args, text) = split_args_text(args.unwrap_or_default(), cfg!(windows));
     if !text.is_empty() {
@@ -3046,7 +3286,7 @@ pub async fn macro_execute(
         println!(">> {}", multiline_text(&command));
         run_interactive_command(&config, abort_signal.clone(), &command).await?;
     }
-    Ok(())
+    Ok(config)
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -3317,94 +3557,6 @@ fn persist_env_var(env_path: &Path, key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct LinearTeamAlias {
-    key: Option<String>,
-    name: String,
-}
-
-fn extract_linear_teams(raw: &Value) -> Result<Vec<LinearTeamAlias>> {
-    if let Some(value) = raw.get("structuredContent") {
-        let teams = extract_linear_teams_from_value(value);
-        if !teams.is_empty() {
-            return Ok(teams);
-        }
-    }
-
-    if let Some(content) = raw.get("content").and_then(|v| v.as_array()) {
-        for item in content {
-            if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
-                if let Ok(parsed) = serde_json::from_str::<Value>(text) {
-                    let teams = extract_linear_teams_from_value(&parsed);
-                    if !teams.is_empty() {
-                        return Ok(teams);
-                    }
-                }
-            }
-        }
-    }
-
-    let teams = extract_linear_teams_from_value(raw);
-    if teams.is_empty() {
-        bail!("Unable to parse teams from Linear MCP response");
-    }
-    Ok(teams)
-}
-
-fn extract_linear_teams_from_value(value: &Value) -> Vec<LinearTeamAlias> {
-    let mut teams = Vec::new();
-    collect_linear_team_aliases(value, &mut teams);
-    teams.sort_by(|a, b| {
-        a.key
-            .as_deref()
-            .unwrap_or(a.name.as_str())
-            .cmp(b.key.as_deref().unwrap_or(b.name.as_str()))
-    });
-    teams.dedup_by(|a, b| a.key == b.key && a.name.eq_ignore_ascii_case(&b.name));
-    teams
-}
-
-fn collect_linear_team_aliases(value: &Value, teams: &mut Vec<LinearTeamAlias>) {
-    if let Some(team) = parse_linear_team_alias(value) {
-        teams.push(team);
-        return;
-    }
-
-    match value {
-        Value::Array(items) => {
-            for item in items {
-                collect_linear_team_aliases(item, teams);
-            }
-        }
-        Value::Object(map) => {
-            for nested in map.values() {
-                collect_linear_team_aliases(nested, teams);
-            }
-        }
-        _ => {}
-    }
-}
-
-fn parse_linear_team_alias(value: &Value) -> Option<LinearTeamAlias> {
-    let obj = value.as_object()?;
-    let name = obj.get("name")?.as_str()?.trim();
-    if name.is_empty() {
-        return None;
-    }
-
-    let key = obj
-        .get("key")
-        .and_then(|v| v.as_str())
-        .map(str::trim)
-        .filter(|v| !v.is_empty())
-        .map(|v| v.to_ascii_uppercase());
-
-    Some(LinearTeamAlias {
-        key,
-        name: name.to_string(),
-    })
-}
-
 pub(crate) fn ensure_parent_exists(path: &Path) -> Result<()> {
     if path.exists() {
         return Ok(());
@@ -0,0 +1,202 @@
+use super::ToolPermissions;
+
+use crate::function::glob_match;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// What one caller of the HTTP API or the chat gateway may do.
+///
+/// Callers are identified by an API key (`Authorization: Bearer <key>`) or a gateway
+/// user id. List fields accept exact names or `*` globs; an unset list allows everything.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UserProfile {
+    pub api_keys: Vec<String>,
+    pub telegram_user_ids: Vec<i64>,
+    /// Roles the user may use.
+    pub roles: Option<Vec<String>>,
+    /// Agents the user may use.
+    pub agents: Option<Vec<String>>,
+    /// Deny every built-in tool that changes the host.
+    pub read_only: bool,
+    /// Merged into the role or global `tool_permissions`: lists are combined, so the
+    /// profile can add denials but cannot lift them.
+    pub tool_permissions: Option<ToolPermissions>,
+    /// Model used when the caller does not pick one.
+    pub model: Option<String>,
+    /// MCP servers whose tools the user can see and call.
+    pub mcp_servers: Option<Vec<String>>,
+}
+
+/// The profile a request runs as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserIdentity {
+    pub name: String,
+    pub profile: UserProfile,
+}
+
+impl UserIdentity {
+    pub fn new(name: &str, profile: &UserProfile) -> Self {
+        Self {
+            name: name.to_string(),
+            profile: profile.clone(),
+        }
+    }
+
+    pub fn allows_role(&self, name: &str) -> bool {
+        allows(&self.profile.roles, name)
+    }
+
+    pub fn allows_agent(&self, name: &str) -> bool {
+        allows(&self.profile.agents, name)
+    }
+
+    pub fn allows_mcp_server(&self, name: &str) -> bool {
+        allows(&self.profile.mcp_servers, name)
+    }
+
+    /// Whether the user may see and call a tool at all, before any other permission check.
+    /// `read_only` is the built-in tool's flag, if the tool is built in.
+    pub fn allows_tool(&self, tool_name: &str, read_only: Option<bool>) -> bool {
+        if self.profile.read_only && read_only == Some(false) {
+            return false;
+        }
+        if let Some(server) = crate::mcp::extract_server_name(tool_name) {
+            if !self.allows_mcp_server(&server) {
+                return false;
+            }
+        }
+        let denied = self
+            .profile
+            .tool_permissions
+            .as_ref()
+            .and_then(|v| v.denied.as_ref());
+        !denied.is_some_and(|denied| denied.iter().any(|v| glob_match(tool_name, v)))
+    }
+
+    /// Combine `base` permissions with the profile overlay.
+    pub fn merge_tool_permissions(
+        &self,
+        base: Option<&ToolPermissions>,
+    ) -> Option<ToolPermissions> {
+        let Some(overlay) = &self.profile.tool_permissions else {
+            return base.cloned();
+        };
+        let Some(base) = base else {
+            return Some(overlay.clone());
+        };
+        let merge = |a: &Option<Vec<String>>, b: &Option<Vec<String>>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(
+                a.iter()
+                    .chain(b.iter())
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        Some(ToolPermissions {
+            allowed: merge(&base.allowed, &overlay.allowed),
+            denied: merge(&base.denied, &overlay.denied),
+            ask: merge(&base.ask, &overlay.ask),
+        })
+    }
+
+    pub fn guard_role(&self, name: &str) -> Result<()> {
+        if !self.allows_role(name) {
+            bail!(
+                "User '{}' is not allowed to use the role '{name}'",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn guard_mcp_server(&self, name: &str) -> Result<()> {
+        if !self.allows_mcp_server(name) {
+            bail!(
+                "User '{}' is not allowed to use the MCP server '{name}'",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn guard_agent(&self, name: &str) -> Result<()> {
+        if !self.allows_agent(name) {
+            bail!(
+                "User '{}' is not allowed to use the agent '{name}'",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+fn allows(patterns: &Option<Vec<String>>, name: &str) -> bool {
+    match patterns {
+        Some(patterns) => patterns.iter().any(|v| glob_match(name, v)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer() -> UserIdentity {
+        let profile: UserProfile = serde_yaml::from_str(
+            r#"
+api_keys: [k1]
+roles: [ops, "read-*"]
+read_only: true
+tool_permissions:
+  denied: [fio_file_tail]
+  ask: [web_search]
+mcp_servers: [github]
+"#,
+        )
+        .unwrap();
+        UserIdentity::new("viewer", &profile)
+    }
+
+    #[test]
+    fn test_allows() {
+        let user = viewer();
+        assert!(user.allows_role("ops") && user.allows_role("read-logs"));
+        assert!(!user.allows_role("admin"));
+        assert!(user.guard_role("admin").is_err());
+        assert!(user.allows_agent("anything"));
+
+        assert!(user.allows_tool("fio_system_info", Some(true)));
+        assert!(!user.allows_tool("fio_service_restart", Some(false)));
+        assert!(!user.allows_tool("fio_file_tail", Some(true)));
+        assert!(user.allows_tool("mcp__github__list_issues", None));
+        assert!(!user.allows_tool("mcp__linear__create_issue", None));
+    }
+
+    #[test]
+    fn test_merge_tool_permissions() {
+        let user = viewer();
+        assert_eq!(
+            user.merge_tool_permissions(None),
+            user.profile.tool_permissions
+        );
+        let base = ToolPermissions {
+            allowed: Some(vec!["fs_cat".into()]),
+            denied: Some(vec!["fs_rm".into()]),
+            ask: None,
+        };
+        let merged = user.merge_tool_permissions(Some(&base)).unwrap();
+        assert_eq!(merged.allowed, Some(vec!["fs_cat".into()]));
+        assert_eq!(
+            merged.denied,
+            Some(vec!["fs_rm".into(), "fio_file_tail".into()])
+        );
+        assert_eq!(merged.ask, Some(vec!["web_search".into()]));
+
+        let plain = UserIdentity::new("admin", &UserProfile::default());
+        assert_eq!(plain.merge_tool_permissions(Some(&base)), Some(base));
+    }
+}
@@ -1,10 +1,17 @@
+mod budget;
+mod builtin;
+mod output;
 mod permission;
 
-pub use permission::ToolPermission;
+pub use budget::{ToolBudget, ToolBudgetConfig};
+pub use builtin::{BuiltinTool, BuiltinTools, BuiltinToolsConfig};
+pub use output::{ToolOutputConfig, ToolOutputFilter};
+pub use permission::{glob_match, ToolPermission};
 
 use crate::{
     config::{Agent, Config, GlobalConfig, ToolPermissions},
     mcp,
+    telemetry::{in_span, SpanKind},
     utils::*,
 };
 
@@ -16,6 +23,8 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 
 #[cfg(windows)]
@@ -39,12 +48,28 @@ pub async fn eval_tool_calls(
     }
     let mut permission_checker =
         ToolPermission::new_with_role(config, role_tool_call_permission, role_tool_permissions);
+    let output_filter = ToolOutputFilter::new(&config.read().tool_output);
     let mut is_all_null = true;
     for call in calls {
         let permitted = permission_checker.check_permission(&call).await?;
         let mut result = if permitted {
-            call.eval_async(config).await?
+            let start = Instant::now();
+            let ret = call.eval_async(config).await;
+            let duration_ms = start.elapsed().as_millis() as u64;
+            let outcome = if ret.is_ok() { "ok" } else { "error" };
+            info!(
+                tool = call.name.as_str(), permission = "allowed", duration_ms = duration_ms,
+                outcome = outcome;
+                "tool {} {outcome} in {duration_ms}ms", call.name
+            );
+            crate::metrics::record_tool_call(&call.name, outcome, "allowed");
+            ret?
         } else {
+            info!(
+                tool = call.name.as_str(), permission = "denied", outcome = "skipped";
+                "tool {} denied", call.name
+            );
+            crate::metrics::record_tool_call(&call.name, "skipped", "denied");
             json!({
                 "error": "Permission denied",
                 "tool": call.name,
@@ -55,6 +80,7 @@ pub async fn eval_tool_calls(
             result = json!("DONE");
         } else {
             is_all_null = false;
+            result = output_filter.process(&call.name, result);
         }
         output.push(ToolResult::new(call, result));
     }
@@ -79,6 +105,7 @@ impl ToolResult {
 #[derive(Debug, Clone, Default)]
 pub struct Functions {
     declarations: Vec<FunctionDeclaration>,
+    builtin_tools: BuiltinTools,
     mcp_declarations: Vec<FunctionDeclaration>,
 }
 
@@ -102,6 +129,7 @@ impl Functions {
 
         Ok(Self {
             declarations,
+            builtin_tools: Default::default(),
             mcp_declarations: mcp_tools.unwrap_or_default(),
         })
     }
@@ -109,32 +137,47 @@ impl Functions {
     pub fn init_from_mcp(mcp_tools: Option<Vec<FunctionDeclaration>>) -> Self {
         Self {
             declarations: vec![],
+            builtin_tools: Default::default(),
             mcp_declarations: mcp_tools.unwrap_or_default(),
         }
     }
 
+    pub fn with_builtin_tools(mut self, builtin_tools: BuiltinTools) -> Self {
+        self.builtin_tools = builtin_tools;
+        self
+    }
+
     pub fn find(&self, name: &str) -> Option<&FunctionDeclaration> {
-        self.declarations
-            .iter()
-            .chain(self.mcp_declarations.iter())
-            .find(|v| v.name == name)
+        self.all_declarations().find(|v| v.name == name)
     }
 
     pub fn contains(&self, name: &str) -> bool {
-        self.declarations.iter().any(|v| v.name == name)
-            || self.mcp_declarations.iter().any(|v| v.name == name)
+        self.all_declarations().any(|v| v.name == name)
     }
 
     pub fn declarations(&self) -> Vec<FunctionDeclaration> {
-        self.declarations
-            .iter()
-            .chain(self.mcp_declarations.iter())
-            .cloned()
-            .collect()
+        self.all_declarations().cloned().collect()
+    }
+
+    pub fn builtin_tool(&self, name: &str) -> Option<Arc<dyn BuiltinTool>> {
+        self.builtin_tools.get(name)
+    }
+
+    pub fn builtin_tools(&self) -> &BuiltinTools {
+        &self.builtin_tools
     }
 
     pub fn is_empty(&self) -> bool {
-        self.declarations.is_empty() && self.mcp_declarations.is_empty()
+        self.declarations.is_empty()
+            && self.builtin_tools.declarations().is_empty()
+            && self.mcp_declarations.is_empty()
+    }
+
+    fn all_declarations(&self) -> impl Iterator<Item = &FunctionDeclaration> {
+        self.declarations
+            .iter()
+            .chain(self.builtin_tools.declarations())
+            .chain(self.mcp_declarations.iter())
     }
 }
 
@@ -214,9 +257,23 @@ impl ToolCall {
     }
 
     pub async fn eval_async(&self, config: &GlobalConfig) -> Result<Value> {
+        let attributes = vec![("fiochat.tool", self.name.as_str().into())];
+        let fut = self.eval_async_inner(config);
+        in_span("tool_call", SpanKind::Internal, attributes, fut).await
+    }
+
+    async fn eval_async_inner(&self, config: &GlobalConfig) -> Result<Value> {
         if mcp::is_mcp_tool(&self.name) {
             return self.eval_mcp_async(config).await;
         }
+        let builtin_tool = config.read().functions.builtin_tool(&self.name);
+        if let Some(tool) = builtin_tool {
+            let prompt = format!("Call {} {}", self.name, self.arguments);
+            if *IS_STDOUT_TERMINAL {
+                println!("{}", dimmed_text(&prompt));
+            }
+            return tool.call(self.json_arguments()?).await;
+        }
 
         let (call_name, cmd_name, mut cmd_args, envs) = match &config.read().agent {
             Some(agent) => self.extract_call_config_from_agent(config, agent)?,
@@ -255,30 +312,36 @@ impl ToolCall {
     }
 
     async fn eval_mcp_async(&self, config: &GlobalConfig) -> Result<Value> {
-        let manager = config
-            .read()
-            .mcp_manager
-            .clone()
-            .ok_or_else(|| anyhow!("MCP is not configured"))?;
+        let (manager, user) = {
+            let cfg = config.read();
+            (cfg.mcp_manager.clone(), cfg.user.clone())
+        };
+        let manager = manager.ok_or_else(|| anyhow!("MCP is not configured"))?;
 
-        let json_data = if self.arguments.is_object() {
-            self.arguments.clone()
+        manager
+            .call_tool_visible(&self.name, self.json_arguments()?, |server| {
+                user.as_ref().is_none_or(|v| v.allows_mcp_server(server))
+            })
+            .await
+    }
+
+    fn json_arguments(&self) -> Result<Value> {
+        if self.arguments.is_object() {
+            Ok(self.arguments.clone())
         } else if let Some(arguments) = self.arguments.as_str() {
             serde_json::from_str(arguments).map_err(|_| {
                 anyhow!(
                     "The call '{}' has invalid arguments: {arguments}",
                     self.name
                 )
-            })?
+            })
         } else {
             bail!(
                 "The call '{}' has invalid arguments: {}",
                 self.name,
                 self.arguments
             );
-        };
-
-        manager.call_tool(&self.name, json_data).await
+        }
     }
 
     fn extract_call_config_from_agent(
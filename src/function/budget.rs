@@ -0,0 +1,182 @@
+use super::ToolResult;
+use crate::client::Model;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Limits on how long a single turn may keep calling tools.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ToolBudgetConfig {
+    /// Maximum tool-calling rounds per turn.
+    pub max_rounds: usize,
+    /// How many times the exact same call (name and arguments) may run per turn.
+    pub max_repeated_calls: usize,
+    /// Estimated token budget (prompt + completion, summed over rounds) per turn.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Estimated cost budget in USD per turn, based on the model's pricing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+}
+
+impl Default for ToolBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_rounds: 16,
+            max_repeated_calls: 3,
+            max_tokens: None,
+            max_cost: None,
+        }
+    }
+}
+
+/// Per-turn bookkeeping of tool-calling rounds.
+#[derive(Debug, Clone, Default)]
+pub struct ToolBudget {
+    rounds: usize,
+    calls: HashMap<String, usize>,
+    tokens: usize,
+    cost: f64,
+    exceeded: Option<String>,
+}
+
+impl ToolBudget {
+    /// Record a completed tool round. Returns the reason when this round exhausted the budget.
+    pub fn record_round(
+        &mut self,
+        config: &ToolBudgetConfig,
+        model: &Model,
+        input_tokens: usize,
+        output_tokens: usize,
+        tool_results: &[ToolResult],
+    ) -> Option<&str> {
+        if self.exceeded.is_some() {
+            return None;
+        }
+        self.rounds += 1;
+        self.tokens += input_tokens + output_tokens;
+        let data = model.data();
+        self.cost += input_tokens as f64 * data.input_price.unwrap_or_default() / 1_000_000.0
+            + output_tokens as f64 * data.output_price.unwrap_or_default() / 1_000_000.0;
+
+        let mut repeated = None;
+        for result in tool_results {
+            let count = self.calls.entry(call_signature(result)).or_default();
+            *count += 1;
+            if *count >= config.max_repeated_calls.max(1) && repeated.is_none() {
+                repeated = Some(result.call.name.clone());
+            }
+        }
+
+        let reason = if let Some(name) = repeated {
+            Some(format!(
+                "the tool '{name}' was called repeatedly with identical arguments"
+            ))
+        } else if self.rounds >= config.max_rounds.max(1) {
+            Some(format!("reached the limit of {} tool rounds", self.rounds))
+        } else if config.max_tokens.is_some_and(|max| self.tokens >= max) {
+            Some(format!("used ~{} tokens of the turn budget", self.tokens))
+        } else if config.max_cost.is_some_and(|max| self.cost >= max) {
+            Some(format!("spent ~${:.4} of the turn budget", self.cost))
+        } else {
+            None
+        };
+        self.exceeded = reason;
+        self.exceeded.as_deref()
+    }
+
+    pub fn exceeded(&self) -> Option<&str> {
+        self.exceeded.as_deref()
+    }
+
+    /// The instruction given to the model for its final, tool-free answer.
+    pub fn final_answer_notice(&self) -> Option<String> {
+        let reason = self.exceeded.as_deref()?;
+        Some(format!(
+            "[Tool budget exhausted: {reason}. Tool calls are disabled for the rest of this turn. \
+             Answer now using the information gathered so far, and say what remains unfinished.]"
+        ))
+    }
+}
+
+fn call_signature(result: &ToolResult) -> String {
+    let arguments = match &result.call.arguments {
+        Value::String(text) => serde_json::from_str::<Value>(text)
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| text.clone()),
+        other => other.to_string(),
+    };
+    format!("{}:{arguments}", result.call.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function::ToolCall;
+    use serde_json::json;
+
+    fn result(name: &str, arguments: Value) -> ToolResult {
+        ToolResult::new(ToolCall::new(name.into(), arguments, None), json!("ok"))
+    }
+
+    #[test]
+    fn test_max_rounds() {
+        let config = ToolBudgetConfig {
+            max_rounds: 2,
+            ..Default::default()
+        };
+        let model = Model::default();
+        let mut budget = ToolBudget::default();
+        assert!(budget
+            .record_round(&config, &model, 10, 10, &[result("a", json!({"n": 1}))])
+            .is_none());
+        let reason = budget
+            .record_round(&config, &model, 10, 10, &[result("a", json!({"n": 2}))])
+            .unwrap();
+        assert!(reason.contains("2 tool rounds"));
+        assert!(budget.final_answer_notice().unwrap().contains("disabled"));
+    }
+
+    #[test]
+    fn test_repeated_identical_calls() {
+        let config = ToolBudgetConfig {
+            max_repeated_calls: 2,
+            ..Default::default()
+        };
+        let model = Model::default();
+        let mut budget = ToolBudget::default();
+        assert!(budget
+            .record_round(&config, &model, 0, 0, &[result("ls", json!({"path": "/"}))])
+            .is_none());
+        // String-encoded arguments are normalized before comparison.
+        let reason = budget
+            .record_round(
+                &config,
+                &model,
+                0,
+                0,
+                &[result("ls", json!(r#"{"path":"/"}"#))],
+            )
+            .unwrap();
+        assert!(reason.contains("'ls'"));
+    }
+
+    #[test]
+    fn test_token_budget() {
+        let config = ToolBudgetConfig {
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        let model = Model::default();
+        let mut budget = ToolBudget::default();
+        assert!(budget
+            .record_round(&config, &model, 400, 100, &[result("a", json!({}))])
+            .is_none());
+        assert!(budget
+            .record_round(&config, &model, 400, 100, &[result("b", json!({}))])
+            .is_some());
+        assert!(budget.exceeded().is_some());
+    }
+}
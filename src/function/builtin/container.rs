@@ -0,0 +1,780 @@
+use super::{new_declaration, optional_str, optional_usize, required_str, BuiltinTool};
+use crate::function::FunctionDeclaration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_LOG_LINES: usize = 100;
+const MAX_LOG_LINES: usize = 2000;
+const DEFAULT_STOP_TIMEOUT: usize = 10;
+/// Leaves the engine time to answer within `REQUEST_TIMEOUT` after a stop timeout.
+const MAX_STOP_TIMEOUT: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerEngine {
+    #[default]
+    Docker,
+    Podman,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ContainerConfig {
+    /// `docker` or `podman`; only decides the default socket, both speak the Docker Engine API.
+    pub engine: ContainerEngine,
+    /// Unix socket of the engine API. Defaults to the engine's standard socket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+}
+
+impl ContainerConfig {
+    pub fn socket_path(&self) -> PathBuf {
+        if let Some(socket) = &self.socket {
+            return PathBuf::from(socket);
+        }
+        match self.engine {
+            ContainerEngine::Docker => PathBuf::from("/var/run/docker.sock"),
+            ContainerEngine::Podman => {
+                // Rootless podman listens in the user's runtime dir; fall back to the system socket.
+                let rootless = std::env::var("XDG_RUNTIME_DIR")
+                    .ok()
+                    .map(|dir| Path::new(&dir).join("podman/podman.sock"))
+                    .filter(|path| path.exists());
+                rootless.unwrap_or_else(|| PathBuf::from("/run/podman/podman.sock"))
+            }
+        }
+    }
+}
+
+/// Minimal Docker Engine API client over a unix socket.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    socket: PathBuf,
+}
+
+impl Engine {
+    pub fn new(config: &ContainerConfig) -> Self {
+        Self {
+            socket: config.socket_path(),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.socket.exists()
+    }
+
+    async fn request(&self, method: Method, path: &str) -> Result<(StatusCode, Bytes)> {
+        tokio::time::timeout(REQUEST_TIMEOUT, self.request_inner(method, path))
+            .await
+            .map_err(|_| anyhow!("Container engine request timed out: {path}"))?
+    }
+
+    #[cfg(not(unix))]
+    async fn request_inner(&self, _method: Method, _path: &str) -> Result<(StatusCode, Bytes)> {
+        bail!("Container tools need a unix socket, which this platform does not support")
+    }
+
+    #[cfg(unix)]
+    async fn request_inner(&self, method: Method, path: &str) -> Result<(StatusCode, Bytes)> {
+        use http::Request;
+        use http_body_util::{BodyExt, Empty};
+        use hyper_util::rt::TokioIo;
+
+        let stream = tokio::net::UnixStream::connect(&self.socket)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to connect to the container engine at {}",
+                    self.socket.display()
+                )
+            })?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header("Host", "localhost")
+            .body(Empty::<Bytes>::new())?;
+        let response = sender.send_request(request).await?;
+        let status = response.status();
+        let body = response.into_body().collect().await?.to_bytes();
+        Ok((status, body))
+    }
+
+    async fn get_json(&self, path: &str) -> Result<Value> {
+        let (status, body) = self.request(Method::GET, path).await?;
+        check_status(status, &body)?;
+        serde_json::from_slice(&body).context("Invalid response from the container engine")
+    }
+}
+
+fn check_status(status: StatusCode, body: &[u8]) -> Result<()> {
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        return Ok(());
+    }
+    // Engine errors look like {"message": "..."}.
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|v| v["message"].as_str().map(|v| v.to_string()))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string());
+    bail!("Container engine returned {status}: {message}")
+}
+
+pub struct ContainerList(pub Engine);
+
+#[async_trait]
+impl BuiltinTool for ContainerList {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_container_list",
+            "List containers with their image, state and published ports.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "all": {
+                        "type": "boolean",
+                        "description": "Include stopped containers",
+                        "default": false,
+                    },
+                },
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let all = args.get("all").and_then(|v| v.as_bool()).unwrap_or(false);
+        let list = self
+            .0
+            .get_json(&format!("/containers/json?all={all}"))
+            .await?;
+        let containers: Vec<Value> = list
+            .as_array()
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|c| {
+                let names: Vec<&str> = c["Names"]
+                    .as_array()
+                    .map(|v| v.as_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|v| v.trim_start_matches('/'))
+                    .collect();
+                let ports: Vec<String> = c["Ports"]
+                    .as_array()
+                    .map(|v| v.as_slice())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|p| match p["PublicPort"].as_u64() {
+                        Some(public) => format!(
+                            "{}:{}->{}/{}",
+                            p["IP"].as_str().unwrap_or("0.0.0.0"),
+                            public,
+                            p["PrivatePort"],
+                            p["Type"].as_str().unwrap_or("tcp")
+                        ),
+                        None => format!(
+                            "{}/{}",
+                            p["PrivatePort"],
+                            p["Type"].as_str().unwrap_or("tcp")
+                        ),
+                    })
+                    .collect();
+                json!({
+                    "id": short_id(c["Id"].as_str().unwrap_or_default()),
+                    "names": names,
+                    "image": c["Image"],
+                    "state": c["State"],
+                    "status": c["Status"],
+                    "ports": ports,
+                })
+            })
+            .collect();
+        Ok(json!({ "containers": containers }))
+    }
+}
+
+pub struct ContainerInspect(pub Engine);
+
+#[async_trait]
+impl BuiltinTool for ContainerInspect {
+    fn declaration(&self) -> FunctionDeclaration {
+        container_declaration(
+            "fio_container_inspect",
+            "Show a container's state, health, restart count, image, mounts and ports.",
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let container = container_arg(&args)?;
+        let info = self
+            .0
+            .get_json(&format!("/containers/{container}/json"))
+            .await?;
+        let state = &info["State"];
+        let mounts: Vec<Value> = info["Mounts"]
+            .as_array()
+            .map(|v| v.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|m| json!({"source": m["Source"], "destination": m["Destination"], "mode": m["Mode"]}))
+            .collect();
+        // Environment variables are left out on purpose: they commonly carry credentials.
+        Ok(json!({
+            "id": short_id(info["Id"].as_str().unwrap_or_default()),
+            "name": info["Name"].as_str().map(|v| v.trim_start_matches('/')),
+            "image": info["Config"]["Image"],
+            "created": info["Created"],
+            "state": {
+                "status": state["Status"],
+                "running": state["Running"],
+                "exit_code": state["ExitCode"],
+                "oom_killed": state["OOMKilled"],
+                "error": state["Error"],
+                "started_at": state["StartedAt"],
+                "finished_at": state["FinishedAt"],
+                "health": state["Health"]["Status"],
+            },
+            "restart_count": info["RestartCount"],
+            "restart_policy": info["HostConfig"]["RestartPolicy"]["Name"],
+            "command": info["Config"]["Cmd"],
+            "mounts": mounts,
+            "ports": info["NetworkSettings"]["Ports"],
+        }))
+    }
+}
+
+pub struct ContainerLogs(pub Engine);
+
+#[async_trait]
+impl BuiltinTool for ContainerLogs {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_container_logs",
+            "Read the most recent log lines (stdout and stderr) of a container.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "container": {
+                        "type": "string",
+                        "description": "Container name or ID",
+                    },
+                    "tail": {
+                        "type": "integer",
+                        "description": format!("Number of lines to return (at most {MAX_LOG_LINES})"),
+                        "default": DEFAULT_LOG_LINES,
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only show logs newer than this: a duration such as '15m', '2h' or '1d', an RFC 3339 time, or a unix timestamp",
+                    },
+                },
+                "required": ["container"],
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let container = container_arg(&args)?;
+        let tail = optional_usize(&args, "tail")?
+            .unwrap_or(DEFAULT_LOG_LINES)
+            .clamp(1, MAX_LOG_LINES);
+        let mut path = format!(
+            "/containers/{container}/logs?stdout=true&stderr=true&timestamps=true&tail={tail}"
+        );
+        if let Some(since) = optional_str(&args, "since") {
+            path.push_str(&format!("&since={}", parse_since(since, Utc::now())?));
+        }
+        let (status, body) = self.0.request(Method::GET, &path).await?;
+        check_status(status, &body)?;
+        let content = demux_logs(&body);
+        Ok(json!({
+            "container": container,
+            "lines": content.lines().count(),
+            "content": content.trim_end(),
+        }))
+    }
+}
+
+pub struct ContainerStats(pub Engine);
+
+#[async_trait]
+impl BuiltinTool for ContainerStats {
+    fn declaration(&self) -> FunctionDeclaration {
+        container_declaration(
+            "fio_container_stats",
+            "Take a snapshot of a container's CPU, memory, network and block I/O usage.",
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let container = container_arg(&args)?;
+        let stats = self
+            .0
+            .get_json(&format!("/containers/{container}/stats?stream=false"))
+            .await?;
+        Ok(summarize_stats(&container, &stats))
+    }
+}
+
+/// A mutating `POST /containers/{id}/{action}` call.
+pub struct ContainerAction {
+    pub engine: Engine,
+    pub action: &'static str,
+}
+
+impl ContainerAction {
+    pub fn all(engine: &Engine) -> Vec<Self> {
+        ["restart", "stop"]
+            .into_iter()
+            .map(|action| Self {
+                engine: engine.clone(),
+                action,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BuiltinTool for ContainerAction {
+    fn declaration(&self) -> FunctionDeclaration {
+        let description = match self.action {
+            "restart" => "Restart a container.",
+            _ => "Stop a running container.",
+        };
+        new_declaration(
+            &format!("fio_container_{}", self.action),
+            description,
+            json!({
+                "type": "object",
+                "properties": {
+                    "container": {
+                        "type": "string",
+                        "description": "Container name or ID",
+                    },
+                    "timeout": {
+                        "type": "integer",
+                        "description": format!("Seconds to wait for a graceful stop before killing the container (at most {MAX_STOP_TIMEOUT})"),
+                        "default": DEFAULT_STOP_TIMEOUT,
+                    },
+                },
+                "required": ["container"],
+            }),
+        )
+    }
+
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let container = container_arg(&args)?;
+        let timeout = optional_usize(&args, "timeout")?
+            .unwrap_or(DEFAULT_STOP_TIMEOUT)
+            .min(MAX_STOP_TIMEOUT);
+        let path = format!("/containers/{container}/{}?t={timeout}", self.action);
+        let (status, body) = self.engine.request(Method::POST, &path).await?;
+        check_status(status, &body)?;
+        let state = self
+            .engine
+            .get_json(&format!("/containers/{container}/json"))
+            .await
+            .ok()
+            .map(|info| info["State"]["Status"].clone());
+        Ok(json!({
+            "container": container,
+            "action": self.action,
+            "ok": true,
+            "already_done": status == StatusCode::NOT_MODIFIED,
+            "state": state,
+        }))
+    }
+}
+
+fn container_declaration(name: &str, description: &str) -> FunctionDeclaration {
+    new_declaration(
+        name,
+        description,
+        json!({
+            "type": "object",
+            "properties": {
+                "container": {
+                    "type": "string",
+                    "description": "Container name or ID",
+                },
+            },
+            "required": ["container"],
+        }),
+    )
+}
+
+fn container_arg(args: &Value) -> Result<String> {
+    let container = required_str(args, "container")?;
+    let valid = container
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric())
+        && container
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+    if !valid {
+        bail!("Invalid container name or ID '{container}'");
+    }
+    Ok(container.to_string())
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(12)]
+}
+
+/// Convert `since` into the unix timestamp the engine API expects.
+fn parse_since(since: &str, now: DateTime<Utc>) -> Result<i64> {
+    if let Ok(timestamp) = since.parse::<i64>() {
+        return Ok(timestamp);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.timestamp());
+    }
+    let split = since.len() - since.chars().last().map_or(0, |c| c.len_utf8());
+    let (amount, unit) = since.split_at(split);
+    let seconds = match (amount.parse::<i64>(), unit) {
+        (Ok(n), "s") => n,
+        (Ok(n), "m") => n * 60,
+        (Ok(n), "h") => n * 3600,
+        (Ok(n), "d") => n * 86400,
+        _ => bail!("Invalid since '{since}', expected e.g. '15m', '2h', an RFC 3339 time or a unix timestamp"),
+    };
+    Ok(now.timestamp() - seconds)
+}
+
+/// Containers without a TTY multiplex stdout/stderr into frames with an 8-byte header
+/// (stream type, three zero bytes, big-endian length). TTY containers send raw text.
+fn demux_logs(body: &[u8]) -> String {
+    let mut output = Vec::with_capacity(body.len());
+    let mut rest = body;
+    while rest.len() >= 8 && rest[0] <= 2 && rest[1..4] == [0, 0, 0] {
+        let len = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let end = (8 + len).min(rest.len());
+        output.extend_from_slice(&rest[8..end]);
+        rest = &rest[end..];
+    }
+    output.extend_from_slice(rest);
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn summarize_stats(container: &str, stats: &Value) -> Value {
+    let cpu_delta = stats["cpu_stats"]["cpu_usage"]["total_usage"]
+        .as_f64()
+        .unwrap_or(0.0)
+        - stats["precpu_stats"]["cpu_usage"]["total_usage"]
+            .as_f64()
+            .unwrap_or(0.0);
+    let system_delta = stats["cpu_stats"]["system_cpu_usage"]
+        .as_f64()
+        .unwrap_or(0.0)
+        - stats["precpu_stats"]["system_cpu_usage"]
+            .as_f64()
+            .unwrap_or(0.0);
+    let online_cpus = stats["cpu_stats"]["online_cpus"].as_f64().unwrap_or(1.0);
+    let cpu_percent = if cpu_delta > 0.0 && system_delta > 0.0 {
+        cpu_delta / system_delta * online_cpus * 100.0
+    } else {
+        0.0
+    };
+
+    let memory = &stats["memory_stats"];
+    // Like `docker stats`, exclude the page cache from memory usage.
+    let cache = memory["stats"]["inactive_file"]
+        .as_u64()
+        .or_else(|| memory["stats"]["total_inactive_file"].as_u64())
+        .unwrap_or(0);
+    let memory_usage = memory["usage"].as_u64().unwrap_or(0).saturating_sub(cache);
+    let memory_limit = memory["limit"].as_u64().unwrap_or(0);
+
+    let (mut rx, mut tx) = (0, 0);
+    if let Some(networks) = stats["networks"].as_object() {
+        for network in networks.values() {
+            rx += network["rx_bytes"].as_u64().unwrap_or(0);
+            tx += network["tx_bytes"].as_u64().unwrap_or(0);
+        }
+    }
+    let (mut read, mut write) = (0, 0);
+    for entry in stats["blkio_stats"]["io_service_bytes_recursive"]
+        .as_array()
+        .map(|v| v.as_slice())
+        .unwrap_or_default()
+    {
+        let value = entry["value"].as_u64().unwrap_or(0);
+        match entry["op"].as_str().map(|v| v.to_lowercase()).as_deref() {
+            Some("read") => read += value,
+            Some("write") => write += value,
+            _ => {}
+        }
+    }
+
+    json!({
+        "container": container,
+        "cpu_percent": (cpu_percent * 100.0).round() / 100.0,
+        "memory_usage_bytes": memory_usage,
+        "memory_limit_bytes": memory_limit,
+        "memory_percent": if memory_limit > 0 {
+            (memory_usage as f64 * 10000.0 / memory_limit as f64).round() / 100.0
+        } else {
+            0.0
+        },
+        "network_rx_bytes": rx,
+        "network_tx_bytes": tx,
+        "block_read_bytes": read,
+        "block_write_bytes": write,
+        "pids": stats["pids_stats"]["current"],
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use http::{Request, Response};
+    use http_body_util::Full;
+    use hyper::{body::Incoming, service::service_fn};
+    use hyper_util::rt::TokioIo;
+    use parking_lot::Mutex;
+    use std::{convert::Infallible, sync::Arc};
+    use tokio::net::UnixListener;
+
+    /// A Docker Engine API stand-in on a temporary unix socket that records request lines.
+    struct MockEngine {
+        socket: PathBuf,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockEngine {
+        async fn start(name: &str) -> Self {
+            let socket =
+                std::env::temp_dir().join(format!("fio-engine-{name}-{}.sock", std::process::id()));
+            let _ = std::fs::remove_file(&socket);
+            let listener = UnixListener::bind(&socket).unwrap();
+            let requests = Arc::new(Mutex::new(vec![]));
+            let recorded = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let recorded = recorded.clone();
+                    tokio::spawn(async move {
+                        let service = service_fn(move |req: Request<Incoming>| {
+                            let recorded = recorded.clone();
+                            async move {
+                                let target = req.uri().to_string();
+                                recorded.lock().push(format!("{} {target}", req.method()));
+                                let (status, body) = respond(req.method(), &target);
+                                Ok::<_, Infallible>(
+                                    Response::builder()
+                                        .status(status)
+                                        .body(Full::new(Bytes::from(body)))
+                                        .unwrap(),
+                                )
+                            }
+                        });
+                        let _ = hyper::server::conn::http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            });
+            Self { socket, requests }
+        }
+
+        fn engine(&self) -> Engine {
+            Engine::new(&ContainerConfig {
+                engine: ContainerEngine::Docker,
+                socket: Some(self.socket.display().to_string()),
+            })
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().clone()
+        }
+    }
+
+    impl Drop for MockEngine {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.socket);
+        }
+    }
+
+    fn respond(method: &Method, target: &str) -> (StatusCode, Vec<u8>) {
+        let path = target.split('?').next().unwrap_or_default();
+        let json = |v: Value| v.to_string().into_bytes();
+        match (method.as_str(), path) {
+            ("GET", "/containers/json") => (
+                StatusCode::OK,
+                json(json!([{
+                    "Id": "0123456789abcdef0123",
+                    "Names": ["/web"],
+                    "Image": "nginx:1.25",
+                    "State": "running",
+                    "Status": "Up 2 hours",
+                    "Ports": [{"IP": "0.0.0.0", "PrivatePort": 80, "PublicPort": 8080, "Type": "tcp"}],
+                }])),
+            ),
+            ("GET", "/containers/web/json") => (
+                StatusCode::OK,
+                json(json!({
+                    "Id": "0123456789abcdef0123",
+                    "Name": "/web",
+                    "RestartCount": 3,
+                    "State": {"Status": "running", "Running": true, "ExitCode": 0, "Health": {"Status": "healthy"}},
+                    "Config": {"Image": "nginx:1.25", "Env": ["DB_PASSWORD=secret"], "Cmd": ["nginx"]},
+                    "HostConfig": {"RestartPolicy": {"Name": "always"}},
+                    "Mounts": [],
+                })),
+            ),
+            ("GET", "/containers/web/logs") => {
+                let mut body = vec![];
+                for (stream, line) in [(1u8, "started\n"), (2u8, "warning: slow\n")] {
+                    body.extend_from_slice(&[stream, 0, 0, 0]);
+                    body.extend_from_slice(&(line.len() as u32).to_be_bytes());
+                    body.extend_from_slice(line.as_bytes());
+                }
+                (StatusCode::OK, body)
+            }
+            ("GET", "/containers/web/stats") => (
+                StatusCode::OK,
+                json(json!({
+                    "cpu_stats": {"cpu_usage": {"total_usage": 400}, "system_cpu_usage": 2000, "online_cpus": 2},
+                    "precpu_stats": {"cpu_usage": {"total_usage": 200}, "system_cpu_usage": 1000},
+                    "memory_stats": {"usage": 300, "limit": 1000, "stats": {"inactive_file": 100}},
+                    "networks": {"eth0": {"rx_bytes": 10, "tx_bytes": 20}},
+                    "pids_stats": {"current": 4},
+                })),
+            ),
+            ("POST", "/containers/web/restart") => (StatusCode::NO_CONTENT, vec![]),
+            ("POST", "/containers/web/stop") => (StatusCode::NOT_MODIFIED, vec![]),
+            _ => (
+                StatusCode::NOT_FOUND,
+                json(json!({"message": "No such container: missing"})),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_inspect() {
+        let mock = MockEngine::start("list").await;
+        let output = ContainerList(mock.engine())
+            .call(json!({"all": true}))
+            .await
+            .unwrap();
+        let container = &output["containers"][0];
+        assert_eq!(container["id"], "0123456789ab");
+        assert_eq!(container["names"], json!(["web"]));
+        assert_eq!(container["ports"], json!(["0.0.0.0:8080->80/tcp"]));
+
+        let output = ContainerInspect(mock.engine())
+            .call(json!({"container": "web"}))
+            .await
+            .unwrap();
+        assert_eq!(output["state"]["health"], "healthy");
+        assert_eq!(output["restart_count"], 3);
+        assert!(!output.to_string().contains("DB_PASSWORD"));
+
+        let err = ContainerInspect(mock.engine())
+            .call(json!({"container": "missing"}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No such container"));
+        assert_eq!(
+            mock.requests(),
+            [
+                "GET /containers/json?all=true",
+                "GET /containers/web/json",
+                "GET /containers/missing/json"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logs_and_stats() {
+        let mock = MockEngine::start("logs").await;
+        let output = ContainerLogs(mock.engine())
+            .call(json!({"container": "web", "tail": 50, "since": "1700000000"}))
+            .await
+            .unwrap();
+        assert_eq!(output["content"], "started\nwarning: slow");
+        assert_eq!(
+            mock.requests()[0],
+            "GET /containers/web/logs?stdout=true&stderr=true&timestamps=true&tail=50&since=1700000000"
+        );
+
+        let output = ContainerStats(mock.engine())
+            .call(json!({"container": "web"}))
+            .await
+            .unwrap();
+        assert_eq!(output["cpu_percent"], 40.0);
+        assert_eq!(output["memory_usage_bytes"], 200);
+        assert_eq!(output["memory_percent"], 20.0);
+        assert_eq!(output["network_tx_bytes"], 20);
+    }
+
+    #[tokio::test]
+    async fn test_restart_and_stop() {
+        let mock = MockEngine::start("actions").await;
+        let actions = ContainerAction::all(&mock.engine());
+        assert!(actions.iter().all(|tool| !tool.read_only()));
+
+        let output = actions[0]
+            .call(json!({"container": "web", "timeout": 5}))
+            .await
+            .unwrap();
+        assert_eq!(output["state"], "running");
+        let output = actions[1]
+            .call(json!({"container": "web", "timeout": 300}))
+            .await
+            .unwrap();
+        assert_eq!(output["already_done"], true);
+        assert_eq!(
+            mock.requests(),
+            [
+                "POST /containers/web/restart?t=5",
+                "GET /containers/web/json",
+                "POST /containers/web/stop?t=20",
+                "GET /containers/web/json"
+            ]
+        );
+        assert!(actions[0]
+            .call(json!({"container": "../images"}))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_since("15m", now).unwrap(), now.timestamp() - 900);
+        assert_eq!(parse_since("1700000000", now).unwrap(), 1_700_000_000);
+        assert_eq!(
+            parse_since("2024-05-01T11:00:00Z", now).unwrap(),
+            now.timestamp() - 3600
+        );
+        assert!(parse_since("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn test_demux_tty_logs_passthrough() {
+        assert_eq!(demux_logs(b"plain tty output\n"), "plain tty output\n");
+    }
+}
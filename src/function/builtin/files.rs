@@ -0,0 +1,184 @@
+use super::{new_declaration, optional_usize, required_str, BuiltinTool};
+use crate::function::FunctionDeclaration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_TAIL_LINES: usize = 100;
+const MAX_TAIL_LINES: usize = 2000;
+/// Never read more than this from the end of a file, however long its lines are.
+const MAX_TAIL_BYTES: u64 = 1024 * 1024;
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+pub struct FileTail {
+    roots: Vec<PathBuf>,
+}
+
+impl FileTail {
+    pub fn new(roots: &[PathBuf]) -> Self {
+        Self {
+            roots: roots.to_vec(),
+        }
+    }
+}
+
+#[async_trait]
+impl BuiltinTool for FileTail {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_file_tail",
+            &format!(
+                "Read the last lines of a text file, such as a log file, below {}.",
+                self.roots
+                    .iter()
+                    .map(|v| v.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path of the file",
+                    },
+                    "lines": {
+                        "type": "integer",
+                        "description": format!("Number of lines to return (at most {MAX_TAIL_LINES})"),
+                        "default": DEFAULT_TAIL_LINES,
+                    },
+                },
+                "required": ["path"],
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let path = required_str(&args, "path")?.to_string();
+        let lines = optional_usize(&args, "lines")?
+            .unwrap_or(DEFAULT_TAIL_LINES)
+            .clamp(1, MAX_TAIL_LINES);
+        let roots = self.roots.clone();
+        tokio::task::spawn_blocking(move || tail_file(Path::new(&path), lines, &roots))
+            .await
+            .map_err(|err| anyhow!("Tool task failed: {err}"))?
+    }
+}
+
+fn tail_file(path: &Path, lines: usize, roots: &[PathBuf]) -> Result<Value> {
+    if !path.is_absolute() {
+        bail!("Path must be absolute: {}", path.display());
+    }
+    // Symlinks and `..` are resolved first, so they can't lead out of the roots.
+    let real = path
+        .canonicalize()
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let allowed = roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| real.starts_with(root));
+    if !allowed {
+        bail!(
+            "Not below an allowed directory (builtin_tools.file_tail_roots): {}",
+            path.display()
+        );
+    }
+    let mut file =
+        File::open(&real).with_context(|| format!("Failed to open {}", path.display()))?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() {
+        bail!("Not a regular file: {}", path.display());
+    }
+    let size = metadata.len();
+
+    // Read backwards in chunks until we have enough newlines or hit the byte cap.
+    let mut start = size;
+    let mut buf: Vec<u8> = vec![];
+    while start > 0 && size - start < MAX_TAIL_BYTES {
+        let len = CHUNK_SIZE.min(start).min(MAX_TAIL_BYTES - (size - start));
+        start -= len;
+        file.seek(SeekFrom::Start(start))?;
+        let mut chunk = vec![0; len as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buf);
+        buf = chunk;
+        let newlines = buf.iter().filter(|&&b| b == b'\n').count();
+        // One extra newline: the last line may end with a newline of its own.
+        if newlines > lines {
+            break;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let all: Vec<&str> = text.lines().collect();
+    // Unless we reached the beginning, the first line may have been cut mid-way.
+    let skip_partial = usize::from(start > 0).min(all.len());
+    let available = &all[skip_partial..];
+    let selected = &available[available.len().saturating_sub(lines)..];
+    Ok(json!({
+        "path": path.display().to_string(),
+        "size_bytes": size,
+        "lines": selected.len(),
+        "truncated": start > 0 || available.len() > selected.len(),
+        "content": selected.join("\n"),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_tail_file() {
+        let path = std::env::temp_dir().join(format!("fio-tail-{}.log", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        for i in 1..=5000 {
+            writeln!(file, "line {i}").unwrap();
+        }
+        drop(file);
+
+        let roots = [std::env::temp_dir()];
+        let output = tail_file(&path, 3, &roots).unwrap();
+        assert_eq!(output["content"], "line 4998\nline 4999\nline 5000");
+        assert_eq!(output["truncated"], true);
+
+        let output = tail_file(&path, 10_000, &roots).unwrap();
+        assert_eq!(output["lines"], 5000);
+        assert!(output["content"].as_str().unwrap().starts_with("line 1\n"));
+        assert_eq!(output["truncated"], false);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tail_file_rejects_relative_paths() {
+        let roots = [std::env::temp_dir()];
+        assert!(tail_file(Path::new("relative.log"), 10, &roots).is_err());
+    }
+
+    #[test]
+    fn test_tail_file_stays_below_roots() {
+        let dir = std::env::temp_dir().join(format!("fio-tail-roots-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("logs")).unwrap();
+        std::fs::write(dir.join("secret"), "key\n").unwrap();
+        std::fs::write(dir.join("logs/app.log"), "ok\n").unwrap();
+        let roots = [dir.join("logs")];
+
+        assert!(tail_file(&dir.join("logs/app.log"), 10, &roots).is_ok());
+        assert!(tail_file(&dir.join("secret"), 10, &roots).is_err());
+        assert!(tail_file(&dir.join("logs/../secret"), 10, &roots).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("secret"), dir.join("logs/link")).unwrap();
+            assert!(tail_file(&dir.join("logs/link"), 10, &roots).is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+mod container;
+mod files;
+mod notify;
+mod system;
+mod systemd;
+
+use super::FunctionDeclaration;
+use crate::notify::NotifySinks;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, path::PathBuf, sync::Arc};
+
+/// A tool implemented in-process instead of as an llm-functions executable or MCP server.
+#[async_trait]
+pub trait BuiltinTool: Send + Sync {
+    fn declaration(&self) -> FunctionDeclaration;
+
+    /// Read-only tools only observe the host; anything else changes it.
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    async fn call(&self, args: Value) -> Result<Value>;
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BuiltinToolsConfig {
+    /// Expose the built-in ops tools (`fio_*`) alongside local functions and MCP tools.
+    pub enabled: bool,
+    /// Container engine used by the `fio_container_*` tools.
+    pub container: container::ContainerConfig,
+    /// Directories `fio_file_tail` may read below; anything else (keys, the config file)
+    /// stays out of reach of prompt-injected requests.
+    pub file_tail_roots: Vec<PathBuf>,
+}
+
+impl Default for BuiltinToolsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            container: Default::default(),
+            file_tail_roots: vec![PathBuf::from("/var/log")],
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BuiltinTools {
+    tools: IndexMap<String, Arc<dyn BuiltinTool>>,
+    declarations: Vec<FunctionDeclaration>,
+}
+
+impl fmt::Debug for BuiltinTools {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuiltinTools")
+            .field("tools", &self.tools.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl BuiltinTools {
+    pub fn new(config: &BuiltinToolsConfig, notify_sinks: &NotifySinks) -> Self {
+        let mut tools = Self::default();
+        if !config.enabled {
+            return tools;
+        }
+        tools.register(system::SystemInfo);
+        tools.register(system::ResourceUsage);
+        tools.register(system::ProcessList);
+        tools.register(system::PortListeners);
+        tools.register(files::FileTail::new(&config.file_tail_roots));
+        if systemd::Systemd::is_available() {
+            let systemd = systemd::Systemd::default();
+            tools.register(systemd::ServiceStatus(systemd.clone()));
+            tools.register(systemd::FailedUnits(systemd.clone()));
+            tools.register(systemd::JournalTail(systemd.clone()));
+            for action in systemd::ServiceAction::all(&systemd) {
+                tools.register(action);
+            }
+        }
+        let engine = container::Engine::new(&config.container);
+        if engine.is_available() {
+            tools.register(container::ContainerList(engine.clone()));
+            tools.register(container::ContainerInspect(engine.clone()));
+            tools.register(container::ContainerLogs(engine.clone()));
+            tools.register(container::ContainerStats(engine.clone()));
+            for action in container::ContainerAction::all(&engine) {
+                tools.register(action);
+            }
+        }
+        if !notify_sinks.is_empty() {
+            tools.register(notify::Notify::new(notify_sinks));
+        }
+        tools
+    }
+
+    pub fn register(&mut self, tool: impl BuiltinTool + 'static) {
+        let declaration = tool.declaration();
+        self.tools.insert(
+            declaration.name.clone(),
+            Arc::new(tool) as Arc<dyn BuiltinTool>,
+        );
+        self.declarations.push(declaration);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn BuiltinTool>> {
+        self.tools.get(name).cloned()
+    }
+
+    pub fn declarations(&self) -> &[FunctionDeclaration] {
+        &self.declarations
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<dyn BuiltinTool>)> {
+        self.tools.iter().map(|(name, tool)| (name.as_str(), tool))
+    }
+}
+
+fn new_declaration(name: &str, description: &str, parameters: Value) -> FunctionDeclaration {
+    FunctionDeclaration {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters: serde_json::from_value(parameters)
+            .expect("builtin tool parameters should be a valid schema"),
+        agent: false,
+    }
+}
+
+fn optional_str<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn required_str<'a>(args: &'a Value, key: &str) -> Result<&'a str> {
+    optional_str(args, key).ok_or_else(|| anyhow!("Missing required argument '{key}'"))
+}
+
+/// Read a non-negative integer argument; models sometimes send numbers as strings.
+fn optional_usize(args: &Value, key: &str) -> Result<Option<usize>> {
+    match args.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .ok_or_else(|| anyhow!("Argument '{key}' must be a non-negative integer")),
+        Some(Value::String(s)) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("Argument '{key}' must be a non-negative integer")),
+        Some(_) => Err(anyhow!("Argument '{key}' must be a non-negative integer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_registry_declarations() {
+        let tools = BuiltinTools::new(&BuiltinToolsConfig::default(), &NotifySinks::new());
+        let names: Vec<_> = tools
+            .declarations()
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(
+            names[..5],
+            [
+                "fio_system_info",
+                "fio_resource_usage",
+                "fio_process_list",
+                "fio_port_listeners",
+                "fio_file_tail"
+            ]
+        );
+        assert!(names[..5]
+            .iter()
+            .all(|name| tools.get(name).unwrap().read_only()));
+
+        let disabled = BuiltinTools::new(
+            &BuiltinToolsConfig {
+                enabled: false,
+                ..Default::default()
+            },
+            &NotifySinks::new(),
+        );
+        assert!(disabled.declarations().is_empty());
+        assert!(disabled.get("fio_system_info").is_none());
+    }
+
+    #[test]
+    fn test_optional_usize() {
+        let args = json!({"a": 5, "b": "7", "c": -1, "d": "x"});
+        assert_eq!(optional_usize(&args, "a").unwrap(), Some(5));
+        assert_eq!(optional_usize(&args, "b").unwrap(), Some(7));
+        assert_eq!(optional_usize(&args, "missing").unwrap(), None);
+        assert!(optional_usize(&args, "c").is_err());
+        assert!(optional_usize(&args, "d").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use super::{new_declaration, required_str, BuiltinTool};
+use crate::function::FunctionDeclaration;
+use crate::notify::{send, NotifySinks};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+pub struct Notify {
+    sinks: NotifySinks,
+}
+
+impl Notify {
+    pub fn new(sinks: &NotifySinks) -> Self {
+        Self {
+            sinks: sinks.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl BuiltinTool for Notify {
+    fn declaration(&self) -> FunctionDeclaration {
+        let names: Vec<&String> = self.sinks.keys().collect();
+        new_declaration(
+            "fio_notify",
+            "Send a message to a configured notification channel (Telegram, Slack, webhook, ...).",
+            json!({
+                "type": "object",
+                "properties": {
+                    "sink": {
+                        "type": "string",
+                        "description": "Name of the notification sink",
+                        "enum": names,
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "Message text",
+                    },
+                },
+                "required": ["sink", "message"],
+            }),
+        )
+    }
+
+    /// Messages leave the host, so sending one needs the same confirmation as a change.
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let sink = required_str(&args, "sink")?;
+        let message = required_str(&args, "message")?;
+        send(&self.sinks, sink, message).await?;
+        Ok(json!({ "sink": sink, "sent": true }))
+    }
+}
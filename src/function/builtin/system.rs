@@ -0,0 +1,457 @@
+use super::{new_declaration, optional_str, optional_usize, BuiltinTool};
+use crate::function::FunctionDeclaration;
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::{collections::HashSet, net::Ipv4Addr, net::Ipv6Addr};
+use sysinfo::{
+    Disks, ProcessRefreshKind, ProcessesToUpdate, System, Users, MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+const DEFAULT_PROCESS_LIMIT: usize = 20;
+const MAX_PROCESS_LIMIT: usize = 200;
+
+/// Run blocking host inspection off the async runtime.
+async fn blocking<F>(f: F) -> Result<Value>
+where
+    F: FnOnce() -> Result<Value> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| anyhow!("Tool task failed: {err}"))?
+}
+
+pub struct SystemInfo;
+
+#[async_trait]
+impl BuiltinTool for SystemInfo {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_system_info",
+            "Get host information: hostname, OS, kernel, architecture, CPU count and uptime.",
+            json!({"type": "object", "properties": {}}),
+        )
+    }
+
+    async fn call(&self, _args: Value) -> Result<Value> {
+        blocking(|| {
+            let mut sys = System::new();
+            sys.refresh_cpu_all();
+            Ok(json!({
+                "hostname": System::host_name(),
+                "os": System::long_os_version(),
+                "kernel": System::kernel_version(),
+                "arch": System::cpu_arch(),
+                "cpus": sys.cpus().len(),
+                "physical_cores": System::physical_core_count(),
+                "cpu_brand": sys.cpus().first().map(|cpu| cpu.brand().trim().to_string()),
+                "uptime_seconds": System::uptime(),
+                "boot_time": System::boot_time(),
+            }))
+        })
+        .await
+    }
+}
+
+pub struct ResourceUsage;
+
+#[async_trait]
+impl BuiltinTool for ResourceUsage {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_resource_usage",
+            "Get current resource usage: load average, memory and swap, and disk space per mount point.",
+            json!({"type": "object", "properties": {}}),
+        )
+    }
+
+    async fn call(&self, _args: Value) -> Result<Value> {
+        blocking(|| {
+            let mut sys = System::new();
+            sys.refresh_memory();
+            sys.refresh_cpu_all();
+            let load = System::load_average();
+
+            let mut seen = HashSet::new();
+            let disks: Vec<Value> = Disks::new_with_refreshed_list()
+                .iter()
+                .filter(|disk| disk.total_space() > 0)
+                .filter(|disk| seen.insert(disk.mount_point().to_path_buf()))
+                .map(|disk| {
+                    let total = disk.total_space();
+                    let available = disk.available_space();
+                    json!({
+                        "mount_point": disk.mount_point().display().to_string(),
+                        "device": disk.name().to_string_lossy(),
+                        "file_system": disk.file_system().to_string_lossy(),
+                        "total_bytes": total,
+                        "available_bytes": available,
+                        "used_percent": percent(total - available.min(total), total),
+                    })
+                })
+                .collect();
+
+            Ok(json!({
+                "load_average": {
+                    "one": load.one,
+                    "five": load.five,
+                    "fifteen": load.fifteen,
+                },
+                "cpus": sys.cpus().len(),
+                "memory": {
+                    "total_bytes": sys.total_memory(),
+                    "used_bytes": sys.used_memory(),
+                    "available_bytes": sys.available_memory(),
+                    "used_percent": percent(sys.used_memory(), sys.total_memory()),
+                },
+                "swap": {
+                    "total_bytes": sys.total_swap(),
+                    "used_bytes": sys.used_swap(),
+                    "used_percent": percent(sys.used_swap(), sys.total_swap()),
+                },
+                "disks": disks,
+            }))
+        })
+        .await
+    }
+}
+
+pub struct ProcessList;
+
+#[async_trait]
+impl BuiltinTool for ProcessList {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_process_list",
+            "List running processes with their CPU and memory usage, busiest first.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "sort_by": {
+                        "type": "string",
+                        "description": "Sort order",
+                        "enum": ["cpu", "memory"],
+                        "default": "cpu",
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Only include processes whose name or command line contains this text",
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": format!("Maximum number of processes to return (at most {MAX_PROCESS_LIMIT})"),
+                        "default": DEFAULT_PROCESS_LIMIT,
+                    },
+                },
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let sort_by = optional_str(&args, "sort_by").unwrap_or("cpu").to_string();
+        if !matches!(sort_by.as_str(), "cpu" | "memory") {
+            bail!("Invalid sort_by '{sort_by}', expected 'cpu' or 'memory'");
+        }
+        let name = optional_str(&args, "name").map(str::to_lowercase);
+        let limit = optional_usize(&args, "limit")?
+            .unwrap_or(DEFAULT_PROCESS_LIMIT)
+            .clamp(1, MAX_PROCESS_LIMIT);
+
+        blocking(move || {
+            let mut sys = System::new();
+            let kind = ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_memory()
+                .with_cmd(sysinfo::UpdateKind::OnlyIfNotSet)
+                .with_user(sysinfo::UpdateKind::OnlyIfNotSet);
+            // CPU usage is measured between two refreshes.
+            sys.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+            std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+            sys.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+            let users = Users::new_with_refreshed_list();
+            let total_memory = {
+                sys.refresh_memory();
+                sys.total_memory()
+            };
+
+            let mut processes: Vec<_> = sys
+                .processes()
+                .values()
+                .filter(|process| process.thread_kind().is_none())
+                .map(|process| {
+                    let command = process
+                        .cmd()
+                        .iter()
+                        .map(|v| v.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    (process, command)
+                })
+                .filter(|(process, command)| match &name {
+                    Some(name) => {
+                        process
+                            .name()
+                            .to_string_lossy()
+                            .to_lowercase()
+                            .contains(name)
+                            || command.to_lowercase().contains(name)
+                    }
+                    None => true,
+                })
+                .collect();
+            let total = processes.len();
+            if sort_by == "memory" {
+                processes.sort_by_key(|(process, _)| std::cmp::Reverse(process.memory()));
+            } else {
+                processes.sort_by(|(a, _), (b, _)| b.cpu_usage().total_cmp(&a.cpu_usage()));
+            }
+
+            let items: Vec<Value> = processes
+                .into_iter()
+                .take(limit)
+                .map(|(process, command)| {
+                    json!({
+                        "pid": process.pid().as_u32(),
+                        "parent_pid": process.parent().map(|v| v.as_u32()),
+                        "name": process.name().to_string_lossy(),
+                        "user": process
+                            .user_id()
+                            .and_then(|uid| users.get_user_by_id(uid))
+                            .map(|user| user.name().to_string()),
+                        "status": process.status().to_string(),
+                        "cpu_percent": (process.cpu_usage() * 10.0).round() / 10.0,
+                        "memory_bytes": process.memory(),
+                        "memory_percent": percent(process.memory(), total_memory),
+                        "run_time_seconds": process.run_time(),
+                        "command": command,
+                    })
+                })
+                .collect();
+            Ok(json!({ "total": total, "processes": items }))
+        })
+        .await
+    }
+}
+
+pub struct PortListeners;
+
+#[async_trait]
+impl BuiltinTool for PortListeners {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_port_listeners",
+            "List sockets listening for connections, with the owning process when it can be determined.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "protocol": {
+                        "type": "string",
+                        "description": "Which sockets to list",
+                        "enum": ["tcp", "udp", "all"],
+                        "default": "tcp",
+                    },
+                    "port": {
+                        "type": "integer",
+                        "description": "Only include sockets bound to this port",
+                    },
+                },
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let protocol = optional_str(&args, "protocol").unwrap_or("tcp").to_string();
+        let protocols: &[&str] = match protocol.as_str() {
+            "tcp" => &["tcp", "tcp6"],
+            "udp" => &["udp", "udp6"],
+            "all" => &["tcp", "tcp6", "udp", "udp6"],
+            _ => bail!("Invalid protocol '{protocol}', expected 'tcp', 'udp' or 'all'"),
+        };
+        let port = optional_usize(&args, "port")?;
+        if !cfg!(target_os = "linux") {
+            bail!("fio_port_listeners is only supported on Linux");
+        }
+
+        blocking(move || {
+            let mut listeners = vec![];
+            for protocol in protocols {
+                let path = format!("/proc/net/{protocol}");
+                // IPv6 may be disabled, in which case the table does not exist.
+                let Ok(content) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                listeners.extend(parse_proc_net(&content, protocol));
+            }
+            if let Some(port) = port {
+                listeners.retain(|v| v.port as usize == port);
+            }
+            listeners.sort_by(|a, b| (a.port, &a.protocol).cmp(&(b.port, &b.protocol)));
+            let owners = socket_owners();
+
+            let items: Vec<Value> = listeners
+                .into_iter()
+                .map(|listener| {
+                    let owner = owners.iter().find(|(inode, _, _)| *inode == listener.inode);
+                    json!({
+                        "protocol": listener.protocol,
+                        "address": listener.address,
+                        "port": listener.port,
+                        "pid": owner.map(|(_, pid, _)| pid),
+                        "process": owner.map(|(_, _, name)| name),
+                    })
+                })
+                .collect();
+            Ok(json!({ "listeners": items }))
+        })
+        .await
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Listener {
+    protocol: String,
+    address: String,
+    port: u16,
+    inode: u64,
+}
+
+/// Parse a `/proc/net/{tcp,tcp6,udp,udp6}` table, keeping listening sockets only.
+fn parse_proc_net(content: &str, protocol: &str) -> Vec<Listener> {
+    let is_udp = protocol.starts_with("udp");
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (local, state, inode) = (fields.get(1)?, fields.get(3)?, fields.get(9)?);
+            // TCP_LISTEN is 0A; unconnected UDP sockets report TCP_CLOSE (07).
+            let listening = if is_udp {
+                *state == "07"
+            } else {
+                *state == "0A"
+            };
+            if !listening {
+                return None;
+            }
+            let (address, port) = local.split_once(':')?;
+            Some(Listener {
+                protocol: protocol.to_string(),
+                address: decode_address(address)?,
+                port: u16::from_str_radix(port, 16).ok()?,
+                inode: inode.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Addresses are hex encoded as native-endian 32-bit words.
+fn decode_address(hex: &str) -> Option<String> {
+    let words = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let bytes: Vec<u8> = words.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    match bytes.len() {
+        4 => Some(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string()),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            Some(Ipv6Addr::from(octets).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Map socket inodes to `(inode, pid, process name)` by scanning `/proc/<pid>/fd`.
+/// Without root, only sockets owned by the current user can be resolved.
+fn socket_owners() -> Vec<(u64, u32, String)> {
+    let mut owners = vec![];
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|v| v.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let name = std::fs::read_to_string(entry.path().join("comm"))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|v| v.strip_prefix("socket:["))
+                .and_then(|v| v.strip_suffix(']'))
+                .and_then(|v| v.parse().ok());
+            if let Some(inode) = inode {
+                owners.push((inode, pid, name.clone()));
+            }
+        }
+    }
+    owners
+}
+
+fn percent(value: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (value as f64 * 1000.0 / total as f64).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_tcp() {
+        let content = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 12345 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:C350 01 00000000:00000000 00:00000000 00000000  1000        0 12346 1 0000000000000000 20 4 30 10 -1";
+        let listeners = parse_proc_net(content, "tcp");
+        assert_eq!(
+            listeners,
+            [Listener {
+                protocol: "tcp".into(),
+                address: "127.0.0.1".into(),
+                port: 8080,
+                inode: 12345,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_net_tcp6() {
+        let content = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2222 1 0000000000000000 100 0 0 10 0
+   1: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 3333 1 0000000000000000 100 0 0 10 0";
+        let listeners = parse_proc_net(content, "tcp6");
+        assert_eq!(listeners.len(), 2);
+        assert_eq!(
+            (listeners[0].address.as_str(), listeners[0].port),
+            ("::", 22)
+        );
+        assert_eq!(
+            (listeners[1].address.as_str(), listeners[1].port),
+            ("::1", 631)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_list_limit_and_filter() {
+        let output = ProcessList
+            .call(json!({"limit": 2, "sort_by": "memory"}))
+            .await
+            .unwrap();
+        assert!(output["processes"].as_array().unwrap().len() <= 2);
+        assert!(ProcessList.call(json!({"sort_by": "disk"})).await.is_err());
+    }
+}
@@ -0,0 +1,478 @@
+use super::{new_declaration, optional_str, optional_usize, required_str, BuiltinTool};
+use crate::function::FunctionDeclaration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+use std::process::Command;
+
+const DEFAULT_JOURNAL_LINES: usize = 100;
+const MAX_JOURNAL_LINES: usize = 1000;
+const JOURNAL_PRIORITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+const STATUS_PROPERTIES: &str = "Id,Description,LoadState,ActiveState,SubState,UnitFileState,\
+    MainPID,ActiveEnterTimestamp,InactiveEnterTimestamp,NRestarts,Result,FragmentPath";
+
+/// Runs `systemctl`/`journalctl`, optionally with a fixed `PATH` so tests can substitute fakes.
+#[derive(Debug, Clone, Default)]
+pub struct Systemd {
+    search_path: Option<String>,
+}
+
+impl Systemd {
+    pub fn is_available() -> bool {
+        which::which("systemctl").is_ok()
+    }
+
+    async fn run(&self, program: &'static str, args: Vec<String>) -> Result<CommandOutput> {
+        let search_path = self.search_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut command = Command::new(program);
+            command.args(&args);
+            if let Some(path) = search_path {
+                command.env("PATH", path);
+            }
+            let output = command
+                .output()
+                .with_context(|| format!("Failed to run {program}"))?;
+            Ok(CommandOutput {
+                success: output.status.success(),
+                exit_code: output.status.code(),
+                stdout: String::from_utf8_lossy(&output.stdout)
+                    .trim_end()
+                    .to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr)
+                    .trim_end()
+                    .to_string(),
+            })
+        })
+        .await
+        .map_err(|err| anyhow!("Tool task failed: {err}"))?
+    }
+
+    async fn unit_status(&self, unit: &str) -> Result<Value> {
+        let output = self
+            .run(
+                "systemctl",
+                vec![
+                    "show".into(),
+                    format!("--property={STATUS_PROPERTIES}"),
+                    "--no-pager".into(),
+                    "--".into(),
+                    unit.into(),
+                ],
+            )
+            .await?;
+        if !output.success {
+            bail!("systemctl show failed: {}", output.error_text());
+        }
+        let properties = parse_properties(&output.stdout);
+        if properties.get("LoadState").map(String::as_str) == Some("not-found") {
+            bail!("Unit '{unit}' not found");
+        }
+        Ok(json!(properties))
+    }
+}
+
+struct CommandOutput {
+    success: bool,
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+impl CommandOutput {
+    fn error_text(&self) -> String {
+        let text = if self.stderr.is_empty() {
+            &self.stdout
+        } else {
+            &self.stderr
+        };
+        match self.exit_code {
+            Some(code) => format!("exit code {code}: {text}"),
+            None => text.to_string(),
+        }
+    }
+}
+
+pub struct ServiceStatus(pub Systemd);
+
+#[async_trait]
+impl BuiltinTool for ServiceStatus {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_service_status",
+            "Get the status of a systemd unit: load/active state, main PID, restarts and timestamps.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "unit": {
+                        "type": "string",
+                        "description": "Unit name, e.g. nginx.service",
+                    },
+                },
+                "required": ["unit"],
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let unit = unit_arg(&args)?;
+        self.0.unit_status(&unit).await
+    }
+}
+
+pub struct FailedUnits(pub Systemd);
+
+#[async_trait]
+impl BuiltinTool for FailedUnits {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_service_failed",
+            "List systemd units that are in the failed state.",
+            json!({"type": "object", "properties": {}}),
+        )
+    }
+
+    async fn call(&self, _args: Value) -> Result<Value> {
+        let args = [
+            "list-units",
+            "--failed",
+            "--all",
+            "--plain",
+            "--no-legend",
+            "--no-pager",
+        ];
+        let output = self
+            .0
+            .run("systemctl", args.iter().map(|v| v.to_string()).collect())
+            .await?;
+        if !output.success {
+            bail!("systemctl list-units failed: {}", output.error_text());
+        }
+        let units: Vec<Value> = output
+            .stdout
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let unit = fields.next()?;
+                let (load, active, sub) = (fields.next()?, fields.next()?, fields.next()?);
+                Some(json!({
+                    "unit": unit,
+                    "load": load,
+                    "active": active,
+                    "sub": sub,
+                    "description": fields.collect::<Vec<_>>().join(" "),
+                }))
+            })
+            .collect();
+        Ok(json!({ "failed": units }))
+    }
+}
+
+pub struct JournalTail(pub Systemd);
+
+#[async_trait]
+impl BuiltinTool for JournalTail {
+    fn declaration(&self) -> FunctionDeclaration {
+        new_declaration(
+            "fio_journal_tail",
+            "Read recent systemd journal entries, optionally filtered by unit, time and priority.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "unit": {
+                        "type": "string",
+                        "description": "Only show entries of this unit, e.g. nginx.service",
+                    },
+                    "since": {
+                        "type": "string",
+                        "description": "Only show entries newer than this, e.g. '1 hour ago' or '2024-05-01 10:00'",
+                    },
+                    "priority": {
+                        "type": "string",
+                        "description": "Only show entries of this priority or more severe",
+                        "enum": JOURNAL_PRIORITIES,
+                    },
+                    "lines": {
+                        "type": "integer",
+                        "description": format!("Number of entries to return (at most {MAX_JOURNAL_LINES})"),
+                        "default": DEFAULT_JOURNAL_LINES,
+                    },
+                },
+            }),
+        )
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let lines = optional_usize(&args, "lines")?
+            .unwrap_or(DEFAULT_JOURNAL_LINES)
+            .clamp(1, MAX_JOURNAL_LINES);
+        let mut cmd_args = vec![
+            "--no-pager".to_string(),
+            "--quiet".into(),
+            "--output=short-iso".into(),
+            format!("--lines={lines}"),
+        ];
+        if optional_str(&args, "unit").is_some() {
+            cmd_args.push(format!("--unit={}", unit_arg(&args)?));
+        }
+        if let Some(since) = optional_str(&args, "since") {
+            cmd_args.push(format!("--since={since}"));
+        }
+        if let Some(priority) = optional_str(&args, "priority") {
+            let priority = priority.to_lowercase();
+            if !JOURNAL_PRIORITIES.contains(&priority.as_str()) {
+                bail!(
+                    "Invalid priority '{priority}', expected one of {}",
+                    JOURNAL_PRIORITIES.join(", ")
+                );
+            }
+            cmd_args.push(format!("--priority={priority}"));
+        }
+        let output = self.0.run("journalctl", cmd_args).await?;
+        if !output.success {
+            bail!("journalctl failed: {}", output.error_text());
+        }
+        Ok(json!({
+            "lines": output.stdout.lines().count(),
+            "content": output.stdout,
+        }))
+    }
+}
+
+/// A mutating `systemctl <action> <unit>` call.
+pub struct ServiceAction {
+    pub systemd: Systemd,
+    pub action: &'static str,
+}
+
+impl ServiceAction {
+    pub fn all(systemd: &Systemd) -> Vec<Self> {
+        ["restart", "reload", "enable", "disable"]
+            .into_iter()
+            .map(|action| Self {
+                systemd: systemd.clone(),
+                action,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BuiltinTool for ServiceAction {
+    fn declaration(&self) -> FunctionDeclaration {
+        let description = match self.action {
+            "restart" => "Restart a systemd unit (stops and starts it).",
+            "reload" => "Ask a systemd unit to reload its configuration without restarting.",
+            "enable" => "Enable a systemd unit so it starts at boot.",
+            _ => "Disable a systemd unit so it no longer starts at boot.",
+        };
+        new_declaration(
+            &format!("fio_service_{}", self.action),
+            description,
+            json!({
+                "type": "object",
+                "properties": {
+                    "unit": {
+                        "type": "string",
+                        "description": "Unit name, e.g. nginx.service",
+                    },
+                },
+                "required": ["unit"],
+            }),
+        )
+    }
+
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    async fn call(&self, args: Value) -> Result<Value> {
+        let unit = unit_arg(&args)?;
+        let output = self
+            .systemd
+            .run(
+                "systemctl",
+                vec![
+                    self.action.into(),
+                    "--no-ask-password".into(),
+                    "--no-pager".into(),
+                    "--".into(),
+                    unit.clone(),
+                ],
+            )
+            .await?;
+        if !output.success {
+            bail!(
+                "systemctl {} {unit} failed: {}",
+                self.action,
+                output.error_text()
+            );
+        }
+        let status = self.systemd.unit_status(&unit).await.ok();
+        Ok(json!({
+            "unit": unit,
+            "action": self.action,
+            "ok": true,
+            "output": format!("{}\n{}", output.stdout, output.stderr).trim(),
+            "status": status,
+        }))
+    }
+}
+
+fn unit_arg(args: &Value) -> Result<String> {
+    let unit = required_str(args, "unit")?;
+    let valid = !unit.starts_with('-')
+        && unit
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@._:-\\".contains(c));
+    if !valid {
+        bail!("Invalid unit name '{unit}'");
+    }
+    Ok(unit.to_string())
+}
+
+fn parse_properties(text: &str) -> IndexMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    /// A scratch directory with fake `systemctl`/`journalctl` scripts that log their arguments.
+    struct FakeBin {
+        dir: PathBuf,
+    }
+
+    impl FakeBin {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("fio-systemd-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let systemctl = r#"#!/bin/sh
+echo "systemctl $*" >> "$(dirname "$0")/calls.log"
+case "$1" in
+  show)
+    case "$*" in
+      *missing.service*) printf 'Id=missing.service\nLoadState=not-found\nActiveState=inactive\n' ;;
+      *) printf 'Id=nginx.service\nDescription=A high performance web server\nLoadState=loaded\nActiveState=active\nSubState=running\nMainPID=4242\nNRestarts=0\n' ;;
+    esac ;;
+  list-units)
+    printf 'backup.service loaded failed failed Nightly backup job\ncertbot.timer  loaded failed failed Renew certificates\n' ;;
+  restart)
+    case "$*" in
+      *broken.service*) echo "Job for broken.service failed." >&2; exit 1 ;;
+    esac ;;
+esac
+"#;
+            let journalctl = r#"#!/bin/sh
+echo "journalctl $*" >> "$(dirname "$0")/calls.log"
+printf '2024-05-01T10:00:00+0000 host nginx[4242]: started\n2024-05-01T10:00:01+0000 host nginx[4242]: ready\n'
+"#;
+            for (name, script) in [("systemctl", systemctl), ("journalctl", journalctl)] {
+                let path = dir.join(name);
+                fs::write(&path, script).unwrap();
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+            Self { dir }
+        }
+
+        fn systemd(&self) -> Systemd {
+            Systemd {
+                search_path: Some(format!("{}:/usr/bin:/bin", self.dir.display())),
+            }
+        }
+
+        fn calls(&self) -> Vec<String> {
+            fs::read_to_string(self.dir.join("calls.log"))
+                .unwrap_or_default()
+                .lines()
+                .map(|v| v.to_string())
+                .collect()
+        }
+    }
+
+    impl Drop for FakeBin {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_service_status() {
+        let fake = FakeBin::new("status");
+        let tool = ServiceStatus(fake.systemd());
+        let output = tool.call(json!({"unit": "nginx.service"})).await.unwrap();
+        assert_eq!(output["ActiveState"], "active");
+        assert_eq!(output["MainPID"], "4242");
+
+        let err = tool
+            .call(json!({"unit": "missing.service"}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        assert!(tool.call(json!({"unit": "--all"})).await.is_err());
+        assert!(tool.call(json!({"unit": "a b"})).await.is_err());
+        assert_eq!(fake.calls().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_units() {
+        let fake = FakeBin::new("failed");
+        let output = FailedUnits(fake.systemd()).call(json!({})).await.unwrap();
+        let failed = output["failed"].as_array().unwrap();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0]["unit"], "backup.service");
+        assert_eq!(failed[1]["description"], "Renew certificates");
+    }
+
+    #[tokio::test]
+    async fn test_journal_tail_filters() {
+        let fake = FakeBin::new("journal");
+        let tool = JournalTail(fake.systemd());
+        let output = tool
+            .call(json!({"unit": "nginx.service", "since": "1 hour ago", "priority": "ERR", "lines": 5}))
+            .await
+            .unwrap();
+        assert_eq!(output["lines"], 2);
+        assert_eq!(
+            fake.calls(),
+            ["journalctl --no-pager --quiet --output=short-iso --lines=5 --unit=nginx.service --since=1 hour ago --priority=err"]
+        );
+        assert!(tool.call(json!({"priority": "loud"})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_actions() {
+        let fake = FakeBin::new("actions");
+        let systemd = fake.systemd();
+        let actions = ServiceAction::all(&systemd);
+        assert!(actions.iter().all(|tool| !tool.read_only()));
+
+        let restart = &actions[0];
+        assert_eq!(restart.declaration().name, "fio_service_restart");
+        let output = restart
+            .call(json!({"unit": "nginx.service"}))
+            .await
+            .unwrap();
+        assert_eq!(output["ok"], true);
+        assert_eq!(output["status"]["ActiveState"], "active");
+        assert!(fake.calls()[0]
+            .starts_with("systemctl restart --no-ask-password --no-pager -- nginx.service"));
+
+        let err = restart
+            .call(json!({"unit": "broken.service"}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Job for broken.service failed."));
+    }
+}
@@ -37,6 +37,8 @@ pub struct ToolPermission {
     session_allowed: HashSet<String>,
     role_tool_call_permission: Option<String>,
     role_tool_permissions: Option<ToolPermissions>,
+    /// Whether a call may be confirmed on the terminal; without one, asking refuses.
+    interactive: bool,
 }
 
 impl ToolPermission {
@@ -59,13 +61,40 @@ impl ToolPermission {
             session_allowed,
             role_tool_call_permission,
             role_tool_permissions,
+            interactive: *IS_STDOUT_TERMINAL,
         }
     }
 
+    #[cfg(test)]
+    fn non_interactive(mut self) -> Self {
+        self.interactive = false;
+        self
+    }
+
     pub async fn check_permission(&mut self, tool_call: &ToolCall) -> Result<bool> {
         let tool_name = &tool_call.name;
+        let builtin_read_only = self
+            .config
+            .read()
+            .functions
+            .builtin_tool(tool_name)
+            .map(|tool| tool.read_only());
+        // Built-in tools that change the host are high-risk: only an explicit `allowed`
+        // entry skips confirmation, and a "for this session" answer is never remembered.
+        let high_risk = builtin_read_only == Some(false);
+
+        // The request's user profile is a hard limit that nothing below can lift.
+        let user = self.config.read().user.clone();
+        if let Some(user) = &user {
+            if !user.allows_tool(tool_name, builtin_read_only) {
+                if self.config.read().verbose_tool_calls {
+                    self.print_tool_call_info(tool_call, &format!("denied (user '{}')", user.name));
+                }
+                return Ok(false);
+            }
+        }
 
-        if self.session_allowed.contains(tool_name) {
+        if !high_risk && self.session_allowed.contains(tool_name) {
             if self.config.read().verbose_tool_calls {
                 self.print_tool_call_info(tool_call, "auto-allowed (session)");
             }
@@ -110,7 +139,11 @@ impl ToolPermission {
             .role_tool_permissions
             .as_ref()
             .or(global_tool_perms.as_ref());
-        if let Some(tool_perms) = tool_perms {
+        let tool_perms = match &user {
+            Some(user) => user.merge_tool_permissions(tool_perms),
+            None => tool_perms.cloned(),
+        };
+        if let Some(tool_perms) = &tool_perms {
             if let Some(denied) = &tool_perms.denied {
                 if self.matches_any_pattern(tool_name, denied) {
                     if verbose {
@@ -120,7 +153,13 @@ impl ToolPermission {
                 }
             }
             if let Some(allowed) = &tool_perms.allowed {
-                if self.matches_any_pattern(tool_name, allowed) {
+                // A glob such as `*` must not wave through a high-risk built-in.
+                let allowed = if high_risk {
+                    allowed.iter().any(|v| v == tool_name)
+                } else {
+                    self.matches_any_pattern(tool_name, allowed)
+                };
+                if allowed {
                     if verbose {
                         self.print_tool_call_info(tool_call, "auto-allowed (allowed list)");
                     }
@@ -129,11 +168,21 @@ impl ToolPermission {
             }
             if let Some(ask) = &tool_perms.ask {
                 if self.matches_any_pattern(tool_name, ask) {
-                    return self.prompt_user(tool_call).await;
+                    return self.prompt_user(tool_call, high_risk).await;
                 }
             }
         }
 
+        if high_risk {
+            return self.prompt_user(tool_call, true).await;
+        }
+        if builtin_read_only == Some(true) && default_permission != PermissionLevel::Never {
+            if verbose {
+                self.print_tool_call_info(tool_call, "auto-allowed (read-only built-in)");
+            }
+            return Ok(true);
+        }
+
         match default_permission {
             PermissionLevel::Always => {
                 if verbose {
@@ -147,12 +196,12 @@ impl ToolPermission {
                 }
                 Ok(false)
             }
-            PermissionLevel::Ask => self.prompt_user(tool_call).await,
+            PermissionLevel::Ask => self.prompt_user(tool_call, false).await,
         }
     }
 
-    async fn prompt_user(&mut self, tool_call: &ToolCall) -> Result<bool> {
-        if !*IS_STDOUT_TERMINAL {
+    async fn prompt_user(&mut self, tool_call: &ToolCall, high_risk: bool) -> Result<bool> {
+        if !self.interactive {
             // No interactive prompt available; fail closed.
             return Ok(false);
         }
@@ -170,6 +219,12 @@ impl ToolPermission {
         };
 
         println!();
+        if high_risk {
+            println!(
+                "{}",
+                dimmed_text("High-risk tool call detected; explicit confirmation required.")
+            );
+        }
         println!(
             "Can I run {} with the following arguments?\n{}",
             color_text(&tool_name, Color::Cyan),
@@ -177,7 +232,11 @@ impl ToolPermission {
         );
 
         let choice = tokio::task::spawn_blocking(move || {
-            let options = vec!["Yes (this time only)", "Yes (for this session)", "No"];
+            let options = if high_risk {
+                vec!["Yes (this time only)", "No"]
+            } else {
+                vec!["Yes (this time only)", "Yes (for this session)", "No"]
+            };
             Select::new("Allow this tool call?", options)
                 .with_help_message("Choose how to respond to this tool call")
                 .prompt()
@@ -212,33 +271,7 @@ impl ToolPermission {
     }
 
     fn matches_pattern(&self, tool_name: &str, pattern: &str) -> bool {
-        if pattern == tool_name {
-            return true;
-        }
-        if pattern.contains('*') {
-            // Special-case: pattern like "*" or "*****" matches anything.
-            if pattern.chars().all(|c| c == '*') {
-                return true;
-            }
-
-            // Treat patterns as *globs* (only `*` is wildcard). Escape all other regex metacharacters
-            // so users don't accidentally (or maliciously) inject regex.
-            let mut regex_pattern = String::new();
-            for (idx, part) in pattern.split('*').enumerate() {
-                if idx > 0 {
-                    regex_pattern.push_str(".*");
-                }
-                regex_pattern.push_str(&regex_escape(part));
-            }
-
-            let regex_pattern = format!("^{}$", regex_pattern);
-            if let Ok(re) = Regex::new(&regex_pattern) {
-                if let Ok(is_match) = re.is_match(tool_name) {
-                    return is_match;
-                }
-            }
-        }
-        false
+        glob_match(tool_name, pattern)
     }
 
     fn print_tool_call_info(&self, tool_call: &ToolCall, status: &str) {
@@ -250,10 +283,41 @@ impl ToolPermission {
     }
 }
 
+/// Match a tool name against an exact name or a `*` glob pattern.
+pub fn glob_match(tool_name: &str, pattern: &str) -> bool {
+    if pattern == tool_name {
+        return true;
+    }
+    if pattern.contains('*') {
+        // Special-case: pattern like "*" or "*****" matches anything.
+        if pattern.chars().all(|c| c == '*') {
+            return true;
+        }
+
+        // Treat patterns as *globs* (only `*` is wildcard). Escape all other regex metacharacters
+        // so users don't accidentally (or maliciously) inject regex.
+        let mut regex_pattern = String::new();
+        for (idx, part) in pattern.split('*').enumerate() {
+            if idx > 0 {
+                regex_pattern.push_str(".*");
+            }
+            regex_pattern.push_str(&regex_escape(part));
+        }
+
+        let regex_pattern = format!("^{}$", regex_pattern);
+        if let Ok(re) = Regex::new(&regex_pattern) {
+            if let Ok(is_match) = re.is_match(tool_name) {
+                return is_match;
+            }
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::Config;
+    use crate::config::{Config, RoleLike};
     use crate::mcp::McpServerConfig;
     use parking_lot::RwLock;
     use serde_json::json;
@@ -350,4 +414,122 @@ mod tests {
         };
         assert!(!perm.check_permission(&call).await.unwrap());
     }
+    struct FakeTool {
+        name: &'static str,
+        read_only: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::function::BuiltinTool for FakeTool {
+        fn declaration(&self) -> crate::function::FunctionDeclaration {
+            serde_json::from_value(json!({
+                "name": self.name,
+                "description": "",
+                "parameters": {"type": "object", "properties": {}},
+            }))
+            .unwrap()
+        }
+
+        fn read_only(&self) -> bool {
+            self.read_only
+        }
+
+        async fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value> {
+            Ok(json!("ok"))
+        }
+    }
+
+    fn config_with_builtins() -> GlobalConfig {
+        let config = create_config();
+        let mut tools = crate::function::BuiltinTools::default();
+        tools.register(FakeTool {
+            name: "fio_read",
+            read_only: true,
+        });
+        tools.register(FakeTool {
+            name: "fio_write",
+            read_only: false,
+        });
+        config.write().functions = crate::function::Functions::default().with_builtin_tools(tools);
+        config
+    }
+
+    fn call(name: &str) -> ToolCall {
+        ToolCall::new(name.to_string(), json!({}), None)
+    }
+
+    #[tokio::test]
+    async fn test_read_only_builtin_auto_allowed() {
+        let config = config_with_builtins();
+        config.write().tool_call_permission = Some("ask".to_string());
+        let mut perm = ToolPermission::new_with_role(&config, None, None);
+        assert!(perm.check_permission(&call("fio_read")).await.unwrap());
+
+        config.write().tool_call_permission = Some("never".to_string());
+        let mut perm = ToolPermission::new_with_role(&config, None, None);
+        assert!(!perm.check_permission(&call("fio_read")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mutating_builtin_requires_confirmation() {
+        let config = config_with_builtins();
+        {
+            let mut cfg = config.write();
+            cfg.tool_call_permission = Some("always".to_string());
+            cfg.conversation_tool_permissions
+                .insert("fio_write".to_string());
+        }
+        // Neither the global default nor a remembered session answer skips confirmation;
+        // without a terminal to confirm on, the call is refused.
+        let mut perm = ToolPermission::new_with_role(&config, None, None).non_interactive();
+        assert!(!perm.check_permission(&call("fio_write")).await.unwrap());
+
+        config.write().tool_permissions = Some(ToolPermissions {
+            allowed: Some(vec!["fio_write".to_string()]),
+            denied: None,
+            ask: None,
+        });
+        let mut perm = ToolPermission::new_with_role(&config, None, None);
+        assert!(perm.check_permission(&call("fio_write")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unattended_allows_read_only_builtins() {
+        let config = config_with_builtins();
+        let (config, role) = Config::init_unattended(
+            &config,
+            None,
+            None,
+            None,
+            &[],
+            crate::utils::create_abort_signal(),
+        )
+        .await
+        .unwrap();
+        let mut perm = ToolPermission::new_with_role(
+            &config,
+            role.tool_call_permission(),
+            role.tool_permissions(),
+        )
+        .non_interactive();
+        assert!(perm.check_permission(&call("fio_read")).await.unwrap());
+        assert!(!perm.check_permission(&call("fio_write")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_glob_allowed_skips_high_risk_builtins() {
+        let config = config_with_builtins();
+        {
+            let mut cfg = config.write();
+            cfg.tool_call_permission = Some("never".to_string());
+            cfg.tool_permissions = Some(ToolPermissions {
+                allowed: Some(vec!["*".to_string(), "fio_*".to_string()]),
+                denied: None,
+                ask: None,
+            });
+        }
+        let mut perm = ToolPermission::new_with_role(&config, None, None).non_interactive();
+        assert!(perm.check_permission(&call("fio_read")).await.unwrap());
+        assert!(!perm.check_permission(&call("fio_write")).await.unwrap());
+    }
 }
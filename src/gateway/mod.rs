@@ -0,0 +1,407 @@
+//! Chat-platform gateway.
+//!
+//! Each platform chat is mapped to a persistent `Session` on its own copy of the config,
+//! and every message is split and routed part by part like on the CLI; the replies to
+//! the parts of a compound message are sent back together. Turns are unattended: tool
+//! permissions are restricted with `Config::init_unattended`, so nothing ever prompts on
+//! the server terminal. When the resolver can't tell what a part is about, the reply asks
+//! the sender to pick from numbered candidates instead, and the rest of the message
+//! waits; a reply with just the number runs that part and then the rest. What the
+//! resolver learns from a turn goes to the sender's overlay, or the chat's when the
+//! sender has no user profile.
+
+pub mod telegram;
+
+use crate::client::call_chat_completions;
+use crate::config::{Config, GlobalConfig, Input, Role, RoleLike, ToolPermissions, UserIdentity};
+use crate::router::{
+    learn_intent, route_clarified, route_turn, split_turn, Clarification, TurnOperation, TurnRoute,
+};
+use crate::utils::{create_abort_signal, AbortSignal};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex as AsyncMutex;
+
+/// How chat turns are run; shared by all chats of a gateway.
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    pub role: Option<String>,
+    pub agent: Option<String>,
+    pub use_tools: Option<String>,
+    pub allowed_tools: Vec<String>,
+}
+
+/// Per-chat configs, created lazily and keyed by session name.
+pub struct ChatSessions {
+    config: GlobalConfig,
+    options: ChatOptions,
+    chats: Mutex<HashMap<String, Arc<AsyncMutex<Option<Chat>>>>>,
+}
+
+struct Chat {
+    config: GlobalConfig,
+    session_name: String,
+    /// The question of the last reply, if it asked which intent was meant.
+    pending: Option<Clarification>,
+    /// The parts of that message after the one being clarified, run once it is answered.
+    queued: Vec<String>,
+}
+
+impl ChatSessions {
+    pub fn new(config: &GlobalConfig, options: ChatOptions) -> Self {
+        Self {
+            config: config.clone(),
+            options,
+            chats: Default::default(),
+        }
+    }
+
+    /// Run one user message through the chat's session and return the reply text.
+    /// Messages of the same chat are handled one at a time; `user` is the sender's
+    /// profile, which limits this turn's role, tools and MCP servers.
+    pub async fn ask(
+        &self,
+        session_name: &str,
+        text: &str,
+        user: Option<UserIdentity>,
+    ) -> Result<String> {
+        if let Some(user) = &user {
+            if let Some(agent) = &self.options.agent {
+                user.guard_agent(agent)?;
+            } else if let Some(role) = &self.options.role {
+                user.guard_role(role)?;
+            }
+        }
+        let chat = self.chat(session_name);
+        let mut chat = chat.lock().await;
+        let chat = match chat.as_mut() {
+            Some(chat) => chat,
+            None => chat.insert(self.open(session_name).await?),
+        };
+        let model_id = user.as_ref().and_then(|v| v.profile.model.clone());
+        let namespace = user
+            .as_ref()
+            .map_or(session_name, |v| v.name.as_str())
+            .to_string();
+        if let Some(resolver) = chat.config.write().resolver.as_mut() {
+            resolver.set_overlay(Some(&namespace))?;
+        }
+        chat.config.write().user = user;
+        let abort_signal = create_abort_signal();
+        let picked = chat.pending.take().and_then(|clarification| {
+            let intent = clarification.pick(text)?;
+            Some((clarification, intent))
+        });
+        let queued = std::mem::take(&mut chat.queued);
+        let (mut next, parts) = match picked {
+            Some((clarification, intent)) => {
+                let route = route_clarified(&chat.config, &clarification, intent).await;
+                (Some(route), queued)
+            }
+            None => (None, split_turn(&chat.config, text)),
+        };
+        let mut parts = parts.into_iter();
+        let mut outputs = vec![];
+        loop {
+            // Each part is routed only once the parts before it ran.
+            let route = match next.take() {
+                Some(route) => Ok(route),
+                None => match parts.next() {
+                    Some(part) => route_turn(&chat.config, abort_signal.clone(), &part).await,
+                    None => break,
+                },
+            };
+            let ret = match route {
+                Ok(mut route) => match route.clarification.take() {
+                    Some(clarification) => {
+                        outputs.push(clarification.to_text());
+                        chat.pending = Some(clarification);
+                        chat.queued = parts.collect();
+                        break;
+                    }
+                    None => {
+                        chat.run(&route, model_id.clone(), abort_signal.clone())
+                            .await
+                    }
+                },
+                Err(err) => Err(err),
+            };
+            // An error stops the rest, but the replies of the parts that ran still count.
+            match ret {
+                Ok(output) => outputs.push(output),
+                Err(err) if outputs.is_empty() => return Err(err),
+                Err(err) => {
+                    warn!("Failed to run the rest of a compound message: {err:#}");
+                    outputs.push(format!("Failed to run the rest of the message: {err}"));
+                    break;
+                }
+            }
+        }
+        Ok(outputs.join("\n\n"))
+    }
+
+    /// Clear the conversation history of a chat.
+    pub async fn reset(&self, session_name: &str) -> Result<()> {
+        let chat = self.chat(session_name);
+        let mut chat = chat.lock().await;
+        let chat = match chat.as_mut() {
+            Some(chat) => chat,
+            None => chat.insert(self.open(session_name).await?),
+        };
+        chat.config.write().empty_session()?;
+        chat.save().await
+    }
+
+    fn chat(&self, session_name: &str) -> Arc<AsyncMutex<Option<Chat>>> {
+        self.chats
+            .lock()
+            .entry(session_name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    async fn open(&self, session_name: &str) -> Result<Chat> {
+        let (config, role) = Config::init_unattended(
+            &self.config,
+            self.options.role.as_deref(),
+            self.options.agent.as_deref(),
+            self.options.use_tools.as_deref(),
+            &self.options.allowed_tools,
+            create_abort_signal(),
+        )
+        .await?;
+        {
+            let mut cfg = config.write();
+            cfg.last_message = None;
+            cfg.use_session(Some(session_name))?;
+        }
+        // A stored session keeps the permissions it was created with; re-apply the
+        // current restrictions so config changes take effect.
+        restrict(&config, &role);
+        Ok(Chat {
+            config,
+            session_name: session_name.to_string(),
+            pending: None,
+            queued: vec![],
+        })
+    }
+}
+
+impl Chat {
+    /// Run a routed part, save the session and learn what it resolved to.
+    async fn run(
+        &mut self,
+        route: &TurnRoute,
+        model_id: Option<String>,
+        abort_signal: AbortSignal,
+    ) -> Result<String> {
+        let output = match route.operation.clone() {
+            Some(operation) => run_operation(&self.config, operation).await?,
+            None => {
+                let model_id = route.model_id.clone().or(model_id);
+                run_turn(&self.config, route, model_id, abort_signal).await?
+            }
+        };
+        self.save().await?;
+        if let Some(intent) = route.intent.as_ref().filter(|_| !route.learned) {
+            learn_intent(&self.config, intent, &route.input).await;
+        }
+        Ok(output)
+    }
+
+    async fn save(&mut self) -> Result<()> {
+        let need_compress = {
+            let cfg = self.config.read();
+            cfg.session
+                .as_ref()
+                .map(|v| v.need_compress(cfg.compress_threshold))
+                .unwrap_or_default()
+        };
+        if need_compress {
+            if let Err(err) = Config::compress_session(&self.config).await {
+                warn!("Failed to compress the session: {err}");
+            }
+        }
+        self.config.write().save_session(Some(&self.session_name))
+    }
+}
+
+fn restrict(config: &GlobalConfig, role: &Role) {
+    let mut cfg = config.write();
+    if let Some(role_like) = cfg.role_like_mut() {
+        role_like.set_use_tools(role.use_tools());
+        role_like.set_tool_call_permission(role.tool_call_permission());
+        role_like.set_tool_permissions(role.tool_permissions());
+    }
+}
+
+async fn run_turn(
+    config: &GlobalConfig,
+    route: &TurnRoute,
+    model_id: Option<String>,
+    abort_signal: AbortSignal,
+) -> Result<String> {
+    // Temporarily switch model and tools for this turn
+    let (prev_model, prev_use_tools) = {
+        let cfg = config.read();
+        (cfg.current_model().id(), cfg.extract_role().use_tools())
+    };
+    if let Some(id) = &model_id {
+        config.write().set_model(id)?;
+    }
+    if route.use_tools.is_some() {
+        config.write().set_use_tools(route.use_tools.clone());
+    }
+
+    // Nobody can confirm a tool call in an unattended turn, so a route that asks for
+    // confirmation only gets the read-only built-ins.
+    let role = route.confirm.then(|| read_only_role(config));
+    let ret = ask(config, &route.text, role, abort_signal).await;
+
+    if model_id.is_some() {
+        let _ = config.write().set_model(&prev_model);
+    }
+    if route.use_tools.is_some() {
+        config.write().set_use_tools(prev_use_tools);
+    }
+    ret
+}
+
+/// The current role, limited to the read-only built-in tools.
+fn read_only_role(config: &GlobalConfig) -> Role {
+    let cfg = config.read();
+    let mut role = cfg.extract_role();
+    let (read_only, mutating): (Vec<_>, Vec<_>) = cfg
+        .functions
+        .builtin_tools()
+        .iter()
+        .map(|(name, tool)| (name.to_string(), tool.read_only()))
+        .partition(|(_, read_only)| *read_only);
+    let names = |tools: Vec<(String, bool)>| tools.into_iter().map(|(name, _)| name).collect();
+    role.set_tool_call_permission(Some("never".into()));
+    role.set_tool_permissions(Some(ToolPermissions {
+        allowed: Some(names(read_only)),
+        denied: Some(names(mutating)),
+        ask: None,
+    }));
+    role
+}
+
+async fn ask(
+    config: &GlobalConfig,
+    text: &str,
+    role: Option<Role>,
+    abort_signal: AbortSignal,
+) -> Result<String> {
+    let mut input = Input::from_str(config, text, role);
+    loop {
+        let client = input.create_client()?;
+        config.write().before_chat_completion(&input)?;
+        let (output, tool_results) =
+            call_chat_completions(&input, false, false, client.as_ref(), abort_signal.clone())
+                .await?;
+        config
+            .write()
+            .after_chat_completion(&input, &output, &tool_results)?;
+        if tool_results.is_empty() {
+            return Ok(output);
+        }
+        input = input.merge_tool_results(output, tool_results);
+    }
+}
+
+async fn run_operation(config: &GlobalConfig, operation: TurnOperation) -> Result<String> {
+    match operation {
+        TurnOperation::ConnectMcpServer(server_name) => {
+            Config::mcp_connect_server(config, &server_name).await?;
+            Config::refresh_functions(config).await?;
+            Ok(format!("✓ Connected to MCP server '{server_name}'"))
+        }
+        TurnOperation::DisconnectMcpServer(server_name) => {
+            Config::mcp_disconnect_server(config, &server_name).await?;
+            Config::refresh_functions(config).await?;
+            Ok(format!("✓ Disconnected from MCP server '{server_name}'"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function::{eval_tool_calls, BuiltinTools, Functions, ToolCall};
+
+    #[tokio::test]
+    async fn test_chats_can_call_read_only_tools() {
+        let config: GlobalConfig = Default::default();
+        let tools = BuiltinTools::new(&Default::default(), &Default::default());
+        config.write().functions = Functions::default().with_builtin_tools(tools);
+        let sessions = ChatSessions::new(&config, ChatOptions::default());
+        let chat = sessions.open("gateway-read-only-test").await.unwrap();
+
+        let role = chat.config.read().extract_role();
+        let call = ToolCall::new("fio_system_info".into(), serde_json::json!({}), None);
+        let results = eval_tool_calls(
+            &chat.config,
+            vec![call],
+            role.tool_call_permission(),
+            role.tool_permissions(),
+        )
+        .await
+        .unwrap();
+        assert!(results[0].output.get("error").is_none());
+    }
+
+    struct Restart;
+
+    #[async_trait::async_trait]
+    impl crate::function::BuiltinTool for Restart {
+        fn declaration(&self) -> crate::function::FunctionDeclaration {
+            serde_json::from_value(serde_json::json!({
+                "name": "fio_restart",
+                "description": "",
+                "parameters": { "type": "object", "properties": {} },
+            }))
+            .unwrap()
+        }
+
+        fn read_only(&self) -> bool {
+            false
+        }
+
+        async fn call(&self, _args: serde_json::Value) -> Result<serde_json::Value> {
+            Ok(serde_json::json!("restarted"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirm_routes_only_get_read_only_tools() {
+        let config: GlobalConfig = Default::default();
+        let mut tools = BuiltinTools::new(&Default::default(), &Default::default());
+        tools.register(Restart);
+        config.write().functions = Functions::default().with_builtin_tools(tools);
+        let options = ChatOptions {
+            allowed_tools: vec!["fio_restart".into()],
+            ..Default::default()
+        };
+        let sessions = ChatSessions::new(&config, options);
+        let chat = sessions.open("gateway-confirm-test").await.unwrap();
+
+        let role = read_only_role(&chat.config);
+        let calls = vec![
+            ToolCall::new("fio_system_info".into(), serde_json::json!({}), None),
+            ToolCall::new("fio_restart".into(), serde_json::json!({}), None),
+        ];
+        let results = eval_tool_calls(
+            &chat.config,
+            calls,
+            role.tool_call_permission(),
+            role.tool_permissions(),
+        )
+        .await
+        .unwrap();
+        assert!(results[0].output.get("error").is_none());
+        assert_eq!(results[1].output["error"], "Permission denied");
+    }
+}
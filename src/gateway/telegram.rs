@@ -0,0 +1,589 @@
+//! Telegram Bot API long-polling gateway (`fio gateway`).
+
+use super::{ChatOptions, ChatSessions};
+use crate::config::GlobalConfig;
+use crate::render::{render_chat_chunks, strip_html, ChatFormat, CHAT_MAX_CHARS};
+use crate::utils::{new_turn_id, with_turn_id};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashSet, env, sync::Arc, time::Duration};
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+const DEFAULT_POLL_TIMEOUT: u64 = 30;
+const DEFAULT_SERVER_NAME: &str = "unknown-server";
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const THINKING_MESSAGE: &str = "🤔 Fio is thinking…";
+
+/// The `telegram` config section, shared with the `telegram/` bridge.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelegramConfig {
+    /// Falls back to the `TELEGRAM_BOT_TOKEN` environment variable.
+    pub telegram_bot_token: Option<String>,
+    /// Users allowed to talk to the bot; a list or a comma-separated string. Falls back
+    /// to the `ALLOWED_USER_IDS` environment variable. Everyone else is ignored.
+    #[serde(deserialize_with = "deserialize_user_ids")]
+    pub allowed_user_ids: Vec<i64>,
+    pub ops_channel_id: Option<String>,
+    /// Falls back to the `SERVER_NAME` environment variable.
+    pub server_name: Option<String>,
+    /// Prefix of the per-chat session names; defaults to `server_name`.
+    pub ai_service_session_namespace: Option<String>,
+    /// Bot API base url.
+    pub api_url: Option<String>,
+    /// Long-poll timeout in seconds.
+    pub poll_timeout: Option<u64>,
+    pub role: Option<String>,
+    pub agent: Option<String>,
+    /// Tools offered to the model; defaults to the read-only built-in tools.
+    pub use_tools: Option<String>,
+    /// Mutating built-in tools the model may call without confirmation.
+    pub allowed_tools: Vec<String>,
+}
+
+impl TelegramConfig {
+    pub fn bot_token(&self) -> Result<String> {
+        match &self.telegram_bot_token {
+            Some(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+            _ => env::var("TELEGRAM_BOT_TOKEN").map_err(|_| {
+                anyhow!("Missing Telegram bot token; set `telegram.telegram_bot_token` or TELEGRAM_BOT_TOKEN")
+            }),
+        }
+    }
+
+    pub fn allowed_user_ids(&self) -> HashSet<i64> {
+        if !self.allowed_user_ids.is_empty() {
+            return self.allowed_user_ids.iter().copied().collect();
+        }
+        env::var("ALLOWED_USER_IDS")
+            .map(|v| parse_user_ids(&v).into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn server_name(&self) -> String {
+        self.server_name
+            .clone()
+            .or_else(|| env::var("SERVER_NAME").ok())
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_SERVER_NAME.to_string())
+    }
+
+    pub fn session_namespace(&self) -> String {
+        self.ai_service_session_namespace
+            .clone()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| self.server_name())
+    }
+
+    fn chat_options(&self) -> ChatOptions {
+        ChatOptions {
+            role: self.role.clone(),
+            agent: self.agent.clone(),
+            use_tools: self.use_tools.clone(),
+            allowed_tools: self.allowed_tools.clone(),
+        }
+    }
+}
+
+fn deserialize_user_ids<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum UserIds {
+        List(Vec<i64>),
+        Text(String),
+        Id(i64),
+    }
+    Ok(match Option::<UserIds>::deserialize(deserializer)? {
+        Some(UserIds::List(v)) => v,
+        Some(UserIds::Text(v)) => parse_user_ids(&v),
+        Some(UserIds::Id(v)) => vec![v],
+        None => vec![],
+    })
+}
+
+fn parse_user_ids(value: &str) -> Vec<i64> {
+    value
+        .split(',')
+        .filter_map(|v| v.trim().parse().ok())
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    #[serde(default)]
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Message {
+    pub message_id: i64,
+    pub chat: Chat,
+    #[serde(default)]
+    pub from: Option<User>,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// Minimal Telegram Bot API client.
+#[derive(Debug, Clone)]
+pub struct BotApi {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl BotApi {
+    pub fn new(api_url: &str, token: &str, poll_timeout: u64) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(poll_timeout + 15))
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: format!("{}/bot{token}", api_url.trim_end_matches('/')),
+        })
+    }
+
+    pub async fn get_updates(&self, offset: i64, timeout: u64) -> Result<Vec<Update>> {
+        self.call(
+            "getUpdates",
+            json!({ "offset": offset, "timeout": timeout, "allowed_updates": ["message"] }),
+        )
+        .await
+    }
+
+    pub async fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+        parse_mode: Option<&str>,
+    ) -> Result<Message> {
+        let mut body = json!({ "chat_id": chat_id, "text": text });
+        if let Some(parse_mode) = parse_mode {
+            body["parse_mode"] = parse_mode.into();
+        }
+        self.call("sendMessage", body).await
+    }
+
+    pub async fn edit_message_text(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+        parse_mode: Option<&str>,
+    ) -> Result<()> {
+        let mut body = json!({ "chat_id": chat_id, "message_id": message_id, "text": text });
+        if let Some(parse_mode) = parse_mode {
+            body["parse_mode"] = parse_mode.into();
+        }
+        self.call::<Value>("editMessageText", body).await?;
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: Value) -> Result<T> {
+        let res = self
+            .client
+            .post(format!("{}/{method}", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .context("Failed to reach the Telegram Bot API")?;
+        let status = res.status();
+        let data: Value = res.json().await.unwrap_or_default();
+        if !status.is_success() || data["ok"] != true {
+            let description = data["description"].as_str().unwrap_or_default();
+            bail!("Telegram {method} failed ({status}): {description}");
+        }
+        serde_json::from_value(data["result"].clone())
+            .with_context(|| format!("Invalid Telegram {method} response"))
+    }
+}
+
+/// Routes Telegram messages from allowed users into per-chat sessions.
+#[derive(Clone)]
+pub struct TelegramGateway {
+    api: BotApi,
+    config: GlobalConfig,
+    allowed_user_ids: Arc<HashSet<i64>>,
+    server_name: String,
+    session_namespace: String,
+    poll_timeout: u64,
+    sessions: Arc<ChatSessions>,
+}
+
+impl TelegramGateway {
+    pub fn new(config: &GlobalConfig) -> Result<Self> {
+        let telegram = config.read().telegram.clone();
+        // Users with a profile are allowed too.
+        let mut allowed_user_ids = telegram.allowed_user_ids();
+        for profile in config.read().users.values() {
+            allowed_user_ids.extend(&profile.telegram_user_ids);
+        }
+        let poll_timeout = telegram.poll_timeout.unwrap_or(DEFAULT_POLL_TIMEOUT);
+        let api = BotApi::new(
+            telegram.api_url.as_deref().unwrap_or(TELEGRAM_API_URL),
+            &telegram.bot_token()?,
+            poll_timeout,
+        )?;
+        Ok(Self {
+            api,
+            config: config.clone(),
+            allowed_user_ids: Arc::new(allowed_user_ids),
+            server_name: telegram.server_name(),
+            session_namespace: telegram.session_namespace(),
+            poll_timeout,
+            sessions: Arc::new(ChatSessions::new(config, telegram.chat_options())),
+        })
+    }
+
+    pub fn allowed_user_count(&self) -> usize {
+        self.allowed_user_ids.len()
+    }
+
+    /// Long-poll for updates forever, handling each message on its own task.
+    pub async fn run(&self) -> Result<()> {
+        let mut offset = 0;
+        loop {
+            match self.api.get_updates(offset, self.poll_timeout).await {
+                Ok(updates) => {
+                    for update in updates {
+                        offset = offset.max(update.update_id + 1);
+                        let gateway = self.clone();
+                        // Each update is one turn for log correlation.
+                        tokio::spawn(with_turn_id(new_turn_id(), async move {
+                            if let Err(err) = gateway.handle_update(update).await {
+                                warn!("Failed to handle Telegram update: {err:#}");
+                            }
+                        }));
+                    }
+                }
+                Err(err) => {
+                    warn!("{err:#}");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    pub async fn handle_update(&self, update: Update) -> Result<()> {
+        let Some(message) = update.message else {
+            return Ok(());
+        };
+        let Some(from) = &message.from else {
+            return Ok(());
+        };
+        if !self.allowed_user_ids.contains(&from.id) {
+            info!(
+                "Ignoring message from unauthorized user: {} ({})",
+                from.id,
+                from.username.as_deref().unwrap_or("-")
+            );
+            return Ok(());
+        }
+        let text = message.text.as_deref().unwrap_or_default().trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+        let chat_id = message.chat.id;
+        debug!("telegram message chat={chat_id} from={}: {text}", from.id);
+
+        match parse_command(text) {
+            Some("start") | Some("help") => {
+                let help = format!(
+                    "Hi, I'm Fio, the Telegram gateway for \"{}\". Send me a message and I'll answer from this server.\n\nCommands:\n/reset – clear conversation history",
+                    self.server_name
+                );
+                self.api.send_message(chat_id, &help, None).await?;
+            }
+            Some("reset") => {
+                let reply = match self.sessions.reset(&self.session_name(chat_id)).await {
+                    Ok(()) => "Persistent conversation context cleared for this chat.".into(),
+                    Err(err) => format!("⚠️ Failed to clear the conversation: {err}"),
+                };
+                self.api.send_message(chat_id, &reply, None).await?;
+            }
+            _ => {
+                let thinking = self
+                    .api
+                    .send_message(chat_id, THINKING_MESSAGE, None)
+                    .await?;
+                let user = self.config.read().user_by_telegram_id(from.id);
+                let session_name = self.session_name(chat_id);
+                let reply = match self.sessions.ask(&session_name, text, user).await {
+                    Ok(output) => output,
+                    Err(err) => {
+                        warn!("Telegram chat {chat_id}: {err:#}");
+                        format!("⚠️ Error talking to Fio: {err}")
+                    }
+                };
+                self.reply(chat_id, thinking.message_id, &reply).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The first chunk replaces the "thinking" message; the rest follow as new messages.
+    /// A chunk Telegram rejects as HTML is sent again as plain text.
+    async fn reply(&self, chat_id: i64, message_id: i64, text: &str) -> Result<()> {
+        let text = if text.trim().is_empty() {
+            "(empty reply)"
+        } else {
+            text
+        };
+        let chunks = render_chat_chunks(text, ChatFormat::Html, CHAT_MAX_CHARS);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let message_id = (i == 0).then_some(message_id);
+            if let Err(err) = self
+                .send_chunk(chat_id, message_id, chunk, Some("HTML"))
+                .await
+            {
+                warn!("Telegram chat {chat_id}: {err:#}; sending as plain text");
+                self.send_chunk(chat_id, message_id, &strip_html(chunk), None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_chunk(
+        &self,
+        chat_id: i64,
+        message_id: Option<i64>,
+        text: &str,
+        parse_mode: Option<&str>,
+    ) -> Result<()> {
+        match message_id {
+            Some(message_id) => {
+                self.api
+                    .edit_message_text(chat_id, message_id, text, parse_mode)
+                    .await
+            }
+            None => {
+                self.api.send_message(chat_id, text, parse_mode).await?;
+                Ok(())
+            }
+        }
+    }
+
+    fn session_name(&self, chat_id: i64) -> String {
+        format!("{}-telegram-{chat_id}", self.session_namespace)
+    }
+}
+
+/// `/reset@my_bot args` -> `reset`.
+fn parse_command(text: &str) -> Option<&str> {
+    let command = text.strip_prefix('/')?.split_whitespace().next()?;
+    Some(command.split('@').next().unwrap_or(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use mockito::Matcher;
+    use parking_lot::RwLock;
+
+    fn gateway(server: &mockito::Server, allowed_user_ids: Vec<i64>) -> TelegramGateway {
+        let config = Config {
+            telegram: TelegramConfig {
+                telegram_bot_token: Some("TOKEN".into()),
+                allowed_user_ids,
+                server_name: Some("box".into()),
+                api_url: Some(server.url()),
+                poll_timeout: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        TelegramGateway::new(&Arc::new(RwLock::new(config))).unwrap()
+    }
+
+    fn update(update_id: i64, user_id: i64, text: &str) -> Update {
+        serde_json::from_value(json!({
+            "update_id": update_id,
+            "message": {
+                "message_id": 7,
+                "chat": {"id": -100, "type": "group"},
+                "from": {"id": user_id, "is_bot": false, "username": "ops"},
+                "text": text
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_telegram_config() {
+        let config: TelegramConfig = serde_yaml::from_str(
+            r#"
+telegram_bot_token: abc
+allowed_user_ids: "123456789, 987654321,x"
+server_name: myserver
+ai_service_api_url: http://127.0.0.1:8000/v1/chat/completions
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.allowed_user_ids, vec![123456789, 987654321]);
+        assert_eq!(config.bot_token().unwrap(), "abc");
+        assert_eq!(config.session_namespace(), "myserver");
+
+        let config: TelegramConfig =
+            serde_yaml::from_str("allowed_user_ids: [1, 2]\nai_service_session_namespace: ns\n")
+                .unwrap();
+        assert_eq!(config.allowed_user_ids, vec![1, 2]);
+        assert_eq!(config.session_namespace(), "ns");
+
+        let config: TelegramConfig = serde_yaml::from_str("allowed_user_ids: 5\n").unwrap();
+        assert_eq!(config.allowed_user_ids, vec![5]);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/start"), Some("start"));
+        assert_eq!(parse_command("/reset@fio_bot now"), Some("reset"));
+        assert_eq!(parse_command("hello /start"), None);
+        assert_eq!(parse_command("/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_updates() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/botTOKEN/getUpdates")
+            .match_body(Matcher::PartialJson(json!({"offset": 42, "timeout": 1})))
+            .with_body(
+                json!({
+                    "ok": true,
+                    "result": [
+                        {"update_id": 42, "message": {"message_id": 1, "chat": {"id": 9}, "from": {"id": 1}, "text": "hi"}},
+                        {"update_id": 43, "edited_message": {}}
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let api = BotApi::new(&server.url(), "TOKEN", 1).unwrap();
+        let updates = api.get_updates(42, 1).await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(updates.len(), 2);
+        assert_eq!(
+            updates[0].message.as_ref().unwrap().text.as_deref(),
+            Some("hi")
+        );
+        assert!(updates[1].message.is_none());
+
+        server
+            .mock("POST", "/botTOKEN/getUpdates")
+            .with_status(401)
+            .with_body(r#"{"ok":false,"description":"Unauthorized"}"#)
+            .create_async()
+            .await;
+        let err = api.get_updates(0, 1).await.unwrap_err();
+        assert!(err.to_string().contains("Unauthorized"));
+    }
+
+    #[tokio::test]
+    async fn test_ignores_unauthorized_users() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+        let gateway = gateway(&server, vec![1]);
+        gateway.handle_update(update(1, 2, "/start")).await.unwrap();
+        gateway
+            .handle_update(update(2, 2, "restart nginx"))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_start_command() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/botTOKEN/sendMessage")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::PartialJson(json!({"chat_id": -100})),
+                Matcher::Regex(r#"gateway for \\"box\\""#.into()),
+            ]))
+            .with_body(
+                json!({"ok": true, "result": {"message_id": 8, "chat": {"id": -100}}}).to_string(),
+            )
+            .create_async()
+            .await;
+        let gateway = gateway(&server, vec![1]);
+        gateway
+            .handle_update(update(1, 1, "/start@fio_bot"))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_reply_chunks() {
+        let mut server = mockito::Server::new_async().await;
+        let edit = server
+            .mock("POST", "/botTOKEN/editMessageText")
+            .match_body(Matcher::PartialJson(
+                json!({"chat_id": 9, "message_id": 3, "parse_mode": "HTML"}),
+            ))
+            .with_body(r#"{"ok":true,"result":true}"#)
+            .create_async()
+            .await;
+        let send = server
+            .mock("POST", "/botTOKEN/sendMessage")
+            .match_body(Matcher::PartialJson(
+                json!({"chat_id": 9, "parse_mode": "HTML"}),
+            ))
+            .with_body(
+                json!({"ok": true, "result": {"message_id": 4, "chat": {"id": 9}}}).to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let gateway = gateway(&server, vec![1]);
+        let text = format!("{}\n{}", "a".repeat(4090), "b < c");
+        gateway.reply(9, 3, &text).await.unwrap();
+        edit.assert_async().await;
+        send.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_reply_falls_back_to_plain_text() {
+        let mut server = mockito::Server::new_async().await;
+        let html = server
+            .mock("POST", "/botTOKEN/editMessageText")
+            .match_body(Matcher::PartialJson(json!({"parse_mode": "HTML"})))
+            .with_status(400)
+            .with_body(r#"{"ok":false,"description":"Bad Request: can't parse entities"}"#)
+            .create_async()
+            .await;
+        let plain = server
+            .mock("POST", "/botTOKEN/editMessageText")
+            .match_body(Matcher::Json(
+                json!({"chat_id": 9, "message_id": 3, "text": "a < b & c"}),
+            ))
+            .with_body(r#"{"ok":true,"result":true}"#)
+            .create_async()
+            .await;
+        let gateway = gateway(&server, vec![1]);
+        gateway.reply(9, 3, "**a** < b & c").await.unwrap();
+        html.assert_async().await;
+        plain.assert_async().await;
+    }
+}
@@ -0,0 +1,227 @@
+//! Liveness/readiness checks for `--serve` and systemd `sd_notify` integration.
+
+use crate::client::init_client;
+use crate::config::{Config, GlobalConfig};
+use crate::mcp::auth::OAuthStatusKind;
+use crate::rag::Rag;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+
+/// A RAG file's modification time and the check result for that version.
+type RagCheck = (SystemTime, Result<String, String>);
+
+/// RAG check results by file, so probes don't re-read unchanged stores.
+static RAG_CHECKS: LazyLock<Mutex<HashMap<PathBuf, RagCheck>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: String, ret: Result<String, String>) -> Self {
+        let (ok, detail) = match ret {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        Self { name, ok, detail }
+    }
+}
+
+/// Run every readiness check: the model client, enabled MCP servers (connected, and
+/// holding a usable token when they use OAuth) and the RAG stores.
+pub async fn readiness_checks(config: &GlobalConfig) -> Vec<Check> {
+    let mut checks = vec![];
+
+    let ret = init_client(config, None)
+        .map(|v| v.model().id())
+        .map_err(|err| format!("{err:#}"));
+    checks.push(Check::new("model".into(), ret));
+
+    let (servers, manager) = {
+        let cfg = config.read();
+        let servers: Vec<_> = cfg
+            .mcp_servers
+            .iter()
+            .filter(|v| v.enabled)
+            .cloned()
+            .collect();
+        (servers, cfg.mcp_manager.clone())
+    };
+    let connected = match &manager {
+        Some(manager) => manager.list_servers().await,
+        None => vec![],
+    };
+    for server in servers {
+        let is_connected = connected
+            .iter()
+            .any(|(name, connected, _)| *name == server.name && *connected);
+        let ret = if !is_connected {
+            Err("not connected".to_string())
+        } else if let (Some(manager), true) = (
+            &manager,
+            server
+                .auth
+                .as_ref()
+                .and_then(|v| v.oauth_config())
+                .is_some(),
+        ) {
+            match manager.oauth_status(&server.name).await {
+                Ok(status) => match status.kind {
+                    OAuthStatusKind::TokenValid | OAuthStatusKind::TokenExpiredRefreshable => {
+                        Ok(format!("connected, oauth {}", status.kind.as_str()))
+                    }
+                    kind => Err(format!("oauth {}", kind.as_str())),
+                },
+                Err(err) => Err(format!("{err:#}")),
+            }
+        } else {
+            Ok("connected".to_string())
+        };
+        checks.push(Check::new(format!("mcp:{}", server.name), ret));
+    }
+
+    for name in Config::list_rags() {
+        let path = config.read().rag_file(&name);
+        let ret = check_rag(config, &name, path).await;
+        checks.push(Check::new(format!("rag:{name}"), ret));
+    }
+
+    checks
+}
+
+/// Parse the RAG file off the runtime, reusing the last result while the file is unchanged.
+async fn check_rag(config: &GlobalConfig, name: &str, path: PathBuf) -> Result<String, String> {
+    let modified = std::fs::metadata(&path)
+        .and_then(|v| v.modified())
+        .map_err(|err| format!("Failed to read '{}': {err}", path.display()))?;
+    let cached: Option<RagCheck> = RAG_CHECKS.lock().get(&path).cloned();
+    if let Some((time, ret)) = cached {
+        if time == modified {
+            return ret;
+        }
+    }
+    let ret = {
+        let (config, name, path) = (config.clone(), name.to_string(), path.clone());
+        tokio::task::spawn_blocking(move || Rag::verify(&config, &name, &path))
+            .await
+            .map_err(|err| err.to_string())?
+            .map(|_| "loaded".to_string())
+            .map_err(|err| format!("{err:#}"))
+    };
+    let check: RagCheck = (modified, ret.clone());
+    RAG_CHECKS.lock().insert(path, check);
+    ret
+}
+
+/// Send a state (e.g. `READY=1`) to systemd when started with `Type=notify`.
+/// A no-op without `NOTIFY_SOCKET`.
+pub fn sd_notify(state: &str) {
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(err) = sd_notify_to(&path, state) {
+        warn!("Failed to notify systemd: {err}");
+    }
+}
+
+/// Send `state` to the notify socket at `path`; `@name` is an abstract socket.
+#[cfg(target_os = "linux")]
+fn sd_notify_to(path: &str, state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let addr = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(path),
+    }?;
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sd_notify_to(_path: &str, _state: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Tell systemd the service is ready and, when `WatchdogSec=` is set, keep pinging the
+/// watchdog at half its interval for as long as the runtime is responsive.
+pub fn start_sd_notify() {
+    sd_notify("READY=1");
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    tokio::spawn(async move {
+        loop {
+            sd_notify("WATCHDOG=1");
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sd_notify() {
+        let dir = std::env::temp_dir().join(format!("fiochat-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let socket = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        sd_notify_to(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let n = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_check_rag_caches_until_modified() {
+        let dir = std::env::temp_dir().join(format!("fiochat-readyz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("docs.yaml");
+        std::fs::write(&path, "not: [valid").unwrap();
+        let config = GlobalConfig::default();
+        let ret = check_rag(&config, "docs", path.clone()).await;
+        assert!(ret.unwrap_err().contains("Failed to load rag 'docs'"));
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        RAG_CHECKS
+            .lock()
+            .insert(path.clone(), (modified, Ok("cached".into())));
+        let ret = check_rag(&config, "docs", path).await;
+        assert_eq!(ret.unwrap(), "cached");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_check_new() {
+        let check = Check::new("model".into(), Err("Invalid model 'x'".into()));
+        assert!(!check.ok);
+        assert_eq!(
+            serde_json::to_value(&check).unwrap(),
+            serde_json::json!({ "name": "model", "ok": false, "detail": "Invalid model 'x'" })
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! Alert webhooks for `--serve`.
+//!
+//! An alert posted to `/v1/hooks/<name>` is parsed into an [`Incident`], handed to the
+//! hook's role or agent for a triage turn that may only use read-only tools, and the
+//! resulting summary is delivered through `fio-notify`, named notify sinks or an
+//! outbound webhook.
+
+use crate::client::call_chat_completions_headless;
+use crate::config::{Config, GlobalConfig, Input, Macro};
+use crate::notify::{deliver, DeliveryConfig};
+use crate::utils::create_abort_signal;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+const DEFAULT_MESSAGE: &str = "[{{status}}] {{title}}\n\n{{triage}}";
+const DEFAULT_PROMPT: &str = r#"An alert arrived through the "{{hook}}" webhook.
+
+Status: {{status}}
+Severity: {{severity}}
+Title: {{title}}
+Summary: {{summary}}
+
+Alerts:
+{{alerts}}
+
+Investigate with the tools available, then reply with a short triage summary: the likely cause, the impact, and the next steps."#;
+/// Raw payloads are shared with the model; keep them from dominating the prompt.
+const MAX_PAYLOAD_CHARS: usize = 8000;
+/// Triage turns running at once; senders retry the incidents turned away meanwhile.
+pub const MAX_RUNNING_TRIAGES: usize = 4;
+/// Alertmanager and Grafana re-post firing alerts; a repeat within this long is ignored.
+const DEDUPE_WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFormat {
+    Alertmanager,
+    Grafana,
+    #[default]
+    Generic,
+}
+
+impl HookFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookFormat::Alertmanager => "alertmanager",
+            HookFormat::Grafana => "grafana",
+            HookFormat::Generic => "generic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HookConfig {
+    pub format: HookFormat,
+    /// Role used for the triage turn.
+    pub role: Option<String>,
+    /// Agent used for the triage turn; takes precedence over `role`.
+    pub agent: Option<String>,
+    /// Triage prompt template; `{{var}}` placeholders are filled from the incident.
+    pub prompt: Option<String>,
+    /// Shared secret expected as `Authorization: Bearer <token>` or `?token=<token>`.
+    pub token: Option<String>,
+    /// Accept requests without credentials when no `token` is set.
+    pub allow_unauthenticated: bool,
+    /// Tools offered to the model; defaults to the read-only built-in tools.
+    pub use_tools: Option<String>,
+    /// Extra tools (patterns) the triage turn may call without confirmation.
+    pub allowed_tools: Vec<String>,
+    /// Also run a triage turn for resolved alerts instead of just forwarding them.
+    pub triage_resolved: bool,
+    pub notify: DeliveryConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Alert {
+    pub status: String,
+    pub name: String,
+    pub severity: String,
+    pub summary: String,
+    pub labels: IndexMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub id: String,
+    pub hook: String,
+    pub format: HookFormat,
+    pub status: String,
+    pub title: String,
+    pub severity: String,
+    pub summary: String,
+    pub alerts: Vec<Alert>,
+    /// Top-level scalar fields of a generic payload, exposed as template variables.
+    pub extra: IndexMap<String, String>,
+    pub payload: Value,
+    pub received_at: String,
+}
+
+impl Incident {
+    pub fn parse(hook: &str, format: HookFormat, payload: Value) -> Result<Self> {
+        if !payload.is_object() {
+            bail!("Expected a JSON object");
+        }
+        let mut incident = Self {
+            id: format!("inc-{}", &uuid::Uuid::new_v4().simple().to_string()[..12]),
+            hook: hook.to_string(),
+            format,
+            status: String::new(),
+            title: String::new(),
+            severity: String::new(),
+            summary: String::new(),
+            alerts: vec![],
+            extra: IndexMap::new(),
+            payload: Value::Null,
+            received_at: Utc::now().to_rfc3339(),
+        };
+        match format {
+            HookFormat::Alertmanager => incident.parse_alertmanager(&payload)?,
+            HookFormat::Grafana => {
+                if payload.get("alerts").is_some_and(|v| v.is_array()) {
+                    incident.parse_alertmanager(&payload)?;
+                    if let Some(title) = str_field(&payload, &["title"]) {
+                        incident.title = title;
+                    }
+                    if incident.summary.is_empty() {
+                        incident.summary = str_field(&payload, &["message"]).unwrap_or_default();
+                    }
+                } else {
+                    incident.parse_grafana_legacy(&payload);
+                }
+            }
+            HookFormat::Generic => incident.parse_generic(&payload),
+        }
+        if incident.title.is_empty() {
+            incident.title = format!("Alert from {hook}");
+        }
+        if incident.status.is_empty() {
+            incident.status = "firing".into();
+        }
+        incident.payload = payload;
+        Ok(incident)
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.status == "resolved"
+    }
+
+    /// Identifies repeats of the same incident: the hook, status, title and each alert's
+    /// status, name and labels. Ids, timestamps and annotations are left out.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        (&self.hook, &self.status, &self.title).hash(&mut hasher);
+        for alert in &self.alerts {
+            (&alert.status, &alert.name).hash(&mut hasher);
+            for label in &alert.labels {
+                label.hash(&mut hasher);
+            }
+        }
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Variables available to prompt, message and payload templates.
+    pub fn variables(&self) -> IndexMap<String, String> {
+        let mut vars = self.extra.clone();
+        let alerts = self
+            .alerts
+            .iter()
+            .map(|alert| {
+                let mut line = format!("- [{}] {}", alert.status, alert.name);
+                if !alert.severity.is_empty() {
+                    line.push_str(&format!(" (severity={})", alert.severity));
+                }
+                if !alert.summary.is_empty() {
+                    line.push_str(&format!(": {}", alert.summary));
+                }
+                if !alert.labels.is_empty() {
+                    line.push_str(&format!("; labels: {}", format_labels(&alert.labels)));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let labels = self
+            .alerts
+            .first()
+            .map(|v| format_labels(&v.labels))
+            .unwrap_or_default();
+        let mut payload = serde_json::to_string_pretty(&self.payload).unwrap_or_default();
+        if payload.chars().count() > MAX_PAYLOAD_CHARS {
+            payload = payload.chars().take(MAX_PAYLOAD_CHARS).collect();
+            payload.push_str("\n...(truncated)");
+        }
+        for (key, value) in [
+            ("id", self.id.clone()),
+            ("hook", self.hook.clone()),
+            ("format", self.format.name().to_string()),
+            ("status", self.status.clone()),
+            ("title", self.title.clone()),
+            ("severity", self.severity.clone()),
+            ("summary", self.summary.clone()),
+            ("alerts", alerts),
+            ("alert_count", self.alerts.len().to_string()),
+            ("labels", labels),
+            ("payload", payload),
+            ("received_at", self.received_at.clone()),
+        ] {
+            vars.insert(key.to_string(), value);
+        }
+        vars
+    }
+
+    fn parse_alertmanager(&mut self, payload: &Value) -> Result<()> {
+        let alerts = payload
+            .get("alerts")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| anyhow!("Missing 'alerts' array"))?;
+        let common_labels = payload.get("commonLabels").cloned().unwrap_or_default();
+        let common_annotations = payload
+            .get("commonAnnotations")
+            .cloned()
+            .unwrap_or_default();
+        self.status = normalize_status(&str_field(payload, &["status"]).unwrap_or_default());
+        self.alerts = alerts
+            .iter()
+            .map(|alert| {
+                let labels = string_map(alert.get("labels"));
+                let annotations = alert.get("annotations").cloned().unwrap_or_default();
+                Alert {
+                    status: normalize_status(
+                        &str_field(alert, &["status"]).unwrap_or_else(|| self.status.clone()),
+                    ),
+                    name: labels.get("alertname").cloned().unwrap_or_default(),
+                    severity: labels.get("severity").cloned().unwrap_or_default(),
+                    summary: str_field(&annotations, &["summary", "description", "message"])
+                        .unwrap_or_default(),
+                    labels,
+                }
+            })
+            .collect();
+        let first = self.alerts.first();
+        self.title = str_field(&common_labels, &["alertname"])
+            .or_else(|| first.map(|v| v.name.clone()).filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        self.severity = str_field(&common_labels, &["severity"])
+            .or_else(|| first.map(|v| v.severity.clone()).filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        self.summary = str_field(&common_annotations, &["summary", "description"])
+            .or_else(|| first.map(|v| v.summary.clone()).filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    fn parse_grafana_legacy(&mut self, payload: &Value) {
+        self.title = str_field(payload, &["title", "ruleName"]).unwrap_or_default();
+        self.status = normalize_status(&str_field(payload, &["state"]).unwrap_or_default());
+        self.summary = str_field(payload, &["message"]).unwrap_or_default();
+        let tags = string_map(payload.get("tags"));
+        self.severity = tags.get("severity").cloned().unwrap_or_default();
+        let rule_name = str_field(payload, &["ruleName", "title"]).unwrap_or_default();
+        self.alerts = payload
+            .get("evalMatches")
+            .and_then(|v| v.as_array())
+            .map(|matches| {
+                matches
+                    .iter()
+                    .map(|item| {
+                        let mut labels = string_map(item.get("tags"));
+                        if let Some(metric) = str_field(item, &["metric"]) {
+                            labels.insert("metric".into(), metric);
+                        }
+                        if let Some(value) = item.get("value").filter(|v| !v.is_null()) {
+                            labels.insert("value".into(), scalar_string(value));
+                        }
+                        Alert {
+                            status: self.status.clone(),
+                            name: rule_name.clone(),
+                            severity: self.severity.clone(),
+                            summary: String::new(),
+                            labels,
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    fn parse_generic(&mut self, payload: &Value) {
+        if let Some(object) = payload.as_object() {
+            for (key, value) in object {
+                if !value.is_object() && !value.is_array() && !value.is_null() {
+                    self.extra.insert(key.clone(), scalar_string(value));
+                }
+            }
+        }
+        self.title =
+            str_field(payload, &["title", "summary", "subject", "name"]).unwrap_or_default();
+        self.summary =
+            str_field(payload, &["message", "text", "description", "body"]).unwrap_or_default();
+        self.severity = str_field(payload, &["severity", "level", "priority"]).unwrap_or_default();
+        self.status =
+            normalize_status(&str_field(payload, &["status", "state"]).unwrap_or_default());
+        self.alerts = vec![Alert {
+            status: self.status.clone(),
+            name: self.title.clone(),
+            severity: self.severity.clone(),
+            summary: self.summary.clone(),
+            labels: string_map(payload.get("labels")),
+        }];
+    }
+}
+
+impl HookConfig {
+    /// Check the request credentials against the configured token. A hook without a
+    /// token refuses every request unless it sets `allow_unauthenticated`.
+    pub fn authorize(&self, authorization: Option<&str>, query: Option<&str>) -> bool {
+        let Some(token) = self.token.as_deref() else {
+            return self.allow_unauthenticated;
+        };
+        let bearer = authorization
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim);
+        let query_token = query.and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+                .map(|v| {
+                    urlencoding::decode(v)
+                        .map(|v| v.into_owned())
+                        .unwrap_or_default()
+                })
+        });
+        let matches = |v: Option<&str>| v.is_some_and(|v| constant_time_eq(v, token));
+        // Both are checked so the response time doesn't tell which one was sent.
+        matches(bearer) | matches(query_token.as_deref())
+    }
+}
+
+/// Compare without returning early, so the time taken doesn't reveal the matching prefix.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let diff = (0..a.len().max(b.len())).fold(a.len() ^ b.len(), |diff, i| {
+        diff | usize::from(a.get(i).copied().unwrap_or(0) ^ b.get(i).copied().unwrap_or(0))
+    });
+    diff == 0
+}
+
+/// Whether an incident posted to a hook should be triaged.
+#[derive(Debug)]
+pub enum Admission {
+    /// Triage it, holding the permit until done.
+    Accepted(OwnedSemaphorePermit),
+    /// The same incident was accepted within the dedupe window.
+    Duplicate,
+    /// Too many triage turns are running.
+    Busy,
+}
+
+/// Bounds the triage turns the server runs and drops repeats of recent incidents.
+pub struct IncidentQueue {
+    permits: Arc<Semaphore>,
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl IncidentQueue {
+    pub fn new(max_running: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_running)),
+            seen: Default::default(),
+        }
+    }
+
+    pub fn admit(&self, incident: &Incident) -> Admission {
+        let fingerprint = incident.fingerprint();
+        let now = Instant::now();
+        let mut seen = self.seen.lock();
+        seen.retain(|_, at| now.duration_since(*at) < DEDUPE_WINDOW);
+        if seen.contains_key(&fingerprint) {
+            return Admission::Duplicate;
+        }
+        // Only accepted incidents are remembered, so one turned away can be retried.
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            return Admission::Busy;
+        };
+        seen.insert(fingerprint, now);
+        Admission::Accepted(permit)
+    }
+}
+
+/// Triage an incident and deliver the summary. Runs in the background of the server.
+pub async fn handle_incident(config: GlobalConfig, hook: HookConfig, incident: Incident) {
+    let triage = if incident.is_resolved() && !hook.triage_resolved {
+        Ok(incident.summary.clone())
+    } else {
+        triage(&config, &hook, &incident).await
+    };
+    let triage = match triage {
+        Ok(triage) => triage,
+        Err(err) => {
+            error!("Failed to triage incident {}: {err}", incident.id);
+            format!("Automatic triage failed: {err}")
+        }
+    };
+    let mut variables = incident.variables();
+    variables.insert("triage".into(), triage);
+    let sinks = config.read().notify_sinks.clone();
+    match deliver(&sinks, &hook.notify, variables, DEFAULT_MESSAGE).await {
+        Ok(()) => info!(
+            "Delivered incident {} from hook '{}'",
+            incident.id, incident.hook
+        ),
+        Err(err) => error!("Failed to deliver incident {}: {err}", incident.id),
+    }
+}
+
+pub async fn triage(
+    config: &GlobalConfig,
+    hook: &HookConfig,
+    incident: &Incident,
+) -> Result<String> {
+    let abort_signal = create_abort_signal();
+    let (config, role) = Config::init_unattended(
+        config,
+        hook.role.as_deref(),
+        hook.agent.as_deref(),
+        hook.use_tools.as_deref(),
+        &hook.allowed_tools,
+        abort_signal.clone(),
+    )
+    .await?;
+    let prompt = Macro::interpolate_command(
+        hook.prompt.as_deref().unwrap_or(DEFAULT_PROMPT),
+        &incident.variables(),
+    );
+    let input = Input::from_str(&config, &prompt, Some(role));
+    let output = call_chat_completions_headless(input, abort_signal).await?;
+    Ok(output.trim().to_string())
+}
+
+fn str_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        value
+            .get(key)
+            .filter(|v| !v.is_null() && !v.is_object() && !v.is_array())
+            .map(scalar_string)
+            .filter(|v| !v.is_empty())
+    })
+}
+
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn string_map(value: Option<&Value>) -> IndexMap<String, String> {
+    value
+        .and_then(|v| v.as_object())
+        .map(|map| {
+            map.iter()
+                .map(|(k, v)| (k.clone(), scalar_string(v)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn format_labels(labels: &IndexMap<String, String>) -> String {
+    labels
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn normalize_status(status: &str) -> String {
+    match status.trim().to_lowercase().as_str() {
+        "" => String::new(),
+        "firing" | "alerting" | "alert" | "problem" | "triggered" | "open" => "firing".into(),
+        "resolved" | "ok" | "normal" | "recovered" | "closed" => "resolved".into(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_alertmanager() {
+        let payload = json!({
+            "status": "firing",
+            "commonLabels": {"alertname": "HighCPU", "severity": "critical"},
+            "commonAnnotations": {"summary": "CPU above 90%"},
+            "alerts": [
+                {"status": "firing", "labels": {"alertname": "HighCPU", "instance": "web-1"}, "annotations": {}},
+                {"status": "resolved", "labels": {"alertname": "HighCPU", "instance": "web-2"}, "annotations": {"summary": "back to normal"}}
+            ]
+        });
+        let incident = Incident::parse("prom", HookFormat::Alertmanager, payload).unwrap();
+        assert_eq!(incident.title, "HighCPU");
+        assert_eq!(incident.severity, "critical");
+        assert_eq!(incident.summary, "CPU above 90%");
+        assert_eq!(incident.alerts.len(), 2);
+        assert_eq!(incident.alerts[1].status, "resolved");
+        let vars = incident.variables();
+        assert_eq!(vars["status"], "firing");
+        assert_eq!(
+            vars["alerts"],
+            "- [firing] HighCPU; labels: alertname=HighCPU, instance=web-1\n\
+             - [resolved] HighCPU: back to normal; labels: alertname=HighCPU, instance=web-2"
+        );
+
+        assert!(Incident::parse("prom", HookFormat::Alertmanager, json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_grafana() {
+        let legacy = json!({
+            "title": "[Alerting] Disk full",
+            "ruleName": "Disk full",
+            "state": "alerting",
+            "message": "Root volume is almost full",
+            "evalMatches": [{"metric": "disk_used", "value": 97.5, "tags": {"host": "db-1"}}]
+        });
+        let incident = Incident::parse("grafana", HookFormat::Grafana, legacy).unwrap();
+        assert_eq!(incident.title, "[Alerting] Disk full");
+        assert_eq!(incident.status, "firing");
+        assert_eq!(incident.summary, "Root volume is almost full");
+        assert_eq!(incident.alerts[0].labels["value"], "97.5");
+
+        let unified = json!({
+            "status": "resolved",
+            "title": "[RESOLVED] Latency",
+            "message": "p99 latency is back",
+            "alerts": [{"status": "resolved", "labels": {"alertname": "Latency"}, "annotations": {}}]
+        });
+        let incident = Incident::parse("grafana", HookFormat::Grafana, unified).unwrap();
+        assert_eq!(incident.title, "[RESOLVED] Latency");
+        assert!(incident.is_resolved());
+        assert_eq!(incident.summary, "p99 latency is back");
+    }
+
+    #[test]
+    fn test_parse_generic() {
+        let payload = json!({"subject": "Backup failed", "body": "exit 1", "level": "warning", "host": "nas"});
+        let incident = Incident::parse("backup", HookFormat::Generic, payload).unwrap();
+        assert_eq!(incident.title, "Backup failed");
+        assert_eq!(incident.summary, "exit 1");
+        assert_eq!(incident.severity, "warning");
+        assert_eq!(incident.status, "firing");
+        assert_eq!(incident.variables()["host"], "nas");
+
+        let incident = Incident::parse("empty", HookFormat::Generic, json!({})).unwrap();
+        assert_eq!(incident.title, "Alert from empty");
+        assert!(Incident::parse("bad", HookFormat::Generic, json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_incident_queue() {
+        let payload = json!({
+            "status": "firing",
+            "alerts": [{"status": "firing", "labels": {"alertname": "HighCPU", "instance": "web-1"}, "annotations": {"summary": "CPU at 91%"}}]
+        });
+        let parse =
+            |payload: Value| Incident::parse("prom", HookFormat::Alertmanager, payload).unwrap();
+        let first = parse(payload.clone());
+        let mut repeat = payload.clone();
+        repeat["alerts"][0]["annotations"]["summary"] = "CPU at 95%".into();
+        let mut other = payload.clone();
+        other["alerts"][0]["labels"]["instance"] = "web-2".into();
+        let mut resolved = payload;
+        resolved["status"] = "resolved".into();
+        resolved["alerts"][0]["status"] = "resolved".into();
+
+        let queue = IncidentQueue::new(1);
+        let Admission::Accepted(permit) = queue.admit(&first) else {
+            panic!("first incident should be accepted");
+        };
+        assert!(matches!(queue.admit(&parse(repeat)), Admission::Duplicate));
+        assert!(matches!(
+            queue.admit(&parse(other.clone())),
+            Admission::Busy
+        ));
+        drop(permit);
+        assert!(matches!(queue.admit(&parse(other)), Admission::Accepted(_)));
+        assert!(matches!(
+            queue.admit(&parse(resolved)),
+            Admission::Accepted(_)
+        ));
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert!(!HookConfig::default().authorize(None, None));
+        let open = HookConfig {
+            allow_unauthenticated: true,
+            ..Default::default()
+        };
+        assert!(open.authorize(None, None));
+
+        let hook = HookConfig {
+            token: Some("s3cr3t".into()),
+            ..Default::default()
+        };
+        assert!(hook.authorize(Some("Bearer s3cr3t"), None));
+        assert!(hook.authorize(None, Some("a=1&token=s3cr3t")));
+        assert!(!hook.authorize(Some("Bearer wrong"), None));
+        assert!(!hook.authorize(None, Some("token=")));
+        assert!(!hook.authorize(None, None));
+        assert!(!hook.authorize(Some("Bearer s3cr3"), None));
+        assert!(!hook.authorize(Some("Bearer s3cr3t!"), None));
+    }
+}
@@ -16,9 +16,13 @@ use crate::config::{
 use crate::function::FunctionDeclaration;
 use crate::render::render_error;
 use crate::resolver::{extract_linear_workspace_slug_from_url, is_workspace_slug, Resolver};
-use crate::router::{role_for_route, route_turn, TurnOperation};
+use crate::router::{
+    explain_route, learn_intent, role_for_route, route_turn, split_turn, RoutingRules,
+    TurnOperation,
+};
 use crate::utils::{
-    abortable_run_with_spinner, create_abort_signal, dimmed_text, set_text, temp_file, AbortSignal,
+    abortable_run_with_spinner, create_abort_signal, dimmed_text, new_turn_id, set_text, temp_file,
+    with_turn_id, AbortSignal,
 };
 
 use anyhow::{bail, Context, Result};
@@ -39,7 +43,7 @@ use std::sync::LazyLock;
 const MENU_NAME: &str = "completion_menu";
 const SUSPEND_HOST_COMMAND: &str = "__fiochat_internal_suspend__";
 
-static INTERACTIVE_COMMANDS: LazyLock<[InteractiveCommand; 40]> = LazyLock::new(|| {
+static INTERACTIVE_COMMANDS: LazyLock<[InteractiveCommand; 42]> = LazyLock::new(|| {
     [
         InteractiveCommand::new(".help", "Show this help guide", AssertState::pass()),
         InteractiveCommand::new(".info", "Show system info", AssertState::pass()),
@@ -189,6 +193,11 @@ static INTERACTIVE_COMMANDS: LazyLock<[InteractiveCommand; 40]> = LazyLock::new(
             AssertState::pass(),
         ),
         InteractiveCommand::new(".copy", "Copy last response", AssertState::pass()),
+        InteractiveCommand::new(
+            ".notify",
+            "Send a message or the last response to a notify sink",
+            AssertState::pass(),
+        ),
         InteractiveCommand::new(".mcp", "Manage MCP servers/tools", AssertState::pass()),
         InteractiveCommand::new(
             ".linear",
@@ -200,6 +209,11 @@ static INTERACTIVE_COMMANDS: LazyLock<[InteractiveCommand; 40]> = LazyLock::new(
             "Manage intent resolver (provider/workspace/action aliases)",
             AssertState::pass(),
         ),
+        InteractiveCommand::new(
+            ".route",
+            "Explain which routing rule a message would fire",
+            AssertState::pass(),
+        ),
         InteractiveCommand::new(".set", "Modify runtime settings", AssertState::pass()),
         InteractiveCommand::new(
             ".thinking",
@@ -262,9 +276,12 @@ impl InteractiveMode {
                         continue;
                     }
                     self.abort_signal.reset();
-                    match run_interactive_command(&self.config, self.abort_signal.clone(), &line)
-                        .await
-                    {
+                    let ret = with_turn_id(
+                        new_turn_id(),
+                        run_interactive_command(&self.config, self.abort_signal.clone(), &line),
+                    )
+                    .await;
+                    match ret {
                         Ok(exit) => {
                             if exit {
                                 break;
@@ -767,6 +784,26 @@ pub async fn run_interactive_command(
                 };
                 set_text(&output).context("Failed to copy the last chat response")?;
             }
+            ".notify" => match split_first_arg(args) {
+                Some((name, text)) => {
+                    let message = match text {
+                        Some(text) => text.to_string(),
+                        None => match config
+                            .read()
+                            .last_message
+                            .as_ref()
+                            .filter(|v| !v.output.is_empty())
+                        {
+                            Some(v) => v.output.clone(),
+                            None => bail!("No chat response to send"),
+                        },
+                    };
+                    let sinks = config.read().notify_sinks.clone();
+                    crate::notify::send(&sinks, name, &message).await?;
+                    println!("✓ Sent to '{name}'");
+                }
+                None => println!("Usage: /notify <sink> [text]  (sends the last response without text)"),
+            },
             ".mcp" => match split_first_arg(args) {
                 Some(("list", None)) => {
                     let servers = Config::mcp_list_servers(config).await;
@@ -788,6 +825,13 @@ pub async fn run_interactive_command(
                 Some(("connect", Some(server_name))) => {
                     Config::mcp_connect_server(config, server_name).await?;
                     Config::refresh_functions(config).await?;
+                    match Config::sync_resolver_providers(config, Some(server_name)).await {
+                        Ok(synced) => print_synced_workspaces(&synced),
+                        Err(err) => warn!(
+                            "Failed to sync resolver workspaces for '{}': {}",
+                            server_name, err
+                        ),
+                    }
                     println!("✓ Connected to MCP server '{}'", server_name);
                 }
                 Some(("disconnect", Some(server_name))) => {
@@ -911,7 +955,78 @@ Commands:
             ".linear" => {
                 handle_linear_command(config, args).await?;
             }
+            ".route" => match split_first_arg(args) {
+                Some(("explain", Some(text))) => {
+                    println!("{}", explain_route(config, text));
+                }
+                Some(("reload", _)) => {
+                    let rules = RoutingRules::load(&Config::routing_file())?;
+                    let count = rules.rules.len();
+                    config.write().routing_rules = rules;
+                    println!("✓ Loaded {count} routing rules");
+                }
+                _ => println!(
+                    "Usage: /route <command>
+
+Commands:
+  explain <text>  - Show which routing rule fires for a message and the resulting route
+  reload          - Reload the routing rules from {}",
+                    Config::routing_file().display()
+                ),
+            },
             ".resolver" => match split_first_arg(args) {
+                Some(("sync", server)) => {
+                    if config.read().resolver_providers.is_empty() {
+                        println!(
+                            "No providers configured; add them under `resolver_providers` in the config file."
+                        );
+                    } else {
+                        let synced =
+                            Config::sync_resolver_providers(config, server.map(str::trim)).await?;
+                        if synced.is_empty() {
+                            println!("No connected MCP server matches `resolver_providers`.");
+                        }
+                        print_synced_workspaces(&synced);
+                    }
+                }
+                Some(("overlays", _)) => match config.read().resolver.as_ref() {
+                    None => println!("Resolver not initialized"),
+                    Some(r) => {
+                        let overlays = r.overlays()?;
+                        if overlays.is_empty() {
+                            println!("No resolver overlays yet.");
+                        }
+                        for (namespace, pending) in overlays {
+                            println!("{namespace} ({} aliases to promote)", pending.len());
+                            for v in pending {
+                                println!("  {v}");
+                            }
+                        }
+                    }
+                },
+                Some(("promote", Some(rest))) => {
+                    let (namespace, alias) = split_first_arg(Some(rest)).unwrap_or((rest, None));
+                    let mut resolver = config
+                        .read()
+                        .resolver
+                        .clone()
+                        .ok_or_else(|| anyhow::anyhow!("Resolver not initialized"))?;
+                    let promoted = resolver.promote(namespace, alias)?;
+                    if promoted.is_empty() {
+                        println!("Nothing to promote from '{namespace}'.");
+                    } else {
+                        resolver.save()?;
+                        config.write().resolver = Some(resolver);
+                        println!("✓ Promoted to the base store:");
+                        for v in promoted {
+                            println!("  {v}");
+                        }
+                    }
+                }
+                Some(("stats", _)) => match config.read().resolver.as_ref() {
+                    None => println!("Resolver not initialized"),
+                    Some(r) => print!("{}", r.stats()),
+                },
                 Some(("list", _)) => {
                     let resolver = config.read().resolver.clone();
                     match resolver {
@@ -963,6 +1078,29 @@ Commands:
                                     );
                                 }
                             }
+                            if !r.store.examples.is_empty() {
+                                println!("\nExamples:");
+                                for example in &r.store.examples.items {
+                                    let workspace = example
+                                        .workspace
+                                        .as_deref()
+                                        .map(|v| format!("/{v}"))
+                                        .unwrap_or_default();
+                                    let embedded = if example.vector.is_empty() {
+                                        " (not embedded)"
+                                    } else {
+                                        ""
+                                    };
+                                    println!(
+                                        "  \"{}\" → {}{} {}{}",
+                                        example.text,
+                                        example.provider,
+                                        workspace,
+                                        example.action.as_deref().unwrap_or("-"),
+                                        embedded
+                                    );
+                                }
+                            }
                         }
                     }
                 }
@@ -1013,13 +1151,45 @@ Commands:
                         update_resolver(config, |r| r.add_action(name, alias))?;
                         println!("✓ Action '{name}' alias '{alias}' added");
                     }
+                    Some(("example", Some(rest))) => {
+                        let mut parts = rest.trim().splitn(3, ' ');
+                        let target = parts.next().unwrap_or("").trim();
+                        let action = parts.next().unwrap_or("").trim();
+                        let text = parts.next().unwrap_or("").trim();
+                        if target.is_empty() || action.is_empty() || text.is_empty() {
+                            bail!(
+                                "Usage: /resolver learn example <provider>[/<workspace>] <action|-> <text>"
+                            );
+                        }
+                        let (provider, workspace) = match target.split_once('/') {
+                            Some((provider, workspace)) => (provider, Some(workspace)),
+                            None => (target, None),
+                        };
+                        let action = Some(action).filter(|v| *v != "-");
+                        let mut resolver = config
+                            .read()
+                            .resolver
+                            .clone()
+                            .ok_or_else(|| anyhow::anyhow!("Resolver not initialized"))?;
+                        resolver.add_example(text, provider, workspace, action)?;
+                        if let Err(e) = resolver.embed_examples(config).await {
+                            warn!("Resolver: failed to embed examples: {e}");
+                        }
+                        resolver.save()?;
+                        config.write().resolver = Some(resolver);
+                        println!("✓ Example '{text}' added");
+                        if config.read().rag_embedding_model.is_none() {
+                            println!("Set `rag_embedding_model` to match turns against examples.");
+                        }
+                    }
                     _ => println!(
                         "Usage: /resolver learn <type> <args>
 
 Types:
   provider <name> [alias]               - Add or update a provider
   workspace <provider> <name> [alias] [profile=<mcp-server>] - Add or update a workspace
-  action <name> <alias>                 - Add an alias to an action"
+  action <name> <alias>                 - Add an alias to an action
+  example <provider>[/<workspace>] <action|-> <text> - Add a labelled example utterance"
                     ),
                 },
                 Some(("forget", Some(rest))) => match split_first_arg(Some(rest)) {
@@ -1061,13 +1231,25 @@ Types:
                             Ok(())
                         })?;
                     }
+                    Some(("example", Some(text))) => {
+                        let text = text.trim();
+                        update_resolver(config, |r| {
+                            if r.remove_example(text) {
+                                println!("✓ Example '{text}' removed");
+                            } else {
+                                println!("Example '{text}' not found");
+                            }
+                            Ok(())
+                        })?;
+                    }
                     _ => println!(
                         "Usage: /resolver forget <type> <args>
 
 Types:
   provider <name>               - Remove a provider and all its workspaces
   workspace <provider> <name>   - Remove a workspace
-  action <name>                 - Remove an action"
+  action <name>                 - Remove an action
+  example <text>                - Remove a labelled example"
                     ),
                 },
                 _ => println!(
@@ -1075,12 +1257,18 @@ Types:
 
 Commands:
   list                                - List all resolver entries
+  stats                               - Show decayed usage scores and learned alias ages
+  sync [server]                       - Discover workspaces with `resolver_providers`
+  overlays                            - List per-user/per-chat overlays and their new aliases
+  promote <namespace> [alias]         - Move an overlay's aliases (or one) into the base store
   learn provider <name> [alias]       - Add or update a provider alias
   learn workspace <p> <name> [alias] [profile=<mcp-server>] - Add or update a workspace alias/profile
   learn action <name> <alias>         - Add an action alias
+  learn example <p>[/<ws>] <action|-> <text> - Add a labelled example utterance
   forget provider <name>              - Remove a provider
   forget workspace <provider> <name>  - Remove a workspace
-  forget action <name>                - Remove an action"
+  forget action <name>                - Remove an action
+  forget example <text>               - Remove a labelled example"
                 ),
             },
             ".exit" => match args {
@@ -1114,38 +1302,33 @@ Commands:
             _ => unknown_command()?,
         },
         None => {
-            let route = route_turn(config, abort_signal.clone(), line).await?;
-
-            if let Some(operation) = route.operation.clone() {
-                execute_route_operation(config, &route, operation).await?;
-                return Ok(false);
-            }
+            // A compound line runs as several turns, in order, each routed right before it
+            // runs; an error stops the rest.
+            for part in split_turn(config, line) {
+                let route = route_turn(config, abort_signal.clone(), &part).await?;
+                if let Some(operation) = route.operation.clone() {
+                    execute_route_operation(config, &route, operation).await?;
+                    continue;
+                }
 
-            // Temporarily switch model for this turn
-            let prev_model = config.read().current_model().id();
-            if let Some(ref id) = route.model_id {
-                config.write().set_model(id)?;
-            }
+                // Temporarily switch model for this turn
+                let prev_model = config.read().current_model().id();
+                if let Some(ref id) = route.model_id {
+                    config.write().set_model(id)?;
+                }
 
-            let route_role = role_for_route(config, &route);
-            let input = Input::from_str(config, &route.text, route_role);
-            ask(config, abort_signal.clone(), input, true).await?;
+                let route_role = role_for_route(config, &route);
+                let input = Input::from_str(config, &route.text, route_role);
+                ask(config, abort_signal.clone(), input, true).await?;
 
-            // Restore model
-            if route.model_id.is_some() {
-                let _ = config.write().set_model(&prev_model);
-            }
+                // Restore model
+                if route.model_id.is_some() {
+                    let _ = config.write().set_model(&prev_model);
+                }
 
-            // Learn from successful resolution (not called if ask() errored).
-            if let Some(intent) = route.intent {
-                let cloned = config.read().resolver.clone();
-                if let Some(mut r) = cloned {
-                    r.learn(&intent);
-                    if let Err(e) = r.save() {
-                        warn!("Resolver: failed to save after learning: {e}");
-                    } else {
-                        config.write().resolver = Some(r);
-                    }
+                // Learn from successful resolution (not called if ask() errored).
+                if let Some(intent) = route.intent.as_ref().filter(|_| !route.learned) {
+                    learn_intent(config, intent, &route.input).await;
                 }
             }
         }
@@ -1158,6 +1341,18 @@ Commands:
     Ok(false)
 }
 
+fn print_synced_workspaces(synced: &[(String, String, Vec<String>)]) {
+    for (provider, server_name, learned) in synced {
+        println!(
+            "Learned {} {} workspace(s) from '{}': {}",
+            learned.len(),
+            provider,
+            server_name,
+            learned.join(", ")
+        );
+    }
+}
+
 async fn execute_route_operation(
     config: &GlobalConfig,
     route: &crate::router::TurnRoute,
@@ -1224,7 +1419,10 @@ async fn execute_route_operation(
     if let Some(intent) = route.intent.clone() {
         let cloned = config.read().resolver.clone();
         if let Some(mut r) = cloned {
-            r.learn(&intent);
+            r.learn(&intent, &route.input);
+            if let Err(e) = r.embed_examples(config).await {
+                warn!("Resolver: failed to embed examples: {e}");
+            }
             if let Err(e) = r.save() {
                 warn!("Resolver: failed to save after learning: {e}");
             } else {
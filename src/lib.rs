@@ -5,13 +5,20 @@ pub mod cli;
 pub mod client;
 pub mod config;
 pub mod function;
+pub mod gateway;
+pub mod health;
+pub mod hooks;
 pub mod interactive;
 pub mod mcp;
+pub mod metrics;
+pub mod notify;
 pub mod rag;
 pub mod render;
 pub mod resolver;
 pub mod router;
+pub mod scheduler;
 pub mod serve;
+pub mod telemetry;
 
 #[macro_use]
 pub mod utils;
@@ -2,20 +2,29 @@ mod cli;
 mod client;
 mod config;
 mod function;
+mod gateway;
+mod health;
+mod hooks;
 mod interactive;
 mod mcp;
+mod metrics;
+mod notify;
 mod rag;
 mod render;
 mod resolver;
 mod router;
+mod scheduler;
 mod serve;
+mod telemetry;
 #[macro_use]
 mod utils;
 
 #[macro_use]
 extern crate log;
 
-use crate::cli::Cli;
+use crate::cli::{
+    Cli, ResolverCli, ResolverCommand, ResolverEvalArgs, ResolverExportArgs, ResolverImportArgs,
+};
 use crate::client::{
     call_chat_completions, call_chat_completions_streaming, list_models, ModelType,
 };
@@ -23,16 +32,21 @@ use crate::config::{
     ensure_parent_exists, list_agents, load_env_file, macro_execute, Config, GlobalConfig, Input,
     Role, WorkingMode, CODE_ROLE, EXPLAIN_SHELL_ROLE, SHELL_ROLE, TEMP_SESSION_NAME,
 };
+use crate::gateway::telegram::TelegramGateway;
 use crate::interactive::InteractiveMode;
 use crate::render::render_error;
+use crate::resolver::eval::{self, AiMode, EvalReport};
+use crate::resolver::vocabulary::{self, ImportMode, Vocabulary};
+use crate::resolver::Resolver;
 use crate::router::{
-    role_for_route, route_turn, select_route_model, TurnOperation, TurnPolicy, TurnRoute,
+    learn_intent, role_for_route, route_turn, select_route_model, split_turn, TurnOperation,
+    TurnPolicy, TurnRoute,
 };
 use crate::utils::*;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use chrono::{Duration, Local, Utc};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use inquire::Text;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -51,6 +65,8 @@ enum UtilityCommand {
     Arm,
     Disarm,
     Doctor,
+    Daemon,
+    Gateway,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -62,7 +78,26 @@ struct ArmStateStore {
 #[tokio::main]
 async fn main() -> Result<()> {
     load_env_file()?;
+    if is_resolver_command(&env::args().collect::<Vec<_>>()) {
+        let cli = ResolverCli::parse_from(env::args().skip(1));
+        if let Err(err) = run_resolver_command(cli).await {
+            render_error(err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     if let Some(command) = parse_utility_command() {
+        if matches!(command, UtilityCommand::Daemon | UtilityCommand::Gateway) {
+            let ret = match command {
+                UtilityCommand::Gateway => run_gateway().await,
+                _ => run_daemon().await,
+            };
+            if let Err(err) = ret {
+                render_error(err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
         handle_utility_command(command)?;
         return Ok(());
     }
@@ -90,7 +125,10 @@ async fn main() -> Result<()> {
         || cli.list_sessions;
     setup_logger(working_mode.is_serve())?;
     let config = Arc::new(RwLock::new(Config::init(working_mode, info_flag).await?));
-    if let Err(err) = run(config, cli, text, default_policy).await {
+    telemetry::init(&config.read().otel)?;
+    let ret = with_turn_id(new_turn_id(), run(config, cli, text, default_policy)).await;
+    telemetry::flush().await;
+    if let Err(err) = ret {
         render_error(err);
         std::process::exit(1);
     }
@@ -250,63 +288,32 @@ async fn run(
         // One-shot path: route through the router when policy is Auto
         if effective_policy == TurnPolicy::Auto {
             if let Some(ref input_text) = text {
-                let route = route_turn(&config, abort_signal.clone(), input_text).await?;
-
-                if let Some(operation) = route.operation.clone() {
-                    execute_route_operation(&config, &route, operation).await?;
-                    return Ok(());
-                }
-
-                // Apply routed model
-                if let Some(ref id) = route.model_id {
-                    config.write().set_model(id)?;
-                }
-
-                // Apply routed policy
-                let routed_policy = route.policy;
-                if matches!(routed_policy, TurnPolicy::Plan | TurnPolicy::Execute) {
-                    config.write().use_role(SHELL_ROLE)?;
-                }
-                let route_role = role_for_route(&config, &route);
-
-                match routed_policy {
-                    TurnPolicy::Plan | TurnPolicy::Execute => {
-                        let input = create_input(
-                            &config,
-                            Some(route.text),
-                            &cli.file,
-                            abort_signal.clone(),
-                            route_role,
-                        )
-                        .await?;
-                        let auto_armed = scope_is_armed().unwrap_or(false);
-                        let execute_without_confirm =
-                            routed_policy == TurnPolicy::Execute || auto_armed;
-                        shell_execute(
-                            &config,
-                            &SHELL,
-                            input,
-                            abort_signal.clone(),
-                            execute_without_confirm,
-                        )
-                        .await?;
-                        return Ok(());
+                let parts = if cli.is_typed_only(input_text) {
+                    split_turn(&config, input_text)
+                } else {
+                    vec![input_text.clone()]
+                };
+                // Each part of a compound request starts from the same role and model, and
+                // is routed only once the parts before it ran.
+                let (prev_role, prev_model) = {
+                    let cfg = config.read();
+                    (cfg.role.clone(), cfg.current_model().id())
+                };
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        let mut cfg = config.write();
+                        match prev_role.clone() {
+                            Some(role) => cfg.use_role_obj(role)?,
+                            None => cfg.exit_role()?,
+                        }
+                        cfg.set_model(&prev_model)?;
                     }
-                    TurnPolicy::Chat => {
-                        config.write().apply_prelude()?;
-                        let mut input = create_input(
-                            &config,
-                            Some(route.text),
-                            &cli.file,
-                            abort_signal.clone(),
-                            route_role,
-                        )
+                    let route = route_turn(&config, abort_signal.clone(), part).await?;
+                    // A turn with --file is never split, so the files go with its only part.
+                    run_routed_turn(&config, route, &cli.file, cli.code, abort_signal.clone())
                         .await?;
-                        input.use_embeddings(abort_signal.clone()).await?;
-                        return start_directive(&config, input, cli.code, abort_signal).await;
-                    }
-                    TurnPolicy::Auto => unreachable!("router always resolves Auto"),
                 }
+                return Ok(());
             }
         }
 
@@ -341,6 +348,69 @@ async fn run(
     start_interactive(&config).await
 }
 
+/// Run one routed part of a one-shot request.
+async fn run_routed_turn(
+    config: &GlobalConfig,
+    route: TurnRoute,
+    files: &[String],
+    code_mode: bool,
+    abort_signal: AbortSignal,
+) -> Result<()> {
+    if let Some(operation) = route.operation.clone() {
+        return execute_route_operation(config, &route, operation).await;
+    }
+
+    // Apply routed model
+    if let Some(ref id) = route.model_id {
+        config.write().set_model(id)?;
+    }
+
+    // Apply routed policy
+    let routed_policy = route.policy;
+    if matches!(routed_policy, TurnPolicy::Plan | TurnPolicy::Execute) {
+        config.write().use_role(SHELL_ROLE)?;
+    }
+    let route_role = role_for_route(config, &route);
+
+    match routed_policy {
+        TurnPolicy::Plan | TurnPolicy::Execute => {
+            let input = create_input(
+                config,
+                Some(route.text),
+                files,
+                abort_signal.clone(),
+                route_role,
+            )
+            .await?;
+            let auto_armed = scope_is_armed().unwrap_or(false);
+            let execute_without_confirm =
+                !route.confirm && (routed_policy == TurnPolicy::Execute || auto_armed);
+            shell_execute(
+                config,
+                &SHELL,
+                input,
+                abort_signal.clone(),
+                execute_without_confirm,
+            )
+            .await
+        }
+        TurnPolicy::Chat => {
+            config.write().apply_prelude()?;
+            let mut input = create_input(
+                config,
+                Some(route.text),
+                files,
+                abort_signal.clone(),
+                route_role,
+            )
+            .await?;
+            input.use_embeddings(abort_signal.clone()).await?;
+            start_directive(config, input, code_mode, abort_signal).await
+        }
+        TurnPolicy::Auto => unreachable!("router always resolves Auto"),
+    }
+}
+
 #[async_recursion::async_recursion]
 async fn start_directive(
     config: &GlobalConfig,
@@ -516,10 +586,21 @@ fn parse_utility_command() -> Option<UtilityCommand> {
         "arm" => Some(UtilityCommand::Arm),
         "disarm" => Some(UtilityCommand::Disarm),
         "doctor" => Some(UtilityCommand::Doctor),
+        "daemon" => Some(UtilityCommand::Daemon),
+        "gateway" => Some(UtilityCommand::Gateway),
         _ => None,
     }
 }
 
+/// `resolver <subcommand>` with a known subcommand; anything else after `resolver` is a
+/// prompt, e.g. `fio resolver is misrouting my tickets`.
+fn is_resolver_command(args: &[String]) -> bool {
+    args.get(1).map(String::as_str) == Some("resolver")
+        && args
+            .get(2)
+            .is_some_and(|v| ResolverCommand::has_subcommand(v))
+}
+
 fn invoked_as_fiochat() -> bool {
     let argv0 = match env::args().next() {
         Some(v) => v,
@@ -644,12 +725,174 @@ fn handle_utility_command(command: UtilityCommand) -> Result<()> {
             save_arm_state(&path, &state)?;
             println!("Disarmed execution for scope '{}'.", scope);
         }
-        UtilityCommand::Doctor => {}
+        UtilityCommand::Doctor | UtilityCommand::Daemon | UtilityCommand::Gateway => {}
     }
 
     Ok(())
 }
 
+async fn run_daemon() -> Result<()> {
+    setup_logger(true)?;
+    let config = Arc::new(RwLock::new(Config::init(WorkingMode::Serve, false).await?));
+    telemetry::init(&config.read().otel)?;
+    let jobs = scheduler::spawn(&config);
+    if jobs.is_empty() {
+        bail!("No enabled jobs; add them under `jobs` in the config file");
+    }
+    println!(
+        "Running {} scheduled job(s), press Ctrl+C to stop.",
+        jobs.len()
+    );
+    let ret = shutdown_signal().await;
+    telemetry::flush().await;
+    ret
+}
+
+async fn run_gateway() -> Result<()> {
+    setup_logger(true)?;
+    let config = Arc::new(RwLock::new(Config::init(WorkingMode::Serve, false).await?));
+    telemetry::init(&config.read().otel)?;
+    let telegram = TelegramGateway::new(&config)?;
+    if telegram.allowed_user_count() == 0 {
+        bail!("No allowed Telegram users; set `telegram.allowed_user_ids` in the config file");
+    }
+    println!(
+        "Telegram gateway started for {} allowed user(s), press Ctrl+C to stop.",
+        telegram.allowed_user_count()
+    );
+    let ret = tokio::select! {
+        ret = telegram.run() => ret,
+        ret = shutdown_signal() => ret,
+    };
+    telemetry::flush().await;
+    ret
+}
+
+/// Wait for Ctrl+C or, on unix, the SIGTERM a service manager stops us with.
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            ret = tokio::signal::ctrl_c() => ret?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+async fn run_resolver_command(cli: ResolverCli) -> Result<()> {
+    setup_logger(false)?;
+    let config = Arc::new(RwLock::new(Config::init(WorkingMode::Cmd, true).await?));
+    match cli.command {
+        ResolverCommand::Eval(args) => {
+            let resolver = config.read().load_resolver()?;
+            run_resolver_eval(&config, &resolver, args).await
+        }
+        ResolverCommand::Export(args) => run_resolver_export(args),
+        ResolverCommand::Import(args) => run_resolver_import(&config, args),
+    }
+}
+
+/// Export and import work on the stored vocabulary only, not the built-in and
+/// configured providers `load_resolver` adds on every start.
+fn run_resolver_export(args: ResolverExportArgs) -> Result<()> {
+    let resolver = Resolver::load(&Config::config_dir())?;
+    let json = args.json || args.output.as_deref().is_some_and(vocabulary::is_json);
+    let text = resolver.export().to_text(json)?;
+    match &args.output {
+        Some(path) => {
+            write(path, text).with_context(|| format!("Failed to write '{}'", path.display()))?;
+            println!("✓ Exported the resolver vocabulary to {}", path.display());
+        }
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+fn run_resolver_import(config: &GlobalConfig, args: ResolverImportArgs) -> Result<()> {
+    let vocabulary = Vocabulary::load(&args.file)?;
+    let mode = if args.replace {
+        ImportMode::Replace
+    } else {
+        ImportMode::Merge
+    };
+    let servers: Vec<String> = config
+        .read()
+        .mcp_servers
+        .iter()
+        .map(|v| v.name.clone())
+        .collect();
+    let mut resolver = Resolver::load(&Config::config_dir())?;
+    let diff = resolver.import(&vocabulary, mode, &servers)?;
+    if diff.is_empty() {
+        println!("No changes.");
+        return Ok(());
+    }
+    for line in &diff {
+        println!("{line}");
+    }
+    if args.dry_run {
+        println!("\n{} change(s); dry run, nothing saved.", diff.len());
+        return Ok(());
+    }
+    resolver.save()?;
+    println!(
+        "\n✓ Imported {} change(s) into {}",
+        diff.len(),
+        resolver.path().display()
+    );
+    Ok(())
+}
+
+async fn run_resolver_eval(
+    config: &GlobalConfig,
+    resolver: &Resolver,
+    args: ResolverEvalArgs,
+) -> Result<()> {
+    let cases = eval::load_corpus(&args.corpus)?;
+    // Read the baseline first: `--save` may point at the same file.
+    let baseline = args.baseline.as_deref().map(EvalReport::load).transpose()?;
+    let mut ai = match (&args.replay, args.ai) {
+        (Some(path), _) => AiMode::replay(path)?,
+        (None, true) => AiMode::Live(vec![]),
+        (None, false) => AiMode::Off,
+    };
+    let predictions =
+        eval::predict(resolver, config, &cases, &mut ai, create_abort_signal()).await?;
+    if let Some(path) = &args.record {
+        ai.save_recordings(path)?;
+    }
+    let report = EvalReport::build(&cases, &predictions);
+    if let Some(path) = &args.save {
+        report.save(path)?;
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render());
+    }
+    let regressions = report.regressions(baseline.as_ref());
+    if regressions.is_empty() {
+        return Ok(());
+    }
+    eprintln!("\n{} regression(s):", regressions.len());
+    for case in &regressions {
+        eprintln!(
+            "  '{}': expected {} got {} ({})",
+            case.text,
+            serde_json::to_string(&case.expected)?,
+            serde_json::to_string(&case.predicted)?,
+            case.outcome
+        );
+    }
+    process::exit(1);
+}
+
 fn run_doctor() -> Result<()> {
     let fio_path = which::which("fio").ok();
     let fiochat_path = which::which("fiochat").ok();
@@ -806,16 +1049,8 @@ async fn execute_route_operation(
         }
     }
 
-    if let Some(intent) = route.intent.clone() {
-        let cloned = config.read().resolver.clone();
-        if let Some(mut r) = cloned {
-            r.learn(&intent);
-            if let Err(e) = r.save() {
-                warn!("Resolver: failed to save after learning: {e}");
-            } else {
-                config.write().resolver = Some(r);
-            }
-        }
+    if let Some(intent) = route.intent.as_ref().filter(|_| !route.learned) {
+        learn_intent(config, intent, &route.input).await;
     }
 
     Ok(())
@@ -858,15 +1093,41 @@ fn setup_logger(is_serve: bool) -> Result<()> {
         return Ok(());
     }
     let crate_name = env!("CARGO_CRATE_NAME");
-    let log_filter = match std::env::var(get_env_name("log_filter")) {
-        Ok(v) => v,
+    let log_filters = match std::env::var(get_env_name("log_filter")) {
+        Ok(v) => vec![v],
         Err(_) => match is_serve {
-            true => format!("{crate_name}::serve"),
-            false => crate_name.into(),
+            true => [
+                "serve",
+                "gateway",
+                "hooks",
+                "scheduler",
+                "router",
+                "client::common",
+                "function",
+                "mcp::client",
+            ]
+            .iter()
+            .map(|v| format!("{crate_name}::{v}"))
+            .collect(),
+            false => vec![crate_name.into()],
         },
     };
-    let config = ConfigBuilder::new()
-        .add_filter_allow(log_filter)
+    if std::env::var(get_env_name("log_format")).as_deref() == Ok("json") {
+        let writer: Box<dyn std::io::Write + Send> = match log_path {
+            None => Box::new(std::io::stderr()),
+            Some(log_path) => {
+                ensure_parent_exists(&log_path)?;
+                Box::new(std::fs::File::create(log_path)?)
+            }
+        };
+        JsonLogger::init(log_level, log_filters, writer)?;
+        return Ok(());
+    }
+    let mut config = ConfigBuilder::new();
+    for log_filter in log_filters {
+        config.add_filter_allow(log_filter);
+    }
+    let config = config
         .set_time_format_custom(format_description!(
             "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
         ))
@@ -894,4 +1155,15 @@ mod tests {
         assert!(is_high_risk_command("git push --force-with-lease"));
         assert!(!is_high_risk_command("git push origin main"));
     }
+
+    #[test]
+    fn resolver_dispatch_needs_a_known_subcommand() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert!(is_resolver_command(&args("fio resolver eval corpus.jsonl")));
+        assert!(is_resolver_command(&args("fio resolver export")));
+        assert!(!is_resolver_command(&args("fio resolver")));
+        assert!(!is_resolver_command(&args(
+            "fio resolver is misrouting my tickets"
+        )));
+    }
 }
@@ -5,6 +5,7 @@ use rmcp::transport::TokioChildProcess;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::process::Command;
 use tokio::sync::RwLock;
 
@@ -12,6 +13,7 @@ use super::auth::{self, DeviceCodeStart, OAuthStatus};
 use super::config::{McpServerConfig, OAuthConfig, TransportKind};
 use super::convert::mcp_tool_to_function;
 use crate::function::FunctionDeclaration;
+use crate::telemetry::{in_span, SpanKind};
 
 /// Wrapper around a single MCP server connection.
 pub struct McpClient {
@@ -375,6 +377,17 @@ impl McpManager {
     }
 
     pub async fn call_tool(&self, prefixed_name: &str, arguments: Value) -> Result<Value> {
+        self.call_tool_visible(prefixed_name, arguments, |_| true)
+            .await
+    }
+
+    /// Call a tool only if its server passes `visible`, e.g. a user's `mcp_servers`.
+    pub async fn call_tool_visible(
+        &self,
+        prefixed_name: &str,
+        arguments: Value,
+        visible: impl Fn(&str) -> bool,
+    ) -> Result<Value> {
         let parts: Vec<&str> = prefixed_name
             .strip_prefix("mcp__")
             .ok_or_else(|| anyhow!("Invalid MCP tool name: {}", prefixed_name))?
@@ -385,12 +398,30 @@ impl McpManager {
         }
         let server_name = parts[0];
         let tool_name = parts[1];
+        if !visible(server_name) {
+            bail!("MCP server '{}' not found", server_name);
+        }
 
         let clients = self.clients.read().await;
         let client = clients
             .get(server_name)
             .ok_or_else(|| anyhow!("MCP server '{}' not found", server_name))?;
-        client.call_tool(tool_name, arguments).await
+        let start = Instant::now();
+        let attributes = vec![
+            ("mcp.server", server_name.into()),
+            ("mcp.tool", tool_name.into()),
+        ];
+        let fut = client.call_tool(tool_name, arguments);
+        let ret = in_span("mcp_call", SpanKind::Client, attributes, fut).await;
+        let duration = start.elapsed();
+        let duration_ms = duration.as_millis() as u64;
+        let outcome = if ret.is_ok() { "ok" } else { "error" };
+        log::info!(
+            server = server_name, tool = tool_name, duration_ms = duration_ms, outcome = outcome;
+            "MCP call {server_name}/{tool_name} {outcome} in {duration_ms}ms"
+        );
+        crate::metrics::observe_mcp_call(server_name, tool_name, ret.is_ok(), duration);
+        ret
     }
 
     pub async fn list_servers(&self) -> Vec<(String, bool, Option<String>)> {
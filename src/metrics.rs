@@ -0,0 +1,252 @@
+//! Process-wide Prometheus metrics, served by `--serve` at `/metrics`.
+//!
+//! Series live in one in-memory registry; gauges that mirror live state (MCP connections)
+//! are filled in when the text is rendered.
+
+use parking_lot::Mutex;
+use std::{collections::BTreeMap, fmt::Write, sync::LazyLock, time::Duration};
+
+/// Histogram buckets in seconds, from a fast route to a long multi-tool turn.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0, 60.0,
+];
+
+const HTTP_REQUESTS: &str = "fiochat_http_requests_total";
+const HTTP_DURATION: &str = "fiochat_http_request_duration_seconds";
+const TOKENS: &str = "fiochat_llm_tokens_total";
+const TOOL_CALLS: &str = "fiochat_tool_calls_total";
+const MCP_CALLS: &str = "fiochat_mcp_calls_total";
+const MCP_DURATION: &str = "fiochat_mcp_call_duration_seconds";
+const MCP_CONNECTED: &str = "fiochat_mcp_server_connected";
+const RESOLVER_OUTCOMES: &str = "fiochat_resolver_outcomes_total";
+
+const HELP: [(&str, &str); 8] = [
+    (HTTP_REQUESTS, "HTTP requests by route, model and status."),
+    (HTTP_DURATION, "HTTP request latency by route and model."),
+    (
+        TOKENS,
+        "Tokens reported by the model provider, by model and direction.",
+    ),
+    (
+        TOOL_CALLS,
+        "Tool calls by tool, outcome and permission decision.",
+    ),
+    (MCP_CALLS, "MCP tool calls by server, tool and outcome."),
+    (MCP_DURATION, "MCP tool call latency by server."),
+    (
+        MCP_CONNECTED,
+        "Whether an MCP server is connected (1) or not (0).",
+    ),
+    (RESOLVER_OUTCOMES, "Resolver outcomes for routed turns."),
+];
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: Mutex<BTreeMap<(&'static str, Labels), u64>>,
+    histograms: Mutex<BTreeMap<(&'static str, Labels), Histogram>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Registry {
+    fn add(&self, name: &'static str, labels: Labels, value: u64) {
+        *self.counters.lock().entry((name, labels)).or_default() += value;
+    }
+
+    fn observe(&self, name: &'static str, labels: Labels, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let mut histograms = self.histograms.lock();
+        let histogram = histograms.entry((name, labels)).or_default();
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                histogram.buckets[i] += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += secs;
+    }
+}
+
+/// A served HTTP request. `route` should come from [`route_label`] to keep cardinality bounded.
+pub fn observe_http_request(route: &str, model: &str, status: u16, duration: Duration) {
+    REGISTRY.add(
+        HTTP_REQUESTS,
+        vec![
+            ("route", route.into()),
+            ("model", model.into()),
+            ("status", status.to_string()),
+        ],
+        1,
+    );
+    REGISTRY.observe(
+        HTTP_DURATION,
+        vec![("route", route.into()), ("model", model.into())],
+        duration,
+    );
+}
+
+pub fn record_tokens(model: &str, input_tokens: Option<u64>, output_tokens: Option<u64>) {
+    for (direction, tokens) in [("in", input_tokens), ("out", output_tokens)] {
+        if let Some(tokens) = tokens {
+            let labels = vec![("model", model.into()), ("direction", direction.into())];
+            REGISTRY.add(TOKENS, labels, tokens);
+        }
+    }
+}
+
+/// `outcome` is `ok`, `error` or `skipped`; `permission` is `allowed` or `denied`.
+pub fn record_tool_call(tool: &str, outcome: &str, permission: &str) {
+    REGISTRY.add(
+        TOOL_CALLS,
+        vec![
+            ("tool", tool.into()),
+            ("outcome", outcome.into()),
+            ("permission", permission.into()),
+        ],
+        1,
+    );
+}
+
+pub fn observe_mcp_call(server: &str, tool: &str, ok: bool, duration: Duration) {
+    let outcome = if ok { "ok" } else { "error" };
+    REGISTRY.add(
+        MCP_CALLS,
+        vec![
+            ("server", server.into()),
+            ("tool", tool.into()),
+            ("outcome", outcome.into()),
+        ],
+        1,
+    );
+    REGISTRY.observe(MCP_DURATION, vec![("server", server.into())], duration);
+}
+
+/// `outcome` is `resolved`, `needs_ai` or `pass_through`, plus `example` when the
+/// nearest-neighbour stage then resolves the turn.
+pub fn record_resolver_outcome(outcome: &str) {
+    REGISTRY.add(RESOLVER_OUTCOMES, vec![("outcome", outcome.into())], 1);
+}
+
+/// Map a request path to a route label; unknown paths share one label.
+pub fn route_label(path: &str) -> String {
+    match path {
+        "/v1/chat/completions"
+        | "/v1/embeddings"
+        | "/v1/rerank"
+        | "/v1/models"
+        | "/v1/roles"
+        | "/v1/rags"
+        | "/v1/rags/search"
+        | "/v1/resolve"
+        | "/metrics"
+        | "/healthz"
+        | "/readyz" => path.to_string(),
+        "/playground" | "/playground.html" => "/playground".into(),
+        "/arena" | "/arena.html" => "/arena".into(),
+        _ if path.starts_with("/v1/hooks/") => "/v1/hooks/{name}".into(),
+        _ => "other".into(),
+    }
+}
+
+/// Render every series in the Prometheus text exposition format.
+/// `mcp_servers` is the current `(name, connected)` list.
+pub fn render(mcp_servers: &[(String, bool)]) -> String {
+    let mut counters = REGISTRY.counters.lock().clone();
+    for (name, connected) in mcp_servers {
+        counters.insert(
+            (MCP_CONNECTED, vec![("server", name.clone())]),
+            *connected as u64,
+        );
+    }
+    let histograms = REGISTRY.histograms.lock().clone();
+
+    let mut output = String::new();
+    for (name, help) in HELP {
+        let kind = match name {
+            HTTP_DURATION | MCP_DURATION => "histogram",
+            MCP_CONNECTED => "gauge",
+            _ => "counter",
+        };
+        let _ = writeln!(output, "# HELP {name} {help}");
+        let _ = writeln!(output, "# TYPE {name} {kind}");
+        for ((_, labels), value) in counters.iter().filter(|((v, _), _)| *v == name) {
+            let _ = writeln!(output, "{name}{} {value}", format_labels(labels, None));
+        }
+        for ((_, labels), histogram) in histograms.iter().filter(|((v, _), _)| *v == name) {
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                let le = format_labels(labels, Some(&bound.to_string()));
+                let _ = writeln!(output, "{name}_bucket{le} {count}");
+            }
+            let le = format_labels(labels, Some("+Inf"));
+            let labels = format_labels(labels, None);
+            let _ = writeln!(output, "{name}_bucket{le} {}", histogram.count);
+            let _ = writeln!(output, "{name}_sum{labels} {}", histogram.sum);
+            let _ = writeln!(output, "{name}_count{labels} {}", histogram.count);
+        }
+    }
+    output
+}
+
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let mut parts: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape_label_value(v)))
+        .collect();
+    if let Some(le) = le {
+        parts.push(format!("le=\"{le}\""));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", parts.join(","))
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        observe_http_request("/v1/models", "", 200, Duration::from_millis(20));
+        record_tool_call("fio_system_info", "ok", "allowed");
+        record_resolver_outcome("needs_ai");
+        let output = render(&[("github".into(), true)]);
+        assert!(output.contains("# TYPE fiochat_http_request_duration_seconds histogram\n"));
+        assert!(output.contains(
+            "fiochat_http_requests_total{route=\"/v1/models\",model=\"\",status=\"200\"}"
+        ));
+        assert!(output.contains(
+            "fiochat_http_request_duration_seconds_bucket{route=\"/v1/models\",model=\"\",le=\"0.025\"}"
+        ));
+        assert!(output.contains(
+            "fiochat_tool_calls_total{tool=\"fio_system_info\",outcome=\"ok\",permission=\"allowed\"}"
+        ));
+        assert!(output.contains("fiochat_resolver_outcomes_total{outcome=\"needs_ai\"}"));
+        assert!(output.contains("fiochat_mcp_server_connected{server=\"github\"} 1\n"));
+    }
+
+    #[test]
+    fn test_route_label() {
+        assert_eq!(route_label("/v1/hooks/prometheus"), "/v1/hooks/{name}");
+        assert_eq!(route_label("/arena.html"), "/arena");
+        assert_eq!(route_label("/v1/unknown/123"), "other");
+        assert_eq!(escape_label_value("a\"b\\"), "a\\\"b\\\\");
+    }
+}
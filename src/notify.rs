@@ -0,0 +1,571 @@
+//! Outbound notifications.
+//!
+//! Sinks are configured by name under `notify_sinks` and used by alert hooks, scheduled
+//! jobs, the `fio_notify` tool and the `.notify` REPL command (and therefore macros).
+
+use crate::config::{ensure_parent_exists, Macro};
+use crate::render::{render_chat_chunks, ChatFormat, CHAT_MAX_CHARS};
+use crate::utils::run_command_with_output;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{env, fs::OpenOptions, io::Write, path::PathBuf, sync::LazyLock, time::Duration};
+
+pub type NotifySinks = IndexMap<String, SinkConfig>;
+
+const DEFAULT_NOTIFY_COMMAND: &str = "fio-notify";
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+/// Slack truncates `text` beyond this.
+const SLACK_MAX_CHARS: usize = 4000;
+
+static CLIENT: LazyLock<Result<reqwest::Client>> = LazyLock::new(|| {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(16))
+        .build()?;
+    Ok(client)
+});
+
+#[async_trait]
+pub trait NotifySink: Send + Sync {
+    /// Send a rendered message; `variables` are available to sinks with payload templates.
+    async fn send(&self, message: &str, variables: &IndexMap<String, String>) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    Telegram(TelegramSink),
+    Slack(SlackSink),
+    Webhook(WebhookSink),
+    Command(CommandSink),
+    File(FileSink),
+}
+
+impl SinkConfig {
+    pub fn sink(&self) -> &dyn NotifySink {
+        match self {
+            SinkConfig::Telegram(v) => v,
+            SinkConfig::Slack(v) => v,
+            SinkConfig::Webhook(v) => v,
+            SinkConfig::Command(v) => v,
+            SinkConfig::File(v) => v,
+        }
+    }
+}
+
+/// Telegram Bot API `sendMessage`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TelegramSink {
+    /// Falls back to the `TELEGRAM_BOT_TOKEN` environment variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_token: Option<String>,
+    pub chat_id: String,
+    /// `HTML` or `MarkdownV2` to convert the (markdown) message into that markup; plain
+    /// text when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+}
+
+#[async_trait]
+impl NotifySink for TelegramSink {
+    async fn send(&self, message: &str, _variables: &IndexMap<String, String>) -> Result<()> {
+        let token = match &self.bot_token {
+            Some(token) => token.clone(),
+            None => {
+                env::var("TELEGRAM_BOT_TOKEN").map_err(|_| anyhow!("Missing Telegram bot token"))?
+            }
+        };
+        let api_url = self.api_url.as_deref().unwrap_or(TELEGRAM_API_URL);
+        let url = format!("{}/bot{token}/sendMessage", api_url.trim_end_matches('/'));
+        let format = self
+            .parse_mode
+            .as_deref()
+            .and_then(ChatFormat::from_parse_mode);
+        let chunks = match format {
+            Some(format) => render_chat_chunks(message, format, CHAT_MAX_CHARS),
+            None => split_message(message, CHAT_MAX_CHARS),
+        };
+        for chunk in chunks {
+            let mut body = json!({ "chat_id": self.chat_id, "text": chunk });
+            if let Some(parse_mode) = format.and_then(|v| v.parse_mode()) {
+                body["parse_mode"] = parse_mode.into();
+            }
+            let res = client()?
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .context("Failed to reach the Telegram Bot API")?;
+            let status = res.status();
+            let data: Value = res.json().await.unwrap_or_default();
+            if !status.is_success() || data["ok"] != true {
+                let description = data["description"].as_str().unwrap_or_default();
+                bail!("Telegram sendMessage failed ({status}): {description}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Slack incoming webhook.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SlackSink {
+    pub url: String,
+}
+
+#[async_trait]
+impl NotifySink for SlackSink {
+    async fn send(&self, message: &str, _variables: &IndexMap<String, String>) -> Result<()> {
+        for chunk in render_chat_chunks(message, ChatFormat::Mrkdwn, SLACK_MAX_CHARS) {
+            post_json(&self.url, &IndexMap::new(), &json!({ "text": chunk })).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Generic JSON webhook.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WebhookSink {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub headers: IndexMap<String, String>,
+    /// JSON template for the body; string values are interpolated. Defaults to
+    /// `{"text": "{{message}}"}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+#[async_trait]
+impl NotifySink for WebhookSink {
+    async fn send(&self, message: &str, variables: &IndexMap<String, String>) -> Result<()> {
+        let mut variables = variables.clone();
+        variables.insert("message".into(), message.to_string());
+        let payload = self
+            .payload
+            .clone()
+            .unwrap_or_else(|| json!({ "text": "{{message}}" }));
+        post_json(
+            &self.url,
+            &self.headers,
+            &interpolate_json(&payload, &variables),
+        )
+        .await
+    }
+}
+
+/// Local command that receives the message as its last argument, e.g. `fio-notify`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CommandSink {
+    pub command: String,
+}
+
+#[async_trait]
+impl NotifySink for CommandSink {
+    async fn send(&self, message: &str, _variables: &IndexMap<String, String>) -> Result<()> {
+        let command = self.command.clone();
+        let mut args = shell_words::split(&command)
+            .with_context(|| format!("Invalid notify command `{command}`"))?;
+        if args.is_empty() {
+            bail!("Empty notify command");
+        }
+        let program = args.remove(0);
+        args.push(message.to_string());
+        let (success, _, stderr) =
+            tokio::task::spawn_blocking(move || run_command_with_output(&program, &args, None))
+                .await
+                .map_err(|err| anyhow!("Notify task failed: {err}"))??;
+        if !success {
+            bail!("Notify command `{command}` failed: {}", stderr.trim());
+        }
+        Ok(())
+    }
+}
+
+/// File the message is appended to.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FileSink {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl NotifySink for FileSink {
+    async fn send(&self, message: &str, _variables: &IndexMap<String, String>) -> Result<()> {
+        let path = &self.path;
+        ensure_parent_exists(path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{message}")
+            .with_context(|| format!("Failed to write to {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Where an unattended result (alert triage, scheduled job output) is sent.
+/// With no target configured, the message goes to `fio-notify`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DeliveryConfig {
+    /// Names of sinks defined under `notify_sinks`.
+    pub sinks: Vec<String>,
+    /// Command that receives the message as its last argument, e.g. `fio-notify`.
+    pub command: Option<String>,
+    /// URL the payload is POSTed to as JSON.
+    pub webhook: Option<String>,
+    /// File the message is appended to.
+    pub file: Option<PathBuf>,
+    /// JSON template for the webhook body; string values are interpolated.
+    pub payload: Option<Value>,
+    /// Message template; the default depends on what is being delivered.
+    pub message: Option<String>,
+}
+
+impl DeliveryConfig {
+    fn targets(&self, sinks: &NotifySinks) -> Result<Vec<(String, SinkConfig)>> {
+        let mut targets = vec![];
+        for name in &self.sinks {
+            let sink = sinks
+                .get(name)
+                .ok_or_else(|| anyhow!("Unknown notify sink '{name}'"))?;
+            targets.push((name.clone(), sink.clone()));
+        }
+        if let Some(command) = &self.command {
+            let sink = SinkConfig::Command(CommandSink {
+                command: command.clone(),
+            });
+            targets.push(("command".into(), sink));
+        }
+        if let Some(url) = &self.webhook {
+            let sink = SinkConfig::Webhook(WebhookSink {
+                url: url.clone(),
+                headers: IndexMap::new(),
+                payload: self.payload.clone(),
+            });
+            targets.push(("webhook".into(), sink));
+        }
+        if let Some(path) = &self.file {
+            let sink = SinkConfig::File(FileSink { path: path.clone() });
+            targets.push(("file".into(), sink));
+        }
+        if targets.is_empty() {
+            if which::which(DEFAULT_NOTIFY_COMMAND).is_err() {
+                bail!("No notify target configured and '{DEFAULT_NOTIFY_COMMAND}' is not in PATH");
+            }
+            let sink = SinkConfig::Command(CommandSink {
+                command: DEFAULT_NOTIFY_COMMAND.into(),
+            });
+            targets.push((DEFAULT_NOTIFY_COMMAND.into(), sink));
+        }
+        Ok(targets)
+    }
+}
+
+/// Render the message template and send it to every target. A failing target does not
+/// stop the others; all failures are reported together.
+pub async fn deliver(
+    sinks: &NotifySinks,
+    delivery: &DeliveryConfig,
+    mut variables: IndexMap<String, String>,
+    default_message: &str,
+) -> Result<()> {
+    let message = Macro::interpolate_command(
+        delivery.message.as_deref().unwrap_or(default_message),
+        &variables,
+    );
+    variables.insert("message".into(), message.clone());
+    let mut errors = vec![];
+    for (name, sink) in delivery.targets(sinks)? {
+        if let Err(err) = sink.sink().send(&message, &variables).await {
+            errors.push(format!("{name}: {err}"));
+        }
+    }
+    if !errors.is_empty() {
+        bail!("{}", errors.join("; "));
+    }
+    Ok(())
+}
+
+/// Send a plain message to a named sink.
+pub async fn send(sinks: &NotifySinks, name: &str, message: &str) -> Result<()> {
+    let sink = sinks
+        .get(name)
+        .ok_or_else(|| anyhow!("Unknown notify sink '{name}'"))?;
+    let mut variables = IndexMap::new();
+    variables.insert("sink".to_string(), name.to_string());
+    sink.sink().send(message, &variables).await
+}
+
+/// Interpolate `{{var}}` placeholders in every string of a JSON template.
+pub fn interpolate_json(template: &Value, variables: &IndexMap<String, String>) -> Value {
+    match template {
+        Value::String(s) => Value::String(Macro::interpolate_command(s, variables)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| interpolate_json(v, variables))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), interpolate_json(v, variables)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn client() -> Result<&'static reqwest::Client> {
+    CLIENT.as_ref().map_err(|err| anyhow!("{err}"))
+}
+
+async fn post_json(url: &str, headers: &IndexMap<String, String>, body: &Value) -> Result<()> {
+    let mut builder = client()?.post(url).json(body);
+    for (key, value) in headers {
+        builder = builder.header(key, value);
+    }
+    let res = builder
+        .send()
+        .await
+        .with_context(|| format!("Failed to post to {url}"))?;
+    let status = res.status();
+    if !status.is_success() {
+        let text = res.text().await.unwrap_or_default();
+        bail!("Webhook {url} responded with {status}: {text}");
+    }
+    Ok(())
+}
+
+/// Split a message into chunks of at most `max_len` UTF-16 code units (what Telegram
+/// counts), preferring line boundaries.
+fn split_message(message: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut current_len = 0;
+    for line in message.split_inclusive('\n') {
+        let line_len = line.encode_utf16().count();
+        if current_len + line_len > max_len && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if line_len > max_len {
+            let mut part = String::new();
+            let mut part_len = 0;
+            for ch in line.chars() {
+                if part_len + ch.len_utf16() > max_len {
+                    chunks.push(std::mem::take(&mut part));
+                    part_len = 0;
+                }
+                part.push(ch);
+                part_len += ch.len_utf16();
+            }
+            chunks.push(part);
+            continue;
+        }
+        current.push_str(line);
+        current_len += line_len;
+    }
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn vars(pairs: &[(&str, &str)]) -> IndexMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_sink_config() {
+        let sinks: NotifySinks = serde_yaml::from_str(
+            r#"
+ops:
+  type: telegram
+  chat_id: "-100123"
+  parse_mode: HTML
+team:
+  type: slack
+  url: https://hooks.slack.com/services/x
+local:
+  type: command
+  command: fio-notify
+"#,
+        )
+        .unwrap();
+        assert!(matches!(sinks["ops"], SinkConfig::Telegram(ref v) if v.chat_id == "-100123"));
+        assert!(matches!(sinks["team"], SinkConfig::Slack(_)));
+        assert!(matches!(sinks["local"], SinkConfig::Command(_)));
+        assert!(serde_yaml::from_str::<NotifySinks>("x:\n  type: pager\n").is_err());
+    }
+
+    #[test]
+    fn test_interpolate_json() {
+        let vars = vars(&[("title", "Disk full"), ("status", "firing")]);
+        let template =
+            json!({"text": "[{{status}}] {{title}}", "tags": ["{{status}}", 1], "ok": true});
+        assert_eq!(
+            interpolate_json(&template, &vars),
+            json!({"text": "[firing] Disk full", "tags": ["firing", 1], "ok": true})
+        );
+    }
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("", 10), vec![""]);
+        assert_eq!(split_message("a\nb\nc", 10), vec!["a\nb\nc"]);
+        assert_eq!(
+            split_message("aaaa\nbbbb\ncc", 6),
+            vec!["aaaa\n", "bbbb\n", "cc"]
+        );
+        assert_eq!(split_message("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(split_message("🔥🔥🔥", 4), vec!["🔥🔥", "🔥"]);
+    }
+
+    #[tokio::test]
+    async fn test_telegram_sink() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/botTOKEN/sendMessage")
+            .match_body(Matcher::Json(
+                json!({"chat_id": "-100123", "text": "<b>up</b> &lt;db-1&gt;", "parse_mode": "HTML"}),
+            ))
+            .with_body(r#"{"ok":true,"result":{}}"#)
+            .create_async()
+            .await;
+        let sink = TelegramSink {
+            bot_token: Some("TOKEN".into()),
+            chat_id: "-100123".into(),
+            parse_mode: Some("HTML".into()),
+            api_url: Some(server.url()),
+        };
+        sink.send("**up** <db-1>", &IndexMap::new()).await.unwrap();
+        mock.assert_async().await;
+
+        let rejected = server
+            .mock("POST", "/botBAD/sendMessage")
+            .with_status(401)
+            .with_body(r#"{"ok":false,"description":"Unauthorized"}"#)
+            .create_async()
+            .await;
+        let sink = TelegramSink {
+            bot_token: Some("BAD".into()),
+            ..sink
+        };
+        let err = sink.send("x", &IndexMap::new()).await.unwrap_err();
+        assert!(err.to_string().contains("Unauthorized"));
+        rejected.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_slack_and_webhook_sinks() {
+        let mut server = mockito::Server::new_async().await;
+        let slack = server
+            .mock("POST", "/slack")
+            .match_body(Matcher::Json(json!({"text": "disk full"})))
+            .create_async()
+            .await;
+        let webhook = server
+            .mock("POST", "/hook")
+            .match_header("x-api-key", "k")
+            .match_body(Matcher::Json(
+                json!({"summary": "disk full", "host": "db-1"}),
+            ))
+            .create_async()
+            .await;
+        let mut sinks = NotifySinks::new();
+        sinks.insert(
+            "team".into(),
+            SinkConfig::Slack(SlackSink {
+                url: format!("{}/slack", server.url()),
+            }),
+        );
+        sinks.insert(
+            "pager".into(),
+            SinkConfig::Webhook(WebhookSink {
+                url: format!("{}/hook", server.url()),
+                headers: vars(&[("x-api-key", "k")]),
+                payload: Some(json!({"summary": "{{message}}", "host": "{{host}}"})),
+            }),
+        );
+        send(&sinks, "team", "disk full").await.unwrap();
+        let delivery = DeliveryConfig {
+            sinks: vec!["pager".into()],
+            ..Default::default()
+        };
+        deliver(&sinks, &delivery, vars(&[("host", "db-1")]), "disk full")
+            .await
+            .unwrap();
+        slack.assert_async().await;
+        webhook.assert_async().await;
+
+        assert!(send(&sinks, "missing", "x").await.is_err());
+        let unknown = DeliveryConfig {
+            sinks: vec!["missing".into()],
+            ..Default::default()
+        };
+        assert!(deliver(&sinks, &unknown, IndexMap::new(), "x")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deliver_reports_failures_after_trying_all_targets() {
+        let mut server = mockito::Server::new_async().await;
+        let failing = server
+            .mock("POST", "/fail")
+            .with_status(500)
+            .create_async()
+            .await;
+        let dir = std::env::temp_dir().join(format!("fio-notify-{}", std::process::id()));
+        let delivery = DeliveryConfig {
+            webhook: Some(format!("{}/fail", server.url())),
+            file: Some(dir.join("out.log")),
+            message: Some("{{title}}: {{triage}}".into()),
+            ..Default::default()
+        };
+        let vars = vars(&[("title", "Disk full"), ("triage", "rotate logs")]);
+        let err = deliver(&NotifySinks::new(), &delivery, vars, "")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("webhook: "));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("out.log")).unwrap(),
+            "Disk full: rotate logs\n"
+        );
+        failing.assert_async().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_sink() {
+        let dir = std::env::temp_dir().join(format!("fio-notify-cmd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("message.txt");
+        let sink = CommandSink {
+            command: format!("sh -c 'printf %s \"$1\" > {}' notify", out.display()),
+        };
+        sink.send("Disk full", &IndexMap::new()).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "Disk full");
+
+        let sink = CommandSink {
+            command: "false".into(),
+        };
+        assert!(sink.send("x", &IndexMap::new()).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -106,6 +106,16 @@ impl Rag {
         Self::create(config, name, path, data)
     }
 
+    /// Check that a RAG file reads and parses and that its embedding model exists,
+    /// without building the search indexes.
+    pub fn verify(config: &GlobalConfig, name: &str, path: &Path) -> Result<()> {
+        let err = || format!("Failed to load rag '{name}' at '{}'", path.display());
+        let content = fs::read_to_string(path).with_context(err)?;
+        let data: RagData = serde_yaml::from_str(&content).with_context(err)?;
+        Model::retrieve_model(&config.read(), &data.embedding_model, ModelType::Embedding)?;
+        Ok(())
+    }
+
     pub fn create(config: &GlobalConfig, name: &str, path: &Path, data: RagData) -> Result<Self> {
         let hnsw = data.build_hnsw();
         let bm25 = data.build_bm25();
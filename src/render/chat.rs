@@ -0,0 +1,785 @@
+//! Render model markdown for chat platforms.
+//!
+//! Replies are converted block by block (code fences, tables, quotes, lines) into the
+//! platform's markup and split into messages under the platform limit. A code block that
+//! does not fit is cut into several complete blocks, and tables become monospace blocks
+//! since no supported format has tables.
+
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+/// Telegram rejects messages over 4096 UTF-16 code units.
+pub const CHAT_MAX_CHARS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatFormat {
+    /// Telegram `parse_mode: HTML`.
+    Html,
+    /// Telegram `parse_mode: MarkdownV2`.
+    MarkdownV2,
+    /// Slack mrkdwn.
+    Mrkdwn,
+}
+
+impl ChatFormat {
+    /// Map a Telegram `parse_mode` to a format; `None` for plain text or unknown modes.
+    pub fn from_parse_mode(parse_mode: &str) -> Option<Self> {
+        match parse_mode.to_ascii_lowercase().as_str() {
+            "html" => Some(Self::Html),
+            "markdownv2" => Some(Self::MarkdownV2),
+            _ => None,
+        }
+    }
+
+    /// Length of `text` in the unit the platform limits: Telegram counts UTF-16 code
+    /// units, so an emoji takes two.
+    pub fn text_len(&self, text: &str) -> usize {
+        match self {
+            Self::Html | Self::MarkdownV2 => text.encode_utf16().count(),
+            Self::Mrkdwn => char_len(text),
+        }
+    }
+
+    pub fn parse_mode(&self) -> Option<&'static str> {
+        match self {
+            Self::Html => Some("HTML"),
+            Self::MarkdownV2 => Some("MarkdownV2"),
+            Self::Mrkdwn => None,
+        }
+    }
+}
+
+/// Render `text` into messages of at most `max_len` each, measured by [`ChatFormat::text_len`].
+pub fn render_chat_chunks(text: &str, format: ChatFormat, max_len: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    for block in parse_blocks(text) {
+        for piece in block.render_pieces(format, max_len) {
+            let len = format.text_len(&current);
+            if len > 0 && len + 1 + format.text_len(&piece) > max_len {
+                chunks.push(std::mem::take(&mut current).trim_end().to_string());
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&piece);
+        }
+    }
+    let current = current.trim_end();
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current.to_string());
+    }
+    chunks
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Line(String),
+    Quote(Vec<String>),
+    Code { lang: String, lines: Vec<String> },
+}
+
+impl Block {
+    fn render(&self, format: ChatFormat) -> String {
+        match self {
+            Block::Line(line) => render_line(line, format),
+            Block::Quote(lines) => render_quote(lines, format),
+            Block::Code { lang, lines } => render_code(lang, lines, format),
+        }
+    }
+
+    /// Render into pieces that each fit in `max_len`.
+    fn render_pieces(&self, format: ChatFormat, max_len: usize) -> Vec<String> {
+        let output = self.render(format);
+        if format.text_len(&output) <= max_len {
+            return vec![output];
+        }
+        match self {
+            Block::Line(line) => split_words(line, max_len, format, |v| render_line(v, format)),
+            Block::Quote(lines) => lines
+                .iter()
+                .flat_map(|line| {
+                    split_words(line, max_len, format, |v| {
+                        render_quote(&[v.to_string()], format)
+                    })
+                })
+                .collect(),
+            Block::Code { lang, lines } => {
+                let render = |lines: &[String]| render_code(lang, lines, format);
+                let mut pieces = vec![];
+                let mut group: Vec<String> = vec![];
+                for line in lines {
+                    group.push(line.clone());
+                    if format.text_len(&render(&group)) <= max_len {
+                        continue;
+                    }
+                    group.pop();
+                    if !group.is_empty() {
+                        pieces.push(render(&group));
+                        group.clear();
+                    }
+                    // A single line longer than a message is cut by characters.
+                    let mut rest = line.as_str();
+                    while !rest.is_empty() {
+                        let at = fit_prefix(rest, max_len, format, |v| render(&[v.to_string()]));
+                        let (head, tail) = rest.split_at(at);
+                        if tail.is_empty() {
+                            group.push(head.to_string());
+                        } else {
+                            pieces.push(render(&[head.to_string()]));
+                        }
+                        rest = tail;
+                    }
+                }
+                if !group.is_empty() {
+                    pieces.push(render(&group));
+                }
+                pieces
+            }
+        }
+    }
+}
+
+fn parse_blocks(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            let lang = info
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            // In chars: the indentation may hold multibyte whitespace.
+            let indent = char_len(line) - char_len(trimmed);
+            let mut code = vec![];
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                let line = lines[i];
+                let strip: usize = line
+                    .chars()
+                    .take(indent)
+                    .take_while(|ch| ch.is_whitespace())
+                    .map(char::len_utf8)
+                    .sum();
+                code.push(line[strip..].to_string());
+                i += 1;
+            }
+            i += 1;
+            blocks.push(Block::Code { lang, lines: code });
+            continue;
+        }
+        if is_table_row(trimmed) && lines.get(i + 1).is_some_and(|v| is_table_separator(v)) {
+            let mut rows = vec![split_table_row(trimmed)];
+            i += 2;
+            while i < lines.len() && is_table_row(lines[i].trim_start()) {
+                rows.push(split_table_row(lines[i].trim_start()));
+                i += 1;
+            }
+            blocks.push(Block::Code {
+                lang: String::new(),
+                lines: layout_table(&rows),
+            });
+            continue;
+        }
+        if let Some(quote) = quote_text(trimmed) {
+            let mut quote_lines = vec![quote.to_string()];
+            i += 1;
+            while let Some(quote) = lines.get(i).and_then(|v| quote_text(v.trim_start())) {
+                quote_lines.push(quote.to_string());
+                i += 1;
+            }
+            blocks.push(Block::Quote(quote_lines));
+            continue;
+        }
+        blocks.push(Block::Line(line.trim_end().to_string()));
+        i += 1;
+    }
+    blocks
+}
+
+fn quote_text(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn is_table_row(line: &str) -> bool {
+    let line = line.trim_end();
+    line.starts_with('|') && line.len() > 1
+}
+
+fn is_table_separator(line: &str) -> bool {
+    let line = line.trim();
+    is_table_row(line)
+        && line.contains('-')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+fn split_table_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|')
+        .map(|cell| plain_text(&parse_inline(cell.trim())))
+        .collect()
+}
+
+fn layout_table(rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows.iter().map(|v| v.len()).max().unwrap_or_default();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.width())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let format_row = |row: &Vec<String>| {
+        widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| {
+                let cell = row.get(i).map(|v| v.as_str()).unwrap_or_default();
+                format!("{cell}{}", " ".repeat(width - cell.width()))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        lines.push(format_row(row));
+        if i == 0 {
+            let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            lines.push(separator.join("-+-"));
+        }
+    }
+    lines
+}
+
+fn render_line(line: &str, format: ChatFormat) -> String {
+    let indent_len = line.len() - line.trim_start().len();
+    let (indent, text) = line.split_at(indent_len);
+    let text = text.trim_end();
+    if text.is_empty() {
+        return String::new();
+    }
+    if is_rule(text) {
+        return "──────────".into();
+    }
+    let heading = text
+        .strip_prefix('#')
+        .map(|v| v.trim_start_matches('#'))
+        .filter(|v| v.starts_with(' ') && text.len() - v.len() <= 6);
+    if let Some(heading) = heading {
+        let spans = vec![Span::Bold(parse_inline(heading.trim()))];
+        return render_spans(&spans, format);
+    }
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|v| text.strip_prefix(v)) {
+        let prefix = format!("{indent}• ");
+        return format!("{prefix}{}", render_spans(&parse_inline(item), format));
+    }
+    render_spans(&parse_inline(&format!("{indent}{text}")), format)
+}
+
+fn is_rule(text: &str) -> bool {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| compact.chars().all(|v| v == c))
+}
+
+fn render_quote(lines: &[String], format: ChatFormat) -> String {
+    match format {
+        ChatFormat::Html => {
+            let inner: Vec<String> = lines.iter().map(|v| render_line(v, format)).collect();
+            format!("<blockquote>{}</blockquote>", inner.join("\n"))
+        }
+        ChatFormat::MarkdownV2 | ChatFormat::Mrkdwn => {
+            let prefix = if format == ChatFormat::Mrkdwn {
+                "> "
+            } else {
+                ">"
+            };
+            lines
+                .iter()
+                .map(|v| format!("{prefix}{}", render_line(v, format)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+fn render_code(lang: &str, lines: &[String], format: ChatFormat) -> String {
+    let code = lines.join("\n");
+    match format {
+        ChatFormat::Html => {
+            let lang = sanitize_lang(lang);
+            if lang.is_empty() {
+                format!("<pre><code>{}</code></pre>", escape_html(&code))
+            } else {
+                format!(
+                    "<pre><code class=\"language-{lang}\">{}</code></pre>",
+                    escape_html(&code)
+                )
+            }
+        }
+        ChatFormat::MarkdownV2 => {
+            format!(
+                "```{}\n{}\n```",
+                sanitize_lang(lang),
+                escape_markdown_v2_code(&code)
+            )
+        }
+        ChatFormat::Mrkdwn => format!("```\n{}\n```", escape_mrkdwn(&code)),
+    }
+}
+
+fn sanitize_lang(lang: &str) -> &str {
+    if lang
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "+-_#".contains(c))
+    {
+        lang
+    } else {
+        ""
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Span {
+    Text(String),
+    Code(String),
+    Bold(Vec<Span>),
+    Italic(Vec<Span>),
+    Strike(Vec<Span>),
+    Link(Vec<Span>, String),
+}
+
+fn parse_inline(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut i = 0;
+    let flush = |plain: &mut String, spans: &mut Vec<Span>| {
+        if !plain.is_empty() {
+            spans.push(Span::Text(std::mem::take(plain)));
+        }
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() {
+            plain.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '`' {
+            if let Some(end) = find_seq(&chars, i + 1, &['`']) {
+                flush(&mut plain, &mut spans);
+                spans.push(Span::Code(chars[i + 1..end].iter().collect()));
+                i = end + 1;
+                continue;
+            }
+        }
+        let double = chars.get(i + 1) == Some(&c);
+        if double && matches!(c, '*' | '_' | '~') {
+            let delimiter = [c, c];
+            if let Some(end) = find_seq(&chars, i + 2, &delimiter).filter(|end| *end > i + 2) {
+                flush(&mut plain, &mut spans);
+                let inner = parse_inline(&chars[i + 2..end].iter().collect::<String>());
+                spans.push(match c {
+                    '~' => Span::Strike(inner),
+                    _ => Span::Bold(inner),
+                });
+                i = end + 2;
+                continue;
+            }
+        }
+        if matches!(c, '*' | '_') && !double && opens_emphasis(&chars, i) {
+            if let Some(end) = (i + 1..chars.len()).find(|&j| closes_emphasis(&chars, j, c, i)) {
+                flush(&mut plain, &mut spans);
+                let inner = parse_inline(&chars[i + 1..end].iter().collect::<String>());
+                spans.push(Span::Italic(inner));
+                i = end + 1;
+                continue;
+            }
+        }
+        if c == '[' {
+            if let Some(link) = parse_link(&chars, i) {
+                flush(&mut plain, &mut spans);
+                let (label, url, end) = link;
+                spans.push(Span::Link(parse_inline(&label), url));
+                i = end;
+                continue;
+            }
+        }
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut plain, &mut spans);
+    spans
+}
+
+fn find_seq(chars: &[char], from: usize, seq: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&j| chars[j..].starts_with(seq))
+}
+
+/// `_` only emphasizes at word boundaries so `snake_case` stays literal.
+fn opens_emphasis(chars: &[char], i: usize) -> bool {
+    let next = chars.get(i + 1);
+    let prev = i.checked_sub(1).map(|j| chars[j]);
+    next.is_some_and(|v| !v.is_whitespace())
+        && (chars[i] == '*' || !prev.is_some_and(|v| v.is_alphanumeric()))
+}
+
+fn closes_emphasis(chars: &[char], j: usize, c: char, start: usize) -> bool {
+    chars[j] == c
+        && j > start + 1
+        && !chars[j - 1].is_whitespace()
+        && chars.get(j + 1) != Some(&c)
+        && (c == '*' || !chars.get(j + 1).is_some_and(|v| v.is_alphanumeric()))
+}
+
+/// `[label](url)` starting at `i`; returns the label, url and the index after `)`.
+fn parse_link(chars: &[char], i: usize) -> Option<(String, String, usize)> {
+    let close = find_seq(chars, i + 1, &[']', '('])?;
+    let end = (close + 2..chars.len()).find(|&j| chars[j] == ')')?;
+    let url: String = chars[close + 2..end].iter().collect();
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((chars[i + 1..close].iter().collect(), url, end + 1))
+}
+
+fn render_spans(spans: &[Span], format: ChatFormat) -> String {
+    spans.iter().map(|span| render_span(span, format)).collect()
+}
+
+fn render_span(span: &Span, format: ChatFormat) -> String {
+    let wrap = |open: &str, inner: &[Span], close: &str| {
+        format!("{open}{}{close}", render_spans(inner, format))
+    };
+    match (format, span) {
+        (ChatFormat::Html, Span::Text(v)) => escape_html(v),
+        (ChatFormat::Html, Span::Code(v)) => format!("<code>{}</code>", escape_html(v)),
+        (ChatFormat::Html, Span::Bold(v)) => wrap("<b>", v, "</b>"),
+        (ChatFormat::Html, Span::Italic(v)) => wrap("<i>", v, "</i>"),
+        (ChatFormat::Html, Span::Strike(v)) => wrap("<s>", v, "</s>"),
+        (ChatFormat::Html, Span::Link(label, url)) => {
+            wrap(&format!("<a href=\"{}\">", escape_html(url)), label, "</a>")
+        }
+        (ChatFormat::MarkdownV2, Span::Text(v)) => escape_markdown_v2(v),
+        (ChatFormat::MarkdownV2, Span::Code(v)) => {
+            format!("`{}`", escape_markdown_v2_code(v))
+        }
+        (ChatFormat::MarkdownV2, Span::Bold(v)) => wrap("*", v, "*"),
+        (ChatFormat::MarkdownV2, Span::Italic(v)) => wrap("_", v, "_"),
+        (ChatFormat::MarkdownV2, Span::Strike(v)) => wrap("~", v, "~"),
+        (ChatFormat::MarkdownV2, Span::Link(label, url)) => {
+            let url = url.replace('\\', "\\\\").replace(')', "\\)");
+            wrap("[", label, &format!("]({url})"))
+        }
+        (ChatFormat::Mrkdwn, Span::Text(v)) => escape_mrkdwn(v),
+        (ChatFormat::Mrkdwn, Span::Code(v)) => format!("`{}`", escape_mrkdwn(v)),
+        (ChatFormat::Mrkdwn, Span::Bold(v)) => wrap("*", v, "*"),
+        (ChatFormat::Mrkdwn, Span::Italic(v)) => wrap("_", v, "_"),
+        (ChatFormat::Mrkdwn, Span::Strike(v)) => wrap("~", v, "~"),
+        (ChatFormat::Mrkdwn, Span::Link(label, url)) => {
+            let label = plain_text(label).replace('|', "/");
+            format!("<{}|{}>", escape_mrkdwn(url), escape_mrkdwn(&label))
+        }
+    }
+}
+
+fn plain_text(spans: &[Span]) -> String {
+    spans
+        .iter()
+        .map(|span| match span {
+            Span::Text(v) | Span::Code(v) => v.clone(),
+            Span::Bold(v) | Span::Italic(v) | Span::Strike(v) | Span::Link(v, _) => plain_text(v),
+        })
+        .collect()
+}
+
+/// The text of rendered HTML: tags dropped and entities decoded. Rendering escapes every
+/// literal `<`, so each one left starts a tag.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_markdown_v2(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output
+}
+
+fn escape_markdown_v2_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+fn escape_mrkdwn(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Split `line` at spaces (or anywhere, for long words) so each rendered piece fits.
+fn split_words(
+    line: &str,
+    max_len: usize,
+    format: ChatFormat,
+    render: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let mut pieces = vec![];
+    let mut rest = line;
+    while !rest.is_empty() {
+        let at = fit_prefix(rest, max_len, format, &render);
+        let at = match rest[..at].rfind(' ') {
+            Some(space) if at < rest.len() && space > 0 => space + 1,
+            _ => at,
+        };
+        pieces.push(render(rest[..at].trim_end()));
+        rest = &rest[at..];
+    }
+    pieces
+}
+
+/// Longest prefix of `text` (in bytes, at a char boundary, at least one char) whose
+/// rendering fits in `max_len`.
+fn fit_prefix(
+    text: &str,
+    max_len: usize,
+    format: ChatFormat,
+    render: impl Fn(&str) -> String,
+) -> usize {
+    let bounds: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .skip(1)
+        .chain([text.len()])
+        .collect();
+    let (mut lo, mut hi) = (0, bounds.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if format.text_len(&render(&text[..bounds[mid]])) <= max_len {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    bounds[lo]
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_chat(text: &str, format: ChatFormat) -> String {
+        parse_blocks(text)
+            .iter()
+            .map(|block| block.render(format))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_inline_html() {
+        let render = |v| render_chat(v, ChatFormat::Html);
+        assert_eq!(render("a < b && c > d"), "a &lt; b &amp;&amp; c &gt; d");
+        assert_eq!(
+            render("**bold** _it_ ~~gone~~ `x<y` [docs](https://x.io/?a=1&b=2)"),
+            "<b>bold</b> <i>it</i> <s>gone</s> <code>x&lt;y</code> <a href=\"https://x.io/?a=1&amp;b=2\">docs</a>"
+        );
+        assert_eq!(
+            render("my_var_name and 2 * 3 * 4"),
+            "my_var_name and 2 * 3 * 4"
+        );
+        assert_eq!(render("**unclosed"), "**unclosed");
+        assert_eq!(render("## Status"), "<b>Status</b>");
+        assert_eq!(render("- one\n  * two"), "• one\n  • two");
+        assert_eq!(render("---"), "──────────");
+        assert_eq!(
+            render("> quoted\n> more"),
+            "<blockquote>quoted\nmore</blockquote>"
+        );
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let text = "Run:\n```bash\necho <x> `y`\n```\ndone";
+        assert_eq!(
+            render_chat(text, ChatFormat::Html),
+            "Run:\n<pre><code class=\"language-bash\">echo &lt;x&gt; `y`</code></pre>\ndone"
+        );
+        assert_eq!(
+            render_chat(text, ChatFormat::MarkdownV2),
+            "Run:\n```bash\necho <x> \\`y\\`\n```\ndone"
+        );
+        assert_eq!(
+            render_chat(text, ChatFormat::Mrkdwn),
+            "Run:\n```\necho &lt;x&gt; `y`\n```\ndone"
+        );
+        assert_eq!(
+            render_chat("```\nunterminated", ChatFormat::Html),
+            "<pre><code>unterminated</code></pre>"
+        );
+        assert_eq!(
+            render_chat("```a\"b\nx\n```", ChatFormat::Html),
+            "<pre><code>x</code></pre>"
+        );
+        assert_eq!(
+            render_chat(
+                "\u{3000}```\n\u{a0}\u{a0}x\n\u{3000}y\n```",
+                ChatFormat::Html
+            ),
+            "<pre><code>\u{a0}x\ny</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_markdown_v2() {
+        assert_eq!(
+            render_chat("**Disk** at 95.5% (db-1)!", ChatFormat::MarkdownV2),
+            "*Disk* at 95\\.5% \\(db\\-1\\)\\!"
+        );
+        assert_eq!(
+            render_chat("[a.b](https://x.io/a_b)", ChatFormat::MarkdownV2),
+            "[a\\.b](https://x.io/a_b)"
+        );
+        assert_eq!(
+            render_chat("1. first", ChatFormat::MarkdownV2),
+            "1\\. first"
+        );
+    }
+
+    #[test]
+    fn test_mrkdwn() {
+        assert_eq!(
+            render_chat(
+                "**Deploy** _done_ see [logs](https://x.io/l?a=1) & <tag>",
+                ChatFormat::Mrkdwn
+            ),
+            "*Deploy* _done_ see <https://x.io/l?a=1|logs> &amp; &lt;tag&gt;"
+        );
+        assert_eq!(render_chat("> note", ChatFormat::Mrkdwn), "> note");
+    }
+
+    #[test]
+    fn test_tables() {
+        let text = "Hosts:\n| Host | **CPU** |\n|:-----|----:|\n| db-1 | 95% |\n| web | 3% |\nend";
+        assert_eq!(
+            render_chat(text, ChatFormat::Html),
+            "Hosts:\n<pre><code>Host | CPU\n-----+----\ndb-1 | 95%\nweb  | 3%</code></pre>\nend"
+        );
+        assert_eq!(
+            render_chat("| not | a table |", ChatFormat::Html),
+            "| not | a table |"
+        );
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(render_chat_chunks("", ChatFormat::Html, 10), vec![""]);
+        assert_eq!(
+            render_chat_chunks("aaaa\nbbbb\ncc", ChatFormat::Html, 6),
+            vec!["aaaa", "bbbb", "cc"]
+        );
+
+        let code: Vec<String> = (0..40).map(|i| format!("line {i} <x>")).collect();
+        let text = format!("intro\n```sh\n{}\n```\nafter", code.join("\n"));
+        for format in [ChatFormat::Html, ChatFormat::MarkdownV2, ChatFormat::Mrkdwn] {
+            let chunks = render_chat_chunks(&text, format, 200);
+            assert!(chunks.len() > 2);
+            for chunk in &chunks {
+                assert!(char_len(chunk) <= 200, "{chunk:?}");
+                match format {
+                    ChatFormat::Html => assert_eq!(
+                        chunk.matches("<pre>").count(),
+                        chunk.matches("</pre>").count()
+                    ),
+                    _ => assert_eq!(chunk.matches("```").count() % 2, 0, "{chunk:?}"),
+                }
+            }
+            let joined = chunks.join("\n");
+            assert!(joined.contains("line 0 ") && joined.contains("line 39 "));
+            assert!(chunks[0].starts_with("intro"));
+            assert!(chunks.last().unwrap().ends_with("after"));
+        }
+
+        let long = format!("{} {}", "word ".repeat(30).trim(), "<".repeat(50));
+        let chunks = render_chat_chunks(&long, ChatFormat::Html, 40);
+        assert!(chunks.iter().all(|v| char_len(v) <= 40));
+        assert!(chunks.iter().all(|v| !v.ends_with('&')));
+        assert_eq!(chunks.concat().matches("&lt;").count(), 50);
+
+        let chunks = render_chat_chunks(
+            &format!("```\n{}\n```", "x".repeat(100)),
+            ChatFormat::Html,
+            40,
+        );
+        assert!(chunks.len() > 2);
+        assert!(chunks.iter().all(|v| char_len(v) <= 40
+            && v.starts_with("<pre><code>")
+            && v.ends_with("</code></pre>")));
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let html = render_chat(
+            "**a** < b && `c` [d](https://x.io/?a=1&b=2)",
+            ChatFormat::Html,
+        );
+        assert_eq!(strip_html(&html), "a < b && c d");
+        assert_eq!(strip_html("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn test_chunks_count_utf16_for_telegram() {
+        let text = "🔥".repeat(30);
+        assert_eq!(ChatFormat::Html.text_len(&text), 60);
+        assert_eq!(ChatFormat::Mrkdwn.text_len(&text), 30);
+        let chunks = render_chat_chunks(&text, ChatFormat::Html, 40);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|v| v.encode_utf16().count() <= 40));
+        assert_eq!(chunks.concat(), text);
+        assert_eq!(render_chat_chunks(&text, ChatFormat::Mrkdwn, 40), [text]);
+    }
+}
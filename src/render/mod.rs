@@ -1,6 +1,8 @@
+mod chat;
 mod markdown;
 mod stream;
 
+pub use self::chat::{render_chat_chunks, strip_html, ChatFormat, CHAT_MAX_CHARS};
 pub use self::markdown::{MarkdownRender, RenderOptions};
 use self::stream::{markdown_stream, raw_stream};
 
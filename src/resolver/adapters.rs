@@ -0,0 +1,330 @@
+//! Workspace discovery for resolver providers.
+//!
+//! A [`ProviderAdapter`] lists a provider's workspaces (Linear teams, GitHub repos, Jira
+//! projects, Sentry orgs, ...) through a tool on an MCP server. Each discovered workspace
+//! is stored with that server as its `target_profile`, so a resolved turn is scoped to
+//! the right server. Linear is built in; other providers are configured under
+//! `resolver_providers` with a listing tool and a field mapping.
+
+use crate::function::glob_match;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// A workspace found by an adapter: its display name and extra aliases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredWorkspace {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+pub trait ProviderAdapter: Send + Sync {
+    /// Canonical provider key, e.g. `linear`.
+    fn provider(&self) -> &str;
+
+    /// Aliases registered for the provider itself.
+    fn provider_aliases(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Whether this adapter can list workspaces on the MCP server `server`.
+    fn matches_server(&self, server: &str) -> bool;
+
+    /// The listing tool, without the `mcp__<server>__` prefix.
+    fn list_tool(&self) -> &str;
+
+    fn list_arguments(&self) -> Value {
+        Value::Object(Map::new())
+    }
+
+    /// Workspaces in one JSON document of the listing tool's result.
+    fn parse_workspaces(&self, value: &Value) -> Vec<DiscoveredWorkspace>;
+
+    /// A workspace implied by the server's configuration alone, without calling it.
+    fn profile_workspace(&self, _server: &str) -> Option<DiscoveredWorkspace> {
+        None
+    }
+}
+
+/// Workspaces in a raw `tools/call` result, trying `structuredContent`, then each text
+/// part that holds JSON, then the result itself.
+pub fn discover(adapter: &dyn ProviderAdapter, raw: &Value) -> Result<Vec<DiscoveredWorkspace>> {
+    let mut documents = vec![];
+    if let Some(value) = raw.get("structuredContent") {
+        documents.push(value.clone());
+    }
+    if let Some(content) = raw.get("content").and_then(|v| v.as_array()) {
+        for item in content {
+            if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
+                if let Ok(parsed) = serde_json::from_str::<Value>(text) {
+                    documents.push(parsed);
+                }
+            }
+        }
+    }
+    documents.push(raw.clone());
+
+    for document in &documents {
+        let mut workspaces = adapter.parse_workspaces(document);
+        if !workspaces.is_empty() {
+            workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+            workspaces.dedup_by(|a, b| a.name == b.name);
+            return Ok(workspaces);
+        }
+    }
+    bail!(
+        "Unable to parse {} workspaces from the '{}' result",
+        adapter.provider(),
+        adapter.list_tool()
+    )
+}
+
+/// Linear: teams from `list_teams`, keyed by team key, on `linear` / `linear-<slug>`
+/// servers. A `linear-<slug>` server is itself the `<SLUG>` workspace.
+pub struct LinearAdapter;
+
+impl ProviderAdapter for LinearAdapter {
+    fn provider(&self) -> &str {
+        "linear"
+    }
+
+    fn provider_aliases(&self) -> Vec<String> {
+        vec!["ln".to_string()]
+    }
+
+    fn matches_server(&self, server: &str) -> bool {
+        server == "linear" || server.starts_with("linear-")
+    }
+
+    fn list_tool(&self) -> &str {
+        "list_teams"
+    }
+
+    fn parse_workspaces(&self, value: &Value) -> Vec<DiscoveredWorkspace> {
+        let mut teams = vec![];
+        collect_objects(value, &mut teams, &|obj| {
+            let name = obj.get("name")?.as_str()?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let canonical = obj
+                .get("key")
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .unwrap_or(name)
+                .to_ascii_uppercase();
+            let mut aliases = vec![canonical.to_ascii_lowercase(), name.to_ascii_lowercase()];
+            aliases.sort();
+            aliases.dedup();
+            Some(DiscoveredWorkspace {
+                name: canonical,
+                aliases,
+            })
+        });
+        teams
+    }
+
+    fn profile_workspace(&self, server: &str) -> Option<DiscoveredWorkspace> {
+        let slug = server.strip_prefix("linear-")?.trim();
+        if slug.is_empty() {
+            return None;
+        }
+        Some(DiscoveredWorkspace {
+            name: slug.to_ascii_uppercase(),
+            aliases: vec![slug.to_lowercase()],
+        })
+    }
+}
+
+/// A provider configured under `resolver_providers`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderSyncConfig {
+    pub provider: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Glob over MCP server names, e.g. `github*`.
+    pub servers: String,
+    pub list_tool: String,
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+    /// Dotted path to the array of workspaces; when omitted, every object with the
+    /// name field is taken.
+    pub items: Option<String>,
+    pub fields: FieldMapping,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMapping {
+    /// Dotted path, within an item, to the workspace name.
+    pub name: String,
+    /// Dotted paths to extra aliases.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+impl ProviderAdapter for ProviderSyncConfig {
+    fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    fn provider_aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn matches_server(&self, server: &str) -> bool {
+        glob_match(server, &self.servers)
+    }
+
+    fn list_tool(&self) -> &str {
+        &self.list_tool
+    }
+
+    fn list_arguments(&self) -> Value {
+        Value::Object(self.arguments.clone())
+    }
+
+    fn parse_workspaces(&self, value: &Value) -> Vec<DiscoveredWorkspace> {
+        let item = |obj: &Map<String, Value>| {
+            let obj = Value::Object(obj.clone());
+            let name = path_str(&obj, &self.fields.name)?;
+            let mut aliases = vec![name.to_lowercase()];
+            for field in &self.fields.aliases {
+                let alias = path_str(&obj, field).map(|v| v.to_lowercase());
+                if let Some(alias) = alias.filter(|v| !aliases.contains(v)) {
+                    aliases.push(alias);
+                }
+            }
+            Some(DiscoveredWorkspace { name, aliases })
+        };
+        let mut workspaces = vec![];
+        match &self.items {
+            Some(items) => {
+                let items = path(value, items).and_then(|v| v.as_array());
+                for obj in items.into_iter().flatten().filter_map(|v| v.as_object()) {
+                    workspaces.extend(item(obj));
+                }
+            }
+            None => collect_objects(value, &mut workspaces, &item),
+        }
+        workspaces
+    }
+}
+
+/// Depth-first, take each object `parse` accepts instead of descending into it.
+fn collect_objects<T>(
+    value: &Value,
+    output: &mut Vec<T>,
+    parse: &dyn Fn(&Map<String, Value>) -> Option<T>,
+) {
+    match value {
+        Value::Object(obj) => match parse(obj) {
+            Some(v) => output.push(v),
+            None => {
+                for nested in obj.values() {
+                    collect_objects(nested, output, parse);
+                }
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                collect_objects(item, output, parse);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|v| !v.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        })
+}
+
+fn path_str(value: &Value, field: &str) -> Option<String> {
+    let value = match path(value, field)? {
+        Value::String(v) => v.trim().to_string(),
+        Value::Number(v) => v.to_string(),
+        _ => return None,
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn linear_teams_from_text_content() {
+        let teams =
+            json!({"teams": [{"id": "1", "key": "sam", "name": "Samwise"}, {"name": "Ops"}]});
+        let raw = json!({"content": [{"type": "text", "text": teams.to_string()}]});
+        let workspaces = discover(&LinearAdapter, &raw).unwrap();
+        assert_eq!(
+            workspaces,
+            [
+                DiscoveredWorkspace {
+                    name: "OPS".into(),
+                    aliases: vec!["ops".into()]
+                },
+                DiscoveredWorkspace {
+                    name: "SAM".into(),
+                    aliases: vec!["sam".into(), "samwise".into()]
+                },
+            ]
+        );
+        assert_eq!(
+            LinearAdapter.profile_workspace("linear-acme").unwrap().name,
+            "ACME"
+        );
+        assert!(!LinearAdapter.matches_server("linearish"));
+    }
+
+    #[test]
+    fn configured_adapter_maps_fields() {
+        let adapter: ProviderSyncConfig = serde_yaml::from_str(
+            r#"
+provider: github
+aliases: [gh]
+servers: "github*"
+list_tool: list_repositories
+arguments: { per_page: 100 }
+items: data.repositories
+fields: { name: full_name, aliases: [name, owner.login] }
+"#,
+        )
+        .unwrap();
+        assert!(adapter.matches_server("github-work"));
+        assert_eq!(adapter.list_arguments(), json!({"per_page": 100}));
+
+        let raw = json!({"structuredContent": {"data": {"repositories": [
+            {"full_name": "acme/api", "name": "api", "owner": {"login": "acme"}},
+            {"full_name": "acme/web", "name": "web"},
+            {"name": "no-full-name"},
+        ]}}});
+        let workspaces = discover(&adapter, &raw).unwrap();
+        assert_eq!(workspaces.len(), 2);
+        assert_eq!(workspaces[0].name, "acme/api");
+        assert_eq!(workspaces[0].aliases, ["acme/api", "api", "acme"]);
+
+        let jira = ProviderSyncConfig {
+            items: None,
+            fields: FieldMapping {
+                name: "key".into(),
+                aliases: vec!["name".into()],
+            },
+            ..adapter
+        };
+        let raw = json!([{"project": {"key": "CORE", "name": "Core Platform"}}]);
+        let workspaces = discover(&jira, &raw).unwrap();
+        assert_eq!(workspaces[0].aliases, ["core", "core platform"]);
+        assert!(discover(&jira, &json!({"nothing": []})).is_err());
+    }
+}
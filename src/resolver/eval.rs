@@ -0,0 +1,614 @@
+//! Offline evaluation of the resolver against a labelled corpus (`fio resolver eval`).
+//!
+//! A corpus is JSONL, one `{"text", "provider", "workspace", "action"}` object per line;
+//! a null or missing label means the utterance should not resolve that field. Blank
+//! lines and lines starting with `#` are skipped. The report can be saved and passed
+//! back as a baseline, in which case only utterances that newly fail count as
+//! regressions.
+
+use super::{ResolutionOutcome, ResolvedIntent, Resolver};
+
+use crate::config::GlobalConfig;
+use crate::utils::AbortSignal;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+const FIELDS: [&str; 3] = ["provider", "workspace", "action"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvalCase {
+    pub text: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub action: Option<String>,
+}
+
+impl EvalCase {
+    fn labels(&self) -> Labels {
+        Labels::new(
+            self.provider.as_deref(),
+            self.workspace.as_deref(),
+            self.action.as_deref(),
+        )
+    }
+}
+
+/// Normalized provider/workspace/action labels; compared case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Labels {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
+impl Labels {
+    fn new(provider: Option<&str>, workspace: Option<&str>, action: Option<&str>) -> Self {
+        let norm = |v: Option<&str>| v.map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty());
+        Self {
+            provider: norm(provider),
+            workspace: norm(workspace),
+            action: norm(action),
+        }
+    }
+
+    fn from_intent(intent: &ResolvedIntent) -> Self {
+        Self::new(
+            Some(&intent.provider),
+            intent.workspace.as_deref(),
+            intent.action.as_deref(),
+        )
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "provider" => self.provider.as_deref(),
+            "workspace" => self.workspace.as_deref(),
+            _ => self.action.as_deref(),
+        }
+    }
+}
+
+/// What the resolver made of one utterance.
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    /// `resolved`, `ai`, `needs_ai` or `pass_through`.
+    pub outcome: &'static str,
+    pub intent: Option<ResolvedIntent>,
+}
+
+/// How `NeedsAi` outcomes are handled during evaluation.
+pub enum AiMode {
+    /// Count them as unresolved.
+    Off,
+    /// Ask the configured model, keeping every answer for `--record`.
+    Live(Vec<AiRecording>),
+    /// Answer from a recording, keyed by utterance.
+    Replay(HashMap<String, String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiRecording {
+    pub text: String,
+    pub output: String,
+}
+
+impl AiMode {
+    pub fn replay(path: &Path) -> Result<Self> {
+        let recordings: Vec<AiRecording> = read_jsonl(path)?;
+        Ok(Self::Replay(
+            recordings.into_iter().map(|v| (v.text, v.output)).collect(),
+        ))
+    }
+
+    /// Write the answers collected in live mode to `path` as JSONL.
+    pub fn save_recordings(&self, path: &Path) -> Result<()> {
+        let Self::Live(recordings) = self else {
+            return Ok(());
+        };
+        let mut data = String::new();
+        for recording in recordings {
+            data.push_str(&serde_json::to_string(recording)?);
+            data.push('\n');
+        }
+        write(path, data)
+            .with_context(|| format!("Failed to write AI recordings to '{}'", path.display()))
+    }
+}
+
+pub fn load_corpus(path: &Path) -> Result<Vec<EvalCase>> {
+    let cases: Vec<EvalCase> = read_jsonl(path)?;
+    if cases.is_empty() {
+        bail!("Corpus '{}' has no utterances", path.display());
+    }
+    Ok(cases)
+}
+
+fn read_jsonl<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+    let data =
+        read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let mut items = vec![];
+    for (index, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let item = serde_json::from_str(line)
+            .with_context(|| format!("Invalid entry at '{}' line {}", path.display(), index + 1))?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Run the deterministic pass over every case, consulting the AI for `NeedsAi`
+/// outcomes according to `ai`.
+pub async fn predict(
+    resolver: &Resolver,
+    config: &GlobalConfig,
+    cases: &[EvalCase],
+    ai: &mut AiMode,
+    abort_signal: AbortSignal,
+) -> Result<Vec<Prediction>> {
+    let mut predictions = vec![];
+    for case in cases {
+        let prediction = match resolver.resolve(&case.text) {
+            ResolutionOutcome::Resolved(intent) => Prediction {
+                outcome: "resolved",
+                intent: Some(intent),
+            },
+            ResolutionOutcome::PassThrough => Prediction {
+                outcome: "pass_through",
+                intent: None,
+            },
+            ResolutionOutcome::NeedsAi => {
+                let output = match ai {
+                    AiMode::Off => None,
+                    AiMode::Live(recordings) => {
+                        let output = crate::router::resolver_ai_output(
+                            resolver,
+                            config,
+                            abort_signal.clone(),
+                            &case.text,
+                        )
+                        .await?;
+                        recordings.push(AiRecording {
+                            text: case.text.clone(),
+                            output: output.clone(),
+                        });
+                        Some(output)
+                    }
+                    AiMode::Replay(recordings) => match recordings.get(&case.text) {
+                        Some(output) => Some(output.clone()),
+                        None => bail!(
+                            "No recorded AI answer for '{}'; record one with `--ai --record`",
+                            case.text
+                        ),
+                    },
+                };
+                match output.and_then(|v| resolver.parse_ai_output(&v)) {
+                    Some(intent) => Prediction {
+                        outcome: "ai",
+                        intent: Some(intent),
+                    },
+                    None => Prediction {
+                        outcome: "needs_ai",
+                        intent: None,
+                    },
+                }
+            }
+        };
+        predictions.push(prediction);
+    }
+    Ok(predictions)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub total: usize,
+    pub correct: usize,
+    pub outcomes: BTreeMap<String, usize>,
+    pub fields: Vec<FieldScore>,
+    pub confusions: Vec<Confusion>,
+    pub confidence: Vec<ConfidenceBucket>,
+    pub cases: Vec<CaseResult>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldScore {
+    pub field: String,
+    pub accuracy: f32,
+    pub labels: Vec<LabelScore>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LabelScore {
+    pub label: String,
+    /// Utterances labelled with it.
+    pub support: usize,
+    /// Utterances resolved to it.
+    pub predicted: usize,
+    pub correct: usize,
+    pub precision: f32,
+    pub recall: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Confusion {
+    pub field: String,
+    pub expected: String,
+    pub predicted: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfidenceBucket {
+    pub range: String,
+    pub correct: usize,
+    pub incorrect: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub text: String,
+    pub correct: bool,
+    pub outcome: String,
+    pub expected: Labels,
+    pub predicted: Labels,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+impl EvalReport {
+    pub fn build(cases: &[EvalCase], predictions: &[Prediction]) -> Self {
+        let mut report = Self {
+            total: cases.len(),
+            ..Default::default()
+        };
+        for (case, prediction) in cases.iter().zip(predictions) {
+            let expected = case.labels();
+            let predicted = prediction
+                .intent
+                .as_ref()
+                .map(Labels::from_intent)
+                .unwrap_or_default();
+            let correct = expected == predicted;
+            report.correct += usize::from(correct);
+            *report
+                .outcomes
+                .entry(prediction.outcome.to_string())
+                .or_default() += 1;
+            report.cases.push(CaseResult {
+                text: case.text.clone(),
+                correct,
+                outcome: prediction.outcome.to_string(),
+                expected,
+                predicted,
+                confidence: prediction.intent.as_ref().map(|v| v.confidence),
+            });
+        }
+        report.fields = FIELDS.iter().map(|v| report.field_score(v)).collect();
+        report.confusions = report.confusions();
+        report.confidence = report.confidence_buckets();
+        report
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        ratio(self.correct, self.total)
+    }
+
+    fn field_score(&self, field: &str) -> FieldScore {
+        let mut labels: BTreeMap<&str, LabelScore> = BTreeMap::new();
+        let mut matched = 0;
+        for case in &self.cases {
+            let expected = case.expected.field(field);
+            let predicted = case.predicted.field(field);
+            matched += usize::from(expected == predicted);
+            if let Some(label) = expected {
+                labels.entry(label).or_default().support += 1;
+            }
+            if let Some(label) = predicted {
+                let score = labels.entry(label).or_default();
+                score.predicted += 1;
+                score.correct += usize::from(expected == predicted);
+            }
+        }
+        let labels = labels
+            .into_iter()
+            .map(|(label, score)| LabelScore {
+                label: label.to_string(),
+                precision: ratio(score.correct, score.predicted),
+                recall: ratio(score.correct, score.support),
+                ..score
+            })
+            .collect();
+        FieldScore {
+            field: field.to_string(),
+            accuracy: ratio(matched, self.cases.len()),
+            labels,
+        }
+    }
+
+    fn confusions(&self) -> Vec<Confusion> {
+        let mut counts: BTreeMap<(&str, &str, &str), usize> = BTreeMap::new();
+        for case in &self.cases {
+            for field in FIELDS {
+                let expected = case.expected.field(field);
+                let predicted = case.predicted.field(field);
+                if expected != predicted {
+                    let key = (field, expected.unwrap_or("-"), predicted.unwrap_or("-"));
+                    *counts.entry(key).or_default() += 1;
+                }
+            }
+        }
+        let mut confusions: Vec<_> = counts
+            .into_iter()
+            .map(|((field, expected, predicted), count)| Confusion {
+                field: field.to_string(),
+                expected: expected.to_string(),
+                predicted: predicted.to_string(),
+                count,
+            })
+            .collect();
+        confusions.sort_by_key(|v| std::cmp::Reverse(v.count));
+        confusions
+    }
+
+    /// Resolved utterances by confidence, in tenths.
+    fn confidence_buckets(&self) -> Vec<ConfidenceBucket> {
+        let mut buckets: Vec<ConfidenceBucket> = (0..10)
+            .map(|i| ConfidenceBucket {
+                range: format!("{:.1}-{:.1}", i as f32 / 10.0, (i + 1) as f32 / 10.0),
+                correct: 0,
+                incorrect: 0,
+            })
+            .collect();
+        for case in &self.cases {
+            let Some(confidence) = case.confidence else {
+                continue;
+            };
+            let bucket = &mut buckets[((confidence * 10.0) as usize).min(9)];
+            match case.correct {
+                true => bucket.correct += 1,
+                false => bucket.incorrect += 1,
+            }
+        }
+        buckets.retain(|v| v.correct + v.incorrect > 0);
+        buckets
+    }
+
+    /// Failing utterances, except those that already failed in `baseline`.
+    pub fn regressions(&self, baseline: Option<&EvalReport>) -> Vec<&CaseResult> {
+        let known_failures: Vec<&str> = baseline
+            .map(|v| {
+                v.cases
+                    .iter()
+                    .filter(|v| !v.correct)
+                    .map(|v| v.text.as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.cases
+            .iter()
+            .filter(|v| !v.correct && !known_failures.contains(&v.text.as_str()))
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = read_to_string(path)
+            .with_context(|| format!("Failed to read report '{}'", path.display()))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("Invalid report at '{}'", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write report to '{}'", path.display()))
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = format!(
+            "accuracy: {:.3} ({}/{})\noutcomes: {}\n",
+            self.accuracy(),
+            self.correct,
+            self.total,
+            self.outcomes
+                .iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        for field in &self.fields {
+            output.push_str(&format!(
+                "\n{} (accuracy {:.3})\n",
+                field.field, field.accuracy
+            ));
+            for score in &field.labels {
+                output.push_str(&format!(
+                    "  {:<24} precision {:.3}  recall {:.3}  support {}\n",
+                    score.label, score.precision, score.recall, score.support
+                ));
+            }
+        }
+        if !self.confusions.is_empty() {
+            output.push_str("\nconfusions (expected -> predicted)\n");
+            for v in &self.confusions {
+                output.push_str(&format!(
+                    "  {:<9} {} -> {}  x{}\n",
+                    v.field, v.expected, v.predicted, v.count
+                ));
+            }
+        }
+        if !self.confidence.is_empty() {
+            output.push_str("\nconfidence\n");
+            for v in &self.confidence {
+                output.push_str(&format!(
+                    "  {}  correct {:<4} incorrect {}\n",
+                    v.range, v.correct, v.incorrect
+                ));
+            }
+        }
+        output
+    }
+}
+
+fn ratio(n: usize, d: usize) -> f32 {
+    match d {
+        0 => 0.0,
+        _ => n as f32 / d as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(
+        text: &str,
+        provider: Option<&str>,
+        workspace: Option<&str>,
+        action: Option<&str>,
+    ) -> EvalCase {
+        EvalCase {
+            text: text.to_string(),
+            provider: provider.map(str::to_string),
+            workspace: workspace.map(str::to_string),
+            action: action.map(str::to_string),
+        }
+    }
+
+    fn resolved(
+        provider: &str,
+        workspace: Option<&str>,
+        action: Option<&str>,
+        confidence: f32,
+    ) -> Prediction {
+        Prediction {
+            outcome: "resolved",
+            intent: Some(ResolvedIntent {
+                provider: provider.to_string(),
+                workspace: workspace.map(str::to_string),
+                target_profile: None,
+                action: action.map(str::to_string),
+                confidence,
+                reason: "test".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn report_scores_fields_and_confusions() {
+        let cases = vec![
+            case(
+                "file a SAM ticket",
+                Some("linear"),
+                Some("SAM"),
+                Some("create_tickets"),
+            ),
+            case(
+                "what's open in SAM",
+                Some("linear"),
+                Some("sam"),
+                Some("list_issues"),
+            ),
+            case(
+                "open a bug for OPS",
+                Some("linear"),
+                Some("OPS"),
+                Some("create_tickets"),
+            ),
+            case("tell me a joke", None, None, None),
+        ];
+        let predictions = vec![
+            resolved("linear", Some("SAM"), Some("create_tickets"), 1.0),
+            resolved("linear", Some("SAM"), Some("create_tickets"), 0.85),
+            Prediction {
+                outcome: "needs_ai",
+                intent: None,
+            },
+            Prediction {
+                outcome: "pass_through",
+                intent: None,
+            },
+        ];
+        let report = EvalReport::build(&cases, &predictions);
+        assert_eq!((report.correct, report.total), (2, 4));
+        assert_eq!(report.outcomes["resolved"], 2);
+
+        let action = &report.fields[2];
+        assert_eq!(action.field, "action");
+        let create = &action.labels[0];
+        assert_eq!(create.label, "create_tickets");
+        assert_eq!(
+            (create.support, create.predicted, create.correct),
+            (2, 2, 1)
+        );
+        assert_eq!((create.precision, create.recall), (0.5, 0.5));
+        let list = &action.labels[1];
+        assert_eq!((list.precision, list.recall), (0.0, 0.0));
+
+        let provider = &report.fields[0];
+        assert_eq!(provider.accuracy, 0.75);
+        assert_eq!(provider.labels[0].precision, 1.0);
+
+        assert!(report.confusions.iter().any(|v| v.field == "action"
+            && v.expected == "list_issues"
+            && v.predicted == "create_tickets"));
+        assert!(report
+            .confusions
+            .iter()
+            .any(|v| v.field == "workspace" && v.expected == "ops" && v.predicted == "-"));
+
+        let ranges: Vec<_> = report.confidence.iter().map(|v| v.range.as_str()).collect();
+        assert_eq!(ranges, ["0.8-0.9", "0.9-1.0"]);
+        assert_eq!(report.confidence[0].incorrect, 1);
+        assert!(report.render().contains("accuracy: 0.500 (2/4)"));
+    }
+
+    #[test]
+    fn regressions_ignore_known_failures() {
+        let cases = vec![
+            case(
+                "file a SAM ticket",
+                Some("linear"),
+                Some("SAM"),
+                Some("create_tickets"),
+            ),
+            case("ping ops", Some("linear"), Some("OPS"), None),
+        ];
+        let failing = vec![
+            resolved("linear", Some("SAM"), Some("create_tickets"), 1.0),
+            Prediction {
+                outcome: "pass_through",
+                intent: None,
+            },
+        ];
+        let baseline = EvalReport::build(&cases, &failing);
+        assert_eq!(baseline.regressions(None).len(), 1);
+
+        let json = serde_json::to_string(&baseline).unwrap();
+        let baseline: EvalReport = serde_json::from_str(&json).unwrap();
+        assert!(baseline.regressions(Some(&baseline)).is_empty());
+
+        let worse = vec![
+            resolved("linear", None, Some("create_tickets"), 0.65),
+            Prediction {
+                outcome: "pass_through",
+                intent: None,
+            },
+        ];
+        let report = EvalReport::build(&cases, &worse);
+        let regressions = report.regressions(Some(&baseline));
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].text, "file a SAM ticket");
+    }
+}
@@ -0,0 +1,295 @@
+//! Labelled example utterances for the nearest-neighbour resolver stage.
+//!
+//! Examples are embedded with `rag_embedding_model` and indexed with HNSW, the same way
+//! RAG documents are. A turn the alias pass finds a provider or action hint in but can't
+//! resolve (`NeedsAi`) is embedded and matched against them before falling back to the LLM.
+
+use super::types::ResolvedIntent;
+
+use crate::client::{init_client, EmbeddingsData, Model, ModelType};
+use crate::config::GlobalConfig;
+
+use anyhow::{Context, Result};
+use hnsw_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Cosine similarity a nearest example needs before its labels are used.
+pub const MIN_SIMILARITY: f32 = 0.85;
+/// Only the most recent examples per label are kept.
+const MAX_PER_LABEL: usize = 20;
+const NEIGHBOURS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentExample {
+    pub text: String,
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Embedding of `text`; empty until [`ExampleSet`] embeds it.
+    #[serde(default, with = "serde_vector", skip_serializing_if = "Vec::is_empty")]
+    pub vector: Vec<f32>,
+}
+
+impl IntentExample {
+    pub fn new(text: &str, provider: &str, workspace: Option<&str>, action: Option<&str>) -> Self {
+        Self {
+            text: text.trim().to_string(),
+            provider: provider.to_lowercase(),
+            workspace: workspace.map(str::to_string),
+            action: action.map(str::to_string),
+            vector: vec![],
+        }
+    }
+
+    fn same_label(&self, other: &Self) -> bool {
+        self.provider == other.provider
+            && self.workspace == other.workspace
+            && self.action == other.action
+    }
+}
+
+/// The persisted examples and the model their vectors came from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExampleSet {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    #[serde(default)]
+    pub items: Vec<IntentExample>,
+}
+
+impl ExampleSet {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Add an example, replacing one with the same text and dropping the oldest of its
+    /// label past the per-label cap.
+    pub fn add(&mut self, example: IntentExample) {
+        if example.text.is_empty() {
+            return;
+        }
+        self.items
+            .retain(|v| !v.text.eq_ignore_ascii_case(&example.text));
+        let same_label = self.items.iter().filter(|v| v.same_label(&example)).count();
+        if same_label >= MAX_PER_LABEL {
+            if let Some(index) = self.items.iter().position(|v| v.same_label(&example)) {
+                self.items.remove(index);
+            }
+        }
+        self.items.push(example);
+    }
+
+    pub fn remove(&mut self, text: &str) -> bool {
+        let len = self.items.len();
+        self.items
+            .retain(|v| !v.text.eq_ignore_ascii_case(text.trim()));
+        self.items.len() != len
+    }
+
+    fn pending(&self) -> usize {
+        self.items.iter().filter(|v| v.vector.is_empty()).count()
+    }
+
+    /// Embed examples that have no vector yet with `rag_embedding_model`, re-embedding
+    /// everything when the model changed.
+    pub async fn embed(&mut self, config: &GlobalConfig) -> Result<()> {
+        let Some(model_id) = config.read().rag_embedding_model.clone() else {
+            return Ok(());
+        };
+        if self.embedding_model.as_deref() != Some(model_id.as_str()) {
+            for item in &mut self.items {
+                item.vector.clear();
+            }
+            self.embedding_model = Some(model_id.clone());
+        }
+        if self.pending() == 0 {
+            return Ok(());
+        }
+        let texts: Vec<String> = self
+            .items
+            .iter()
+            .filter(|v| v.vector.is_empty())
+            .map(|v| v.text.clone())
+            .collect();
+        let vectors = embed_texts(config, &model_id, texts, false).await?;
+        let pending = self.items.iter_mut().filter(|v| v.vector.is_empty());
+        for (item, vector) in pending.zip(vectors) {
+            item.vector = vector;
+        }
+        Ok(())
+    }
+}
+
+/// An HNSW index over the embedded examples, like `RagData::build_hnsw`.
+#[derive(Clone)]
+pub struct ExampleIndex {
+    embedding_model: String,
+    hnsw: Arc<Hnsw<'static, f32, DistCosine>>,
+}
+
+impl std::fmt::Debug for ExampleIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExampleIndex")
+            .field("embedding_model", &self.embedding_model)
+            .finish()
+    }
+}
+
+impl ExampleIndex {
+    /// `None` when no example has been embedded yet.
+    pub fn build(examples: &ExampleSet) -> Option<Self> {
+        let embedding_model = examples.embedding_model.clone()?;
+        let list: Vec<_> = examples
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| !v.vector.is_empty())
+            .map(|(i, v)| (&v.vector, i))
+            .collect();
+        if list.is_empty() {
+            return None;
+        }
+        let hnsw = Hnsw::new(32, list.len(), 16, 200, DistCosine {});
+        hnsw.parallel_insert(&list);
+        Some(Self {
+            embedding_model,
+            hnsw: Arc::new(hnsw),
+        })
+    }
+
+    /// Embed `text` and return the labels of its nearest example, if close enough.
+    pub async fn classify(
+        &self,
+        config: &GlobalConfig,
+        examples: &ExampleSet,
+        text: &str,
+    ) -> Result<Option<ResolvedIntent>> {
+        let vectors =
+            embed_texts(config, &self.embedding_model, vec![text.to_string()], true).await?;
+        let Some(vector) = vectors.into_iter().next() else {
+            return Ok(None);
+        };
+        Ok(self.nearest(examples, &vector))
+    }
+
+    fn nearest(&self, examples: &ExampleSet, vector: &[f32]) -> Option<ResolvedIntent> {
+        let neighbour = self
+            .hnsw
+            .search(vector, NEIGHBOURS, 30)
+            .into_iter()
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))?;
+        let similarity = 1.0 - neighbour.distance;
+        if similarity < MIN_SIMILARITY {
+            return None;
+        }
+        let example = examples.items.get(neighbour.d_id)?;
+        Some(ResolvedIntent {
+            provider: example.provider.clone(),
+            workspace: example.workspace.clone(),
+            target_profile: None,
+            action: example.action.clone(),
+            confidence: similarity,
+            reason: format!("example: '{}' (similarity {similarity:.2})", example.text),
+        })
+    }
+}
+
+async fn embed_texts(
+    config: &GlobalConfig,
+    model_id: &str,
+    texts: Vec<String>,
+    query: bool,
+) -> Result<Vec<Vec<f32>>> {
+    let model = Model::retrieve_model(&config.read(), model_id, ModelType::Embedding)?;
+    let client = init_client(config, Some(model))?;
+    let mut output = vec![];
+    let batch_size = client.model().max_batch_size().unwrap_or(64).max(1);
+    for texts in texts.chunks(batch_size) {
+        let data = EmbeddingsData::new(texts.to_vec(), query);
+        let vectors = client
+            .embeddings(&data)
+            .await
+            .context("Failed to embed resolver examples")?;
+        output.extend(vectors);
+    }
+    Ok(output)
+}
+
+mod serde_vector {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(vector: &[f32], serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f32>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = STANDARD.decode(encoded).map_err(de::Error::custom)?;
+        if bytes.len() % 4 != 0 {
+            return Err(de::Error::custom("Invalid example vector"));
+        }
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(text: &str, action: &str, vector: Vec<f32>) -> IntentExample {
+        IntentExample {
+            vector,
+            ..IntentExample::new(text, "linear", Some("SAM"), Some(action))
+        }
+    }
+
+    #[test]
+    fn add_dedupes_and_caps_per_label() {
+        let mut set = ExampleSet::default();
+        set.add(example("open a bug", "create_tickets", vec![]));
+        set.add(example("Open a bug", "list_issues", vec![]));
+        assert_eq!(set.items.len(), 1);
+        assert_eq!(set.items[0].action.as_deref(), Some("list_issues"));
+
+        for i in 0..MAX_PER_LABEL + 3 {
+            set.add(example(&format!("file bug {i}"), "create_tickets", vec![]));
+        }
+        assert_eq!(set.items.len(), MAX_PER_LABEL + 1);
+        assert!(!set.items.iter().any(|v| v.text == "file bug 0"));
+        assert!(set.remove("FILE BUG 5"));
+        assert!(!set.remove("file bug 5"));
+    }
+
+    #[test]
+    fn vectors_round_trip_and_nearest_neighbour() {
+        let mut set = ExampleSet {
+            embedding_model: Some("openai:text-embedding-3-small".into()),
+            items: vec![],
+        };
+        set.add(example("open a bug", "create_tickets", vec![1.0, 0.0, 0.1]));
+        set.add(example("what is open", "list_issues", vec![0.0, 1.0, 0.1]));
+        set.add(example("not embedded yet", "list_issues", vec![]));
+
+        let json = serde_json::to_string(&set).unwrap();
+        let set: ExampleSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(set.items[0].vector, vec![1.0, 0.0, 0.1]);
+        assert_eq!(set.pending(), 1);
+
+        let index = ExampleIndex::build(&set).unwrap();
+        let intent = index.nearest(&set, &[0.9, 0.05, 0.1]).unwrap();
+        assert_eq!(intent.action.as_deref(), Some("create_tickets"));
+        assert!(intent.confidence >= MIN_SIMILARITY);
+        assert!(intent.reason.starts_with("example: 'open a bug'"));
+        assert!(index.nearest(&set, &[0.5, 0.5, 0.0]).is_none());
+
+        assert!(ExampleIndex::build(&ExampleSet::default()).is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! Layered resolver stores.
+//!
+//! The shared base (`resolver.json`) is what the config owner curates. Each user or chat
+//! gets an overlay under `resolver.d/` that `learn` writes into, so one person's phrasing
+//! never changes routing for everybody. Resolution reads the two merged, with the overlay
+//! taking precedence; `/resolver promote` moves overlay aliases into the base.
+
+use super::types::{AliasEntry, ProviderEntry, ResolverStore, WorkspaceEntry};
+use crate::utils::sha256;
+
+use std::path::{Path, PathBuf};
+
+/// The overlay file for `namespace`, next to the base store. Characters other than
+/// ASCII alphanumerics, `-` and `_` are replaced so a namespace is always one file name;
+/// a replaced name gets a hash of the original so two namespaces never share a file.
+pub fn overlay_path(base_path: &Path, namespace: &str) -> PathBuf {
+    overlays_dir(base_path).join(format!("{}.json", sanitize_namespace(namespace)))
+}
+
+pub fn overlays_dir(base_path: &Path) -> PathBuf {
+    base_path.with_file_name("resolver.d")
+}
+
+pub fn sanitize_namespace(namespace: &str) -> String {
+    let name: String = namespace
+        .trim()
+        .chars()
+        .map(|ch| match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => ch,
+            _ => '_',
+        })
+        .collect();
+    let name = if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    };
+    if name == namespace {
+        return name;
+    }
+    format!("{name}-{}", &sha256(namespace)[..12])
+}
+
+/// What `disk` gained over `loaded`, the copy this process read: entries and aliases
+/// another chat learned into the same overlay since. Scores stay out so merging the
+/// result into the in-memory overlay doesn't count a use twice.
+pub fn changes_since(loaded: &ResolverStore, disk: &ResolverStore) -> ResolverStore {
+    let mut changes = ResolverStore::default();
+    for (prov_key, prov) in &disk.providers {
+        let old = loaded.providers.get(prov_key);
+        let mut entry = ProviderEntry::new(vec![]);
+        entry.alias = new_aliases(&prov.alias, old.map(|v| &v.alias));
+        for (ws_key, ws) in &prov.workspaces {
+            let old_ws = old.and_then(|v| v.workspaces.get(ws_key));
+            let alias = new_aliases(&ws.alias, old_ws.map(|v| &v.alias));
+            if old_ws.is_none() || !alias.aliases.is_empty() {
+                let mut ws = WorkspaceEntry::new(&ws.name, ws.target_profile.clone(), vec![]);
+                ws.alias = alias;
+                entry.workspaces.insert(ws_key.clone(), ws);
+            }
+        }
+        if old.is_none() || !entry.alias.aliases.is_empty() || !entry.workspaces.is_empty() {
+            changes.providers.insert(prov_key.clone(), entry);
+        }
+    }
+    for (action_key, action) in &disk.actions {
+        let alias = new_aliases(action, loaded.actions.get(action_key));
+        if !loaded.actions.contains_key(action_key) || !alias.aliases.is_empty() {
+            changes.actions.insert(action_key.clone(), alias);
+        }
+    }
+    for item in &disk.examples.items {
+        if !loaded.examples.items.contains(item) {
+            changes.examples.items.push(item.clone());
+        }
+    }
+    if !changes.examples.is_empty() {
+        changes.examples.embedding_model = disk.examples.embedding_model.clone();
+    }
+    changes
+}
+
+fn new_aliases(entry: &AliasEntry, old: Option<&AliasEntry>) -> AliasEntry {
+    let is_new = |v: &String| !old.is_some_and(|old| old.aliases.contains(v));
+    let aliases = entry
+        .aliases
+        .iter()
+        .filter(|v| is_new(v))
+        .cloned()
+        .collect();
+    let mut alias = AliasEntry::new(aliases);
+    alias.learned = entry
+        .learned
+        .iter()
+        .filter(|(v, _)| is_new(v))
+        .map(|(v, secs)| (v.clone(), *secs))
+        .collect();
+    alias
+}
+
+/// `base` with `overlay` applied. An alias the overlay gives one entry is taken away from
+/// every other entry of the same kind, usage scores add up, and an overlay workspace's
+/// `target_profile` wins.
+pub fn merge(base: &ResolverStore, overlay: &ResolverStore) -> ResolverStore {
+    let mut merged = base.clone();
+    for (prov_key, prov) in &overlay.providers {
+        release(
+            merged
+                .providers
+                .iter_mut()
+                .filter(|(k, _)| *k != prov_key)
+                .map(|(_, v)| &mut v.alias),
+            &prov.alias.aliases,
+        );
+        let entry = merged
+            .providers
+            .entry(prov_key.clone())
+            .or_insert_with(|| ProviderEntry::new(vec![]));
+        merge_alias(&mut entry.alias, &prov.alias);
+        for (ws_key, ws) in &prov.workspaces {
+            release(
+                entry
+                    .workspaces
+                    .iter_mut()
+                    .filter(|(k, _)| *k != ws_key)
+                    .map(|(_, v)| &mut v.alias),
+                &ws.alias.aliases,
+            );
+            let target = entry
+                .workspaces
+                .entry(ws_key.clone())
+                .or_insert_with(|| WorkspaceEntry::new(&ws.name, None, vec![]));
+            if ws.target_profile.is_some() {
+                target.target_profile = ws.target_profile.clone();
+            }
+            merge_alias(&mut target.alias, &ws.alias);
+        }
+    }
+    for (action_key, action) in &overlay.actions {
+        release(
+            merged
+                .actions
+                .iter_mut()
+                .filter(|(k, _)| *k != action_key)
+                .map(|(_, v)| v),
+            &action.aliases,
+        );
+        let entry = merged.actions.entry(action_key.clone()).or_default();
+        merge_alias(entry, action);
+    }
+
+    // Vectors from another embedding model can't share an index.
+    let examples = &mut merged.examples;
+    if overlay.examples.embedding_model.is_some()
+        && overlay.examples.embedding_model != examples.embedding_model
+    {
+        for item in &mut examples.items {
+            item.vector.clear();
+        }
+        examples.embedding_model = overlay.examples.embedding_model.clone();
+    }
+    for item in &overlay.examples.items {
+        examples.add(item.clone());
+    }
+    merged
+}
+
+/// Move the overlay's aliases that the base lacks (only `alias`, if given) into the
+/// base, where they are never pruned. Returns what moved, e.g. `action list_issues: 'bugs'`.
+pub fn promote(
+    overlay: &mut ResolverStore,
+    base: &mut ResolverStore,
+    alias: Option<&str>,
+) -> Vec<String> {
+    let alias = alias.map(|v| v.trim().to_lowercase());
+    let wanted = |v: &str| alias.as_deref().is_none_or(|a| a == v);
+    let mut promoted = vec![];
+
+    for (prov_key, prov) in &mut overlay.providers {
+        let base_prov = base.providers.get(prov_key);
+        let names = promotable(&prov.alias, base_prov.map(|v| &v.alias), &wanted);
+        let ws_moves: Vec<(String, Vec<String>)> = prov
+            .workspaces
+            .iter()
+            .map(|(ws_key, ws)| {
+                let base_ws = base_prov.and_then(|v| v.workspaces.get(ws_key));
+                let names = promotable(&ws.alias, base_ws.map(|v| &v.alias), &wanted);
+                (ws_key.clone(), names)
+            })
+            .filter(|(_, names)| !names.is_empty())
+            .collect();
+        if names.is_empty() && ws_moves.is_empty() {
+            continue;
+        }
+
+        release(
+            base.providers
+                .iter_mut()
+                .filter(|(k, _)| *k != prov_key)
+                .map(|(_, v)| &mut v.alias),
+            &names,
+        );
+        let base_prov = base
+            .providers
+            .entry(prov_key.clone())
+            .or_insert_with(|| ProviderEntry::new(vec![]));
+        move_aliases(&mut prov.alias, &mut base_prov.alias, &names);
+        for name in names {
+            promoted.push(format!("provider {prov_key}: '{name}'"));
+        }
+
+        for (ws_key, names) in ws_moves {
+            let Some(ws) = prov.workspaces.get_mut(&ws_key) else {
+                continue;
+            };
+            release(
+                base_prov
+                    .workspaces
+                    .iter_mut()
+                    .filter(|(k, _)| **k != ws_key)
+                    .map(|(_, v)| &mut v.alias),
+                &names,
+            );
+            let base_ws = base_prov
+                .workspaces
+                .entry(ws_key)
+                .or_insert_with(|| WorkspaceEntry::new(&ws.name, None, vec![]));
+            if base_ws.target_profile.is_none() {
+                base_ws.target_profile = ws.target_profile.clone();
+            }
+            move_aliases(&mut ws.alias, &mut base_ws.alias, &names);
+            for name in names {
+                promoted.push(format!("workspace {prov_key}/{}: '{name}'", ws.name));
+            }
+        }
+    }
+
+    for (action_key, action) in &mut overlay.actions {
+        let names = promotable(action, base.actions.get(action_key), &wanted);
+        if names.is_empty() {
+            continue;
+        }
+        release(
+            base.actions
+                .iter_mut()
+                .filter(|(k, _)| *k != action_key)
+                .map(|(_, v)| v),
+            &names,
+        );
+        let base_action = base.actions.entry(action_key.clone()).or_default();
+        move_aliases(action, base_action, &names);
+        for name in names {
+            promoted.push(format!("action {action_key}: '{name}'"));
+        }
+    }
+    promoted.sort();
+    promoted
+}
+
+fn promotable(
+    from: &AliasEntry,
+    to: Option<&AliasEntry>,
+    wanted: &dyn Fn(&str) -> bool,
+) -> Vec<String> {
+    from.aliases
+        .iter()
+        .filter(|v| wanted(v) && !to.is_some_and(|to| to.aliases.contains(v)))
+        .cloned()
+        .collect()
+}
+
+fn move_aliases(from: &mut AliasEntry, to: &mut AliasEntry, names: &[String]) {
+    for name in names {
+        to.add_alias(name, false);
+    }
+    from.aliases.retain(|v| !names.contains(v));
+    from.learned.retain(|v, _| !names.contains(v));
+}
+
+fn release<'a>(entries: impl Iterator<Item = &'a mut AliasEntry>, aliases: &[String]) {
+    for entry in entries {
+        entry.aliases.retain(|v| !aliases.contains(v));
+        entry.learned.retain(|v, _| !aliases.contains(v));
+    }
+}
+
+fn merge_alias(to: &mut AliasEntry, from: &AliasEntry) {
+    for alias in &from.aliases {
+        if !to.aliases.contains(alias) {
+            to.aliases.push(alias.clone());
+        }
+    }
+    for (alias, secs) in &from.learned {
+        let learned = to.learned.entry(alias.clone()).or_insert(*secs);
+        *learned = (*learned).max(*secs);
+    }
+    // Both scores decayed to the later use, so `decayed_score` stays their sum.
+    let last_used_secs = to.last_used_secs.max(from.last_used_secs);
+    if let Some(last) = last_used_secs {
+        to.score = to.decayed_score(last) + from.decayed_score(last);
+    }
+    to.last_used_secs = last_used_secs;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(actions: &[(&str, &[&str])]) -> ResolverStore {
+        let mut store = ResolverStore::default();
+        for (key, aliases) in actions {
+            store.actions.insert(
+                key.to_string(),
+                AliasEntry::new(aliases.iter().map(|v| v.to_string()).collect()),
+            );
+        }
+        store
+    }
+
+    #[test]
+    fn overlay_aliases_take_precedence() {
+        let base = store(&[
+            ("create_tickets", &["create tickets", "bugs"]),
+            ("list_issues", &["list issues"]),
+        ]);
+        let mut overlay = store(&[("list_issues", &["bugs"])]);
+        overlay.actions.get_mut("list_issues").unwrap().score = 2.0;
+        overlay
+            .actions
+            .get_mut("list_issues")
+            .unwrap()
+            .last_used_secs = Some(100);
+
+        let merged = merge(&base, &overlay);
+        assert_eq!(merged.actions["create_tickets"].aliases, ["create tickets"]);
+        assert_eq!(
+            merged.actions["list_issues"].aliases,
+            ["list issues", "bugs"]
+        );
+        assert_eq!(merged.actions["list_issues"].score, 2.0);
+        assert_eq!(base.actions["create_tickets"].aliases.len(), 2);
+    }
+
+    #[test]
+    fn promote_moves_missing_aliases_into_base() {
+        let mut base = store(&[("create_tickets", &["create tickets", "bugs"])]);
+        let mut overlay = store(&[
+            ("list_issues", &["bugs", "whats open"]),
+            ("create_tickets", &["create tickets"]),
+        ]);
+        overlay
+            .actions
+            .get_mut("list_issues")
+            .unwrap()
+            .learned
+            .insert("bugs".into(), 1);
+
+        let promoted = promote(&mut overlay.clone(), &mut base.clone(), Some("Whats Open"));
+        assert_eq!(promoted, ["action list_issues: 'whats open'"]);
+
+        let promoted = promote(&mut overlay, &mut base, None);
+        assert_eq!(
+            promoted,
+            [
+                "action list_issues: 'bugs'",
+                "action list_issues: 'whats open'"
+            ]
+        );
+        assert_eq!(base.actions["create_tickets"].aliases, ["create tickets"]);
+        assert_eq!(base.actions["list_issues"].aliases, ["bugs", "whats open"]);
+        assert!(base.actions["list_issues"].learned.is_empty());
+        assert!(overlay.actions["list_issues"].aliases.is_empty());
+        assert!(promote(&mut overlay, &mut base, None).is_empty());
+    }
+
+    #[test]
+    fn namespaces_are_single_file_names() {
+        let base = Path::new("/cfg/resolver.json");
+        assert_eq!(
+            overlay_path(base, "srv-telegram-42"),
+            Path::new("/cfg/resolver.d/srv-telegram-42.json")
+        );
+        let passwd = overlay_path(base, "../etc/passwd");
+        assert!(passwd
+            .to_str()
+            .unwrap()
+            .starts_with("/cfg/resolver.d/___etc_passwd-"));
+        assert_ne!(sanitize_namespace("a/b"), sanitize_namespace("a.b"));
+        assert_ne!(sanitize_namespace(" "), sanitize_namespace(""));
+        let name = sanitize_namespace("bob smith");
+        assert_eq!(sanitize_namespace(&name), name);
+    }
+
+    #[test]
+    fn changes_since_keeps_only_new_aliases() {
+        let loaded = store(&[("list_issues", &["bugs"])]);
+        let mut disk = store(&[
+            ("list_issues", &["bugs", "whats open"]),
+            ("create_tickets", &["file it"]),
+        ]);
+        disk.actions.get_mut("list_issues").unwrap().score = 3.0;
+
+        let changes = changes_since(&loaded, &disk);
+        assert_eq!(changes.actions["list_issues"].aliases, ["whats open"]);
+        assert_eq!(changes.actions["list_issues"].score, 0.0);
+        assert_eq!(changes.actions["create_tickets"].aliases, ["file it"]);
+        assert!(changes_since(&disk, &disk).actions.is_empty());
+    }
+}
@@ -1,15 +1,25 @@
+pub mod adapters;
+pub mod eval;
+mod examples;
+mod layers;
 mod store;
 pub mod types;
+pub mod vocabulary;
 
+pub use examples::IntentExample;
 pub use types::{
     AliasEntry, ProviderEntry, ResolutionOutcome, ResolvedIntent, ResolverStore, WorkspaceEntry,
 };
 
 use anyhow::{anyhow, bail, Result};
 use std::path::{Path, PathBuf};
-use types::word_boundary_match;
+use types::{fuzzy_find, now_secs, word_boundary_match};
 
+use crate::config::GlobalConfig;
 use crate::mcp::McpServerConfig;
+use adapters::{DiscoveredWorkspace, LinearAdapter, ProviderAdapter};
+use examples::ExampleIndex;
+use vocabulary::{ImportMode, Vocabulary};
 
 /// Confidence above which the deterministic pass reports a confident match.
 const CONFIDENT: f32 = 0.80;
@@ -21,23 +31,224 @@ const W_PROVIDER: f32 = 0.40;
 const W_WORKSPACE: f32 = 0.35;
 const W_ACTION: f32 = 0.25;
 
+/// Share of a component's weight earned by an approximate (typo-tolerant) match.
+const W_FUZZY: f32 = 0.60;
+/// Minimum similarity, `1 - distance / alias length`, for an approximate match per field.
+const FUZZY_PROVIDER: f32 = 0.80;
+const FUZZY_WORKSPACE: f32 = 0.85;
+const FUZZY_ACTION: f32 = 0.80;
+/// Aliases shorter than this (e.g. "ln", "sam") only ever match exactly.
+const FUZZY_MIN_LEN: usize = 4;
+
 /// The resolver: loads from `resolver.json`, does deterministic matching,
 /// and supports learning from confirmed resolutions.
+///
+/// With an overlay attached (see [`Resolver::set_overlay`]), `resolver.json` is the
+/// read-only base: learning and `/resolver learn` write the overlay instead.
 #[derive(Debug, Clone)]
 pub struct Resolver {
+    /// What resolution reads: the base store, or the base merged with the overlay.
     pub store: ResolverStore,
     path: PathBuf,
+    overlay: Option<Overlay>,
+    examples_index: Option<ExampleIndex>,
+}
+
+/// A per-user or per-chat layer over the base store.
+#[derive(Debug, Clone)]
+struct Overlay {
+    namespace: String,
+    path: PathBuf,
+    store: ResolverStore,
+    /// `store` as last read from or written to disk, to tell what other chats on the
+    /// same namespace saved since.
+    loaded: ResolverStore,
+    base: ResolverStore,
+    /// The base changed in memory (provider sync, promotion) and is saved too.
+    base_changed: bool,
 }
 
 impl Resolver {
     pub fn load(config_dir: &Path) -> Result<Self> {
         let path = store::resolver_path(config_dir);
         let store = store::load(&path)?;
-        Ok(Self { store, path })
+        let examples_index = ExampleIndex::build(&store.examples);
+        let mut resolver = Self {
+            store,
+            path,
+            overlay: None,
+            examples_index,
+        };
+        resolver.prune_stale_aliases(now_secs());
+        Ok(resolver)
+    }
+
+    /// Save the overlay when one is attached, otherwise the base store. The overlay on
+    /// disk is re-read first, so aliases another chat on the same namespace learned in
+    /// the meantime are kept.
+    pub fn save(&mut self) -> Result<()> {
+        let Some(overlay) = &mut self.overlay else {
+            return store::save(&self.path, &self.store);
+        };
+        if overlay.base_changed {
+            store::save(&self.path, &overlay.base)?;
+        }
+        let disk = store::load(&overlay.path)?;
+        let changes = layers::changes_since(&overlay.loaded, &disk);
+        overlay.store = layers::merge(&overlay.store, &changes);
+        store::save(&overlay.path, &overlay.store)?;
+        overlay.loaded = overlay.store.clone();
+        self.refresh();
+        Ok(())
+    }
+
+    /// Attach the overlay of `namespace` (a user or chat), loading it from
+    /// `resolver.d/<namespace>.json`, or detach it with `None`.
+    pub fn set_overlay(&mut self, namespace: Option<&str>) -> Result<()> {
+        let namespace = namespace.map(layers::sanitize_namespace);
+        if self.overlay_namespace() == namespace.as_deref() {
+            return Ok(());
+        }
+        let overlay = match &namespace {
+            Some(namespace) => {
+                let path = layers::overlay_path(&self.path, namespace);
+                let store = store::load(&path)?;
+                Some((namespace.clone(), path, store))
+            }
+            None => None,
+        };
+        let base = match self.overlay.take() {
+            Some(overlay) => overlay.base,
+            None => std::mem::take(&mut self.store),
+        };
+        match overlay {
+            Some((namespace, path, store)) => {
+                self.overlay = Some(Overlay {
+                    namespace,
+                    path,
+                    loaded: store.clone(),
+                    store,
+                    base,
+                    base_changed: false,
+                });
+                self.prune_stale_aliases(now_secs());
+            }
+            None => {
+                self.store = base;
+                self.refresh();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn overlay_namespace(&self) -> Option<&str> {
+        self.overlay.as_ref().map(|v| v.namespace.as_str())
+    }
+
+    fn base(&self) -> &ResolverStore {
+        match &self.overlay {
+            Some(overlay) => &overlay.base,
+            None => &self.store,
+        }
+    }
+
+    /// The shared base; call [`Resolver::refresh`] after writing it.
+    fn base_mut(&mut self) -> &mut ResolverStore {
+        match &mut self.overlay {
+            Some(overlay) => {
+                overlay.base_changed = true;
+                &mut overlay.base
+            }
+            None => &mut self.store,
+        }
+    }
+
+    fn layer(&self) -> &ResolverStore {
+        match &self.overlay {
+            Some(overlay) => &overlay.store,
+            None => &self.store,
+        }
+    }
+
+    /// The layer learning writes: the overlay, or the base when none is attached.
+    /// Call [`Resolver::refresh`] after writing it.
+    fn layer_mut(&mut self) -> &mut ResolverStore {
+        match &mut self.overlay {
+            Some(overlay) => &mut overlay.store,
+            None => &mut self.store,
+        }
+    }
+
+    /// Give the overlay an empty entry for a provider only the base has, so workspaces
+    /// can be added under it.
+    fn layer_provider(&mut self, provider: &str) {
+        let key = provider.to_lowercase();
+        if self.overlay.is_some() && self.store.providers.contains_key(&key) {
+            self.layer_mut()
+                .providers
+                .entry(key)
+                .or_insert_with(|| ProviderEntry::new(vec![]));
+        }
+    }
+
+    /// Rebuild the merged view and the example index; needed after every write.
+    fn refresh(&mut self) {
+        if let Some(overlay) = &self.overlay {
+            self.store = layers::merge(&overlay.base, &overlay.store);
+        }
+        self.examples_index = ExampleIndex::build(&self.store.examples);
     }
 
-    pub fn save(&self) -> Result<()> {
-        store::save(&self.path, &self.store)
+    /// Every overlay on disk with the aliases [`Resolver::promote`] would move from it.
+    pub fn overlays(&self) -> Result<Vec<(String, Vec<String>)>> {
+        let dir = layers::overlays_dir(&self.path);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut overlays = vec![];
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|v| v != "json") {
+                continue;
+            }
+            let Some(namespace) = path.file_stem().and_then(|v| v.to_str()) else {
+                continue;
+            };
+            let mut store = match &self.overlay {
+                Some(overlay) if overlay.path == path => overlay.store.clone(),
+                _ => store::load(&path)?,
+            };
+            let pending = layers::promote(&mut store, &mut self.base().clone(), None);
+            overlays.push((namespace.to_string(), pending));
+        }
+        overlays.sort();
+        Ok(overlays)
+    }
+
+    /// Move `namespace`'s overlay aliases the base lacks (only `alias`, if given) into the
+    /// base, saving the overlay. Returns what moved; call [`Resolver::save`] to keep it.
+    pub fn promote(&mut self, namespace: &str, alias: Option<&str>) -> Result<Vec<String>> {
+        let path = layers::overlay_path(&self.path, namespace);
+        let attached = self.overlay.as_ref().is_some_and(|v| v.path == path);
+        let mut overlay = match &self.overlay {
+            Some(overlay) if attached => overlay.store.clone(),
+            _ if path.exists() => store::load(&path)?,
+            _ => bail!("No resolver overlay for '{namespace}'"),
+        };
+        let promoted = layers::promote(&mut overlay, self.base_mut(), alias);
+        if !promoted.is_empty() {
+            store::save(&path, &overlay)?;
+        }
+        if attached {
+            if let Some(attached) = &mut self.overlay {
+                if !promoted.is_empty() {
+                    attached.loaded = overlay.clone();
+                }
+                attached.store = overlay;
+            }
+        }
+        self.refresh();
+        Ok(promoted)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -49,8 +260,7 @@ impl Resolver {
     }
 
     pub fn sync_builtin_profiles(&mut self, servers: &[McpServerConfig]) {
-        self.add_provider("linear", Some("ln"))
-            .expect("builtin linear provider should be valid");
+        self.register_adapter(&LinearAdapter, servers);
         for (action, aliases) in [
             (
                 "create_tickets",
@@ -102,18 +312,67 @@ impl Resolver {
             ),
         ] {
             for alias in aliases {
-                self.add_action(action, alias)
+                self.base_mut()
+                    .upsert_action(action, alias, false)
                     .expect("builtin resolver action should be valid");
             }
         }
+        self.refresh();
+    }
 
+    /// Register an adapter's provider in the base, plus the workspaces its matching
+    /// servers imply by configuration alone.
+    pub fn register_adapter(&mut self, adapter: &dyn ProviderAdapter, servers: &[McpServerConfig]) {
+        let provider = adapter.provider();
+        let base = self.base_mut();
+        if let Err(err) = base.upsert_provider(provider, None, false) {
+            warn!("Resolver: invalid provider '{provider}': {err}");
+            return;
+        }
+        for alias in adapter.provider_aliases() {
+            let _ = base.upsert_provider(provider, Some(&alias), false);
+        }
         for server in servers {
-            let Some(workspace_name) = infer_linear_workspace_name(&server.name) else {
+            if !adapter.matches_server(&server.name) {
+                continue;
+            }
+            let Some(workspace) = adapter.profile_workspace(&server.name) else {
                 continue;
             };
-            let alias = workspace_name.to_lowercase();
-            let _ = self.add_workspace("linear", &workspace_name, Some(&server.name), Some(&alias));
+            for alias in &workspace.aliases {
+                let _ = base.upsert_workspace(
+                    provider,
+                    &workspace.name,
+                    Some(&server.name),
+                    Some(alias),
+                    false,
+                );
+            }
         }
+        self.refresh();
+    }
+
+    /// Add workspaces an adapter discovered on `server` to the base, targeting that
+    /// server. Their aliases come from the provider, so they are not learned and never
+    /// pruned. Returns their names.
+    pub fn add_discovered(
+        &mut self,
+        provider: &str,
+        server: &str,
+        workspaces: &[DiscoveredWorkspace],
+    ) -> Result<Vec<String>> {
+        let mut names = vec![];
+        let base = self.base_mut();
+        let ret = workspaces.iter().try_for_each(|workspace| {
+            base.upsert_workspace(provider, &workspace.name, Some(server), None, false)?;
+            for alias in &workspace.aliases {
+                base.upsert_workspace(provider, &workspace.name, Some(server), Some(alias), false)?;
+            }
+            names.push(workspace.name.clone());
+            anyhow::Ok(())
+        });
+        self.refresh();
+        ret.map(|_| names)
     }
 
     // -------------------------------------------------------------------------
@@ -126,6 +385,21 @@ impl Resolver {
             return ResolutionOutcome::PassThrough;
         }
         let lower = text.to_lowercase();
+        // Ties between equally long matches go to the most used entry, recent use
+        // counting more (see `AliasEntry::decayed_score`).
+        let now = now_secs();
+        let provider_score = |key: &str| {
+            self.store
+                .providers
+                .get(key)
+                .map_or(0.0, |v| v.alias.decayed_score(now))
+        };
+        let action_score = |key: &str| {
+            self.store
+                .actions
+                .get(key)
+                .map_or(0.0, |v| v.decayed_score(now))
+        };
 
         // --- Provider (deterministic: prefer the provider whose longest matching alias wins) ---
         let provider_match = self
@@ -141,87 +415,114 @@ impl Resolver {
                     .max()?;
                 Some((key.clone(), best_len))
             })
-            .max_by_key(|(_, len)| *len)
-            .map(|(key, _)| key);
-
-        let provider_score = if provider_match.is_some() {
-            W_PROVIDER
-        } else {
-            0.0
-        };
+            .max_by(|a, b| rank_longest(a, b, provider_score))
+            .map(|(key, _)| (key, Hit::Exact))
+            .or_else(|| {
+                let candidates = self.store.providers.iter().flat_map(|(key, entry)| {
+                    std::iter::once(key)
+                        .chain(entry.alias.aliases.iter())
+                        .map(move |alias| (key.clone(), alias.clone(), alias.len()))
+                });
+                best_fuzzy(&lower, candidates, FUZZY_PROVIDER, provider_score)
+            });
 
         // --- Action (prefer the action whose longest matching alias is the longest) ---
         let action_match = self
             .store
             .actions
             .iter()
-            .filter(|(_, entry)| entry.matches(&lower))
-            .max_by_key(|(_, entry)| {
-                entry
+            .filter_map(|(key, entry)| {
+                let best_len = entry
                     .aliases
                     .iter()
                     .filter(|a| word_boundary_match(&lower, a))
                     .map(|a| a.len())
-                    .max()
-                    .unwrap_or(0)
+                    .max()?;
+                Some((key.clone(), best_len))
             })
-            .map(|(key, _)| key.clone());
+            .max_by(|a, b| rank_longest(a, b, action_score))
+            .map(|(key, _)| (key, Hit::Exact))
+            .or_else(|| {
+                let candidates = self.store.actions.iter().flat_map(|(key, entry)| {
+                    entry
+                        .aliases
+                        .iter()
+                        .map(move |alias| (key.clone(), alias.clone(), alias.len()))
+                });
+                best_fuzzy(&lower, candidates, FUZZY_ACTION, action_score)
+            });
+        let action_key = action_match.as_ref().map(|(key, _)| key.as_str());
+        let connecting = matches!(
+            action_key,
+            Some("connect_workspace" | "disconnect_workspace")
+        );
 
         // --- Workspace (only when a provider matched) ---
-        let workspace_match = provider_match.as_ref().and_then(|p_key| {
+        let workspace_match = provider_match.as_ref().and_then(|(p_key, _)| {
             let prov = self.store.providers.get(p_key)?;
-            prov.workspaces
+            // (workspace key, phrase, length of the name/alias within the phrase)
+            let phrases: Vec<(String, String, usize)> = prov
+                .workspaces
                 .iter()
-                .filter_map(|(ws_key, ws_entry)| {
-                    let name_lower = ws_entry.name.to_lowercase();
-                    let mut candidates = vec![ws_key.clone(), name_lower];
+                .flat_map(|(ws_key, ws_entry)| {
+                    let mut candidates = vec![ws_key.clone(), ws_entry.name.to_lowercase()];
                     candidates.extend(ws_entry.alias.aliases.iter().cloned());
-                    let mut phrases: Vec<String> = ["in ", "for ", "at "]
+                    let mut phrases: Vec<(String, String, usize)> = ["in ", "for ", "at "]
                         .iter()
                         .flat_map(|prep| {
-                            candidates
-                                .iter()
-                                .map(move |candidate| format!("{prep}{candidate}"))
+                            candidates.iter().map(move |candidate| {
+                                (
+                                    ws_key.clone(),
+                                    format!("{prep}{candidate}"),
+                                    candidate.len(),
+                                )
+                            })
                         })
                         .collect();
-                    if matches!(
-                        action_match.as_deref(),
-                        Some("connect_workspace" | "disconnect_workspace")
-                    ) {
-                        phrases.extend(
-                            candidates
-                                .iter()
-                                .map(|candidate| format!("workspace {candidate}")),
-                        );
-                        phrases.extend(candidates.iter().cloned());
+                    if connecting {
+                        for candidate in &candidates {
+                            phrases.push((
+                                ws_key.clone(),
+                                format!("workspace {candidate}"),
+                                candidate.len(),
+                            ));
+                            phrases.push((ws_key.clone(), candidate.clone(), candidate.len()));
+                        }
                     }
-                    let best_len = phrases
-                        .iter()
-                        .filter(|phrase| word_boundary_match(&lower, phrase))
-                        .map(|phrase| phrase.len())
-                        .max()?;
-                    Some((
-                        ws_entry.name.clone(),
-                        ws_entry.target_profile.clone(),
-                        best_len,
-                    ))
+                    phrases
                 })
-                .max_by_key(|(_, _, best_len)| *best_len)
-                .map(|(name, target_profile, _)| (name, target_profile))
+                .collect();
+            let workspace_score = |key: &str| {
+                prov.workspaces
+                    .get(key)
+                    .map_or(0.0, |v| v.alias.decayed_score(now))
+            };
+            let (ws_key, hit) = phrases
+                .iter()
+                .filter(|(_, phrase, _)| word_boundary_match(&lower, phrase))
+                .map(|(ws_key, phrase, _)| (ws_key.clone(), phrase.len()))
+                .max_by(|a, b| rank_longest(a, b, workspace_score))
+                .map(|(ws_key, _)| (ws_key, Hit::Exact))
+                .or_else(|| {
+                    best_fuzzy(
+                        &lower,
+                        phrases.into_iter(),
+                        FUZZY_WORKSPACE,
+                        workspace_score,
+                    )
+                })?;
+            let ws_entry = prov.workspaces.get(&ws_key)?;
+            Some((ws_entry.name.clone(), ws_entry.target_profile.clone(), hit))
         });
 
         let (workspace_match, target_profile_match) = match workspace_match {
-            Some((name, target_profile)) => (Some(name), target_profile),
+            Some((name, target_profile, hit)) => (Some((name, hit)), target_profile),
             None => {
-                if provider_match.as_deref() == Some("linear")
-                    && matches!(
-                        action_match.as_deref(),
-                        Some("connect_workspace" | "disconnect_workspace")
-                    )
-                {
+                let provider_key = provider_match.as_ref().map(|(key, _)| key.as_str());
+                if provider_key == Some("linear") && connecting {
                     match infer_linear_workspace_target(&lower) {
                         Some((workspace, target_profile)) => {
-                            (Some(workspace), Some(target_profile))
+                            (Some((workspace, Hit::Exact)), Some(target_profile))
                         }
                         None => (None, None),
                     }
@@ -231,30 +532,28 @@ impl Resolver {
             }
         };
 
-        let workspace_score = if workspace_match.is_some() {
-            W_WORKSPACE
-        } else {
-            0.0
-        };
-
-        let action_score = if action_match.is_some() {
-            W_ACTION
-        } else {
-            0.0
-        };
-
-        let confidence = provider_score + workspace_score + action_score;
-
+        let mut confidence = 0.0;
         let mut parts = vec![];
-        if let Some(ref p) = provider_match {
-            parts.push(format!("provider={p}"));
-        }
-        if let Some(ref w) = workspace_match {
-            parts.push(format!("workspace={w}"));
-        }
-        if let Some(ref a) = action_match {
-            parts.push(format!("action={a}"));
+        for (field, weight, matched) in [
+            ("provider", W_PROVIDER, &provider_match),
+            ("workspace", W_WORKSPACE, &workspace_match),
+            ("action", W_ACTION, &action_match),
+        ] {
+            match matched {
+                Some((value, Hit::Exact)) => {
+                    confidence += weight;
+                    parts.push(format!("{field}={value}"));
+                }
+                Some((value, Hit::Fuzzy { typed, alias })) => {
+                    confidence += weight * W_FUZZY;
+                    parts.push(format!("{field}={value} (fuzzy: '{typed}' ~ '{alias}')"));
+                }
+                None => {}
+            }
         }
+        let provider_match = provider_match.map(|(key, _)| key);
+        let workspace_match = workspace_match.map(|(name, _)| name);
+        let action_match = action_match.map(|(key, _)| key);
 
         if parts.is_empty() {
             return ResolutionOutcome::PassThrough;
@@ -276,6 +575,91 @@ impl Resolver {
         }
     }
 
+    /// Up to `limit` provider/workspace/action combinations `text` may mean, best first,
+    /// for the user to pick from when [`Resolver::resolve`] can't decide. Fields are
+    /// scored like there, but every matching entry is kept; a field nothing matched
+    /// offers all its entries, most used first. Only matched providers are offered.
+    pub fn candidates(&self, text: &str, limit: usize) -> Vec<ResolvedIntent> {
+        let lower = text.to_lowercase();
+        let now = now_secs();
+        // (key, share of the field's weight, decayed usage score)
+        type Options = Vec<(Option<String>, f32, f32)>;
+        let options = |matched: Options, all: Options| {
+            let mut options = if matched.is_empty() { all } else { matched };
+            if options.is_empty() {
+                options.push((None, 0.0, 0.0));
+            }
+            options
+        };
+
+        let actions: Options = self
+            .store
+            .actions
+            .iter()
+            .map(|(key, entry)| {
+                let hit = field_hit(&lower, entry.aliases.iter().cloned(), FUZZY_ACTION);
+                (Some(key.clone()), hit, entry.decayed_score(now))
+            })
+            .collect();
+        let actions = options(
+            actions.iter().filter(|v| v.1 > 0.0).cloned().collect(),
+            actions,
+        );
+
+        let mut combos = vec![];
+        for (prov_key, prov) in &self.store.providers {
+            let names = std::iter::once(prov_key.clone()).chain(prov.alias.aliases.clone());
+            let prov_hit = field_hit(&lower, names, FUZZY_PROVIDER);
+            if prov_hit == 0.0 {
+                continue;
+            }
+            let workspaces: Options = prov
+                .workspaces
+                .iter()
+                .map(|(ws_key, ws)| {
+                    let mut names = vec![ws_key.clone(), ws.name.to_lowercase()];
+                    names.extend(ws.alias.aliases.iter().cloned());
+                    let phrases = ["in ", "for ", "at "]
+                        .iter()
+                        .flat_map(|prep| names.iter().map(move |name| format!("{prep}{name}")));
+                    let hit = field_hit(&lower, phrases, FUZZY_WORKSPACE);
+                    (Some(ws_key.clone()), hit, ws.alias.decayed_score(now))
+                })
+                .collect();
+            let workspaces = options(
+                workspaces.iter().filter(|v| v.1 > 0.0).cloned().collect(),
+                workspaces,
+            );
+            for (ws_key, ws_hit, ws_score) in &workspaces {
+                let ws = ws_key.as_ref().and_then(|v| prov.workspaces.get(v));
+                for (action, action_hit, action_score) in &actions {
+                    let confidence =
+                        prov_hit * W_PROVIDER + ws_hit * W_WORKSPACE + action_hit * W_ACTION;
+                    let intent = ResolvedIntent {
+                        provider: prov_key.clone(),
+                        workspace: ws.map(|v| v.name.clone()),
+                        target_profile: ws.and_then(|v| v.target_profile.clone()),
+                        action: action.clone(),
+                        confidence,
+                        reason: "candidate".to_string(),
+                    };
+                    combos.push((intent, ws_score + action_score));
+                }
+            }
+        }
+        combos.sort_by(|(a, a_score), (b, b_score)| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| b_score.total_cmp(a_score))
+                .then_with(|| a.label().cmp(&b.label()))
+        });
+        combos
+            .into_iter()
+            .take(limit)
+            .map(|(intent, _)| intent)
+            .collect()
+    }
+
     // -------------------------------------------------------------------------
     // Learning
     // -------------------------------------------------------------------------
@@ -319,47 +703,228 @@ impl Resolver {
         Some(intent)
     }
 
-    /// Boost usage scores for every entry touched by a confirmed resolution.
-    pub fn learn(&mut self, intent: &ResolvedIntent) {
-        if let Some(prov) = self.store.providers.get_mut(&intent.provider) {
-            prov.alias.bump();
-            if let Some(ws_name) = &intent.workspace {
-                let ws_key = ws_name.to_lowercase();
-                if let Some(ws) = prov.workspaces.get_mut(&ws_key) {
-                    ws.alias.bump();
+    /// The prompt asking the LLM to pick provider/workspace/action for `text`.
+    pub fn ai_prompt(&self, text: &str) -> String {
+        let mut options = String::new();
+        for (prov_key, prov_entry) in &self.store.providers {
+            let aliases = prov_entry.alias.aliases.join(", ");
+            options.push_str(&format!("- provider: {prov_key} (aliases: {aliases})\n"));
+            for (ws_key, ws_entry) in &prov_entry.workspaces {
+                let ws_aliases = ws_entry.alias.aliases.join(", ");
+                options.push_str(&format!(
+                    "  - workspace: {ws_key} / {} (aliases: {ws_aliases})\n",
+                    ws_entry.name
+                ));
+            }
+        }
+        for (action_key, action_entry) in &self.store.actions {
+            let aliases = action_entry.aliases.join(", ");
+            options.push_str(&format!("- action: {action_key} (aliases: {aliases})\n"));
+        }
+
+        format!(
+            r#"You are an intent resolver. Extract provider/workspace/action from the user request.
+
+Available entries:
+{options}
+User request: "{text}"
+
+Respond with ONLY valid JSON on a single line. No markdown, no explanation.
+Fields: provider (string|null), workspace (string|null), action (string|null), confidence (0.0-1.0), reason (string).
+Example: {{"provider":"linear","workspace":"SAM","action":"create_tickets","confidence":0.95,"reason":"matched all fields"}}"#
+        )
+    }
+
+    /// Parse and validate the LLM answer to [`Resolver::ai_prompt`].
+    /// Returns `None` when it is malformed or the AI confidence is below threshold.
+    pub fn parse_ai_output(&self, output: &str) -> Option<ResolvedIntent> {
+        const AI_THRESHOLD: f32 = 0.70;
+
+        #[derive(serde::Deserialize)]
+        struct AiOut {
+            provider: Option<String>,
+            workspace: Option<String>,
+            action: Option<String>,
+            confidence: f32,
+            reason: String,
+        }
+        let out: AiOut = serde_json::from_str(extract_json_object(output)?).ok()?;
+        if out.confidence < AI_THRESHOLD {
+            return None;
+        }
+        let intent = ResolvedIntent {
+            provider: out.provider?,
+            workspace: out.workspace,
+            target_profile: None,
+            action: out.action,
+            confidence: out.confidence,
+            reason: format!("AI: {}", out.reason),
+        };
+        self.validate_ai_intent(intent)
+            .filter(|v| v.confidence >= AI_THRESHOLD)
+    }
+
+    /// Nearest-neighbour pass over the labelled examples: embeds `text` and returns the
+    /// validated labels of the closest example. `None` when there is no example index or
+    /// nothing is close enough.
+    pub async fn resolve_by_example(
+        &self,
+        config: &GlobalConfig,
+        text: &str,
+    ) -> Result<Option<ResolvedIntent>> {
+        let Some(index) = &self.examples_index else {
+            return Ok(None);
+        };
+        let Some(intent) = index.classify(config, &self.store.examples, text).await? else {
+            return Ok(None);
+        };
+        Ok(self
+            .validate_ai_intent(intent)
+            .filter(|v| v.confidence >= examples::MIN_SIMILARITY))
+    }
+
+    pub fn has_examples_index(&self) -> bool {
+        self.examples_index.is_some()
+    }
+
+    /// Embed the written layer's examples added since the last call and rebuild the index.
+    pub async fn embed_examples(&mut self, config: &GlobalConfig) -> Result<()> {
+        let ret = self.layer_mut().examples.embed(config).await;
+        self.refresh();
+        ret
+    }
+
+    /// Boost usage scores for every entry touched by a confirmed resolution, and keep
+    /// `utterance` as a labelled example for the nearest-neighbour stage. Both go to the
+    /// overlay when one is attached.
+    pub fn learn(&mut self, intent: &ResolvedIntent, utterance: &str) {
+        let prov = self.store.providers.get(&intent.provider);
+        let workspace = prov.and_then(|prov| {
+            let ws_key = intent.workspace.as_ref()?.to_lowercase();
+            let name = prov.workspaces.get(&ws_key)?.name.clone();
+            Some((ws_key, name))
+        });
+        let known_provider = prov.is_some();
+        let action = intent
+            .action
+            .clone()
+            .filter(|v| self.store.actions.contains_key(v));
+
+        let layer = self.layer_mut();
+        layer.examples.add(IntentExample::new(
+            utterance,
+            &intent.provider,
+            intent.workspace.as_deref(),
+            intent.action.as_deref(),
+        ));
+        let lower = utterance.to_lowercase();
+        if known_provider {
+            let prov = layer
+                .providers
+                .entry(intent.provider.clone())
+                .or_insert_with(|| ProviderEntry::new(vec![]));
+            prov.alias.bump(&lower);
+            if let Some((ws_key, name)) = workspace {
+                let ws = prov
+                    .workspaces
+                    .entry(ws_key)
+                    .or_insert_with(|| WorkspaceEntry::new(&name, None, vec![]));
+                ws.alias.bump(&lower);
+            }
+        }
+        if let Some(action_key) = action {
+            layer.actions.entry(action_key).or_default().bump(&lower);
+        }
+        self.prune_stale_aliases(now_secs());
+    }
+
+    /// Drop learned aliases that have not matched a confirmed resolution for
+    /// [`types::STALE_ALIAS_SECS`] from the written layer. Runs on load, when an overlay
+    /// is attached and after every `learn`.
+    pub fn prune_stale_aliases(&mut self, now: u64) -> Vec<String> {
+        let mut pruned = vec![];
+        let layer = self.layer_mut();
+        for (prov_key, prov) in &mut layer.providers {
+            for alias in prov.alias.prune_learned(now) {
+                pruned.push(format!("provider {prov_key}: '{alias}'"));
+            }
+            for ws in prov.workspaces.values_mut() {
+                for alias in ws.alias.prune_learned(now) {
+                    pruned.push(format!("workspace {prov_key}/{}: '{alias}'", ws.name));
                 }
             }
         }
-        if let Some(action_key) = &intent.action {
-            if let Some(entry) = self.store.actions.get_mut(action_key) {
-                entry.bump();
+        for (action_key, entry) in &mut layer.actions {
+            for alias in entry.prune_learned(now) {
+                pruned.push(format!("action {action_key}: '{alias}'"));
             }
         }
+        for v in &pruned {
+            info!("Resolver: pruned stale alias of {v}");
+        }
+        self.refresh();
+        pruned
+    }
+
+    /// Decayed usage scores for every entry, highest first, with the age of each learned
+    /// alias. Backs `/resolver stats`.
+    pub fn stats(&self) -> String {
+        self.stats_at(now_secs())
+    }
+
+    fn stats_at(&self, now: u64) -> String {
+        let mut rows: Vec<(&str, String, &AliasEntry)> = vec![];
+        for (prov_key, prov) in &self.store.providers {
+            rows.push(("provider", prov_key.clone(), &prov.alias));
+            for ws in prov.workspaces.values() {
+                rows.push(("workspace", format!("{prov_key}/{}", ws.name), &ws.alias));
+            }
+        }
+        for (action_key, entry) in &self.store.actions {
+            rows.push(("action", action_key.clone(), entry));
+        }
+        rows.sort_by(|a, b| {
+            b.2.decayed_score(now)
+                .total_cmp(&a.2.decayed_score(now))
+                .then_with(|| a.1.cmp(&b.1))
+        });
+
+        let mut output = format!(
+            "Usage scores (half-life {}d; learned aliases unused for {}d are pruned)\n",
+            types::SCORE_HALF_LIFE_SECS / DAY_SECS,
+            types::STALE_ALIAS_SECS / DAY_SECS
+        );
+        for (kind, name, entry) in rows {
+            output.push_str(&format!(
+                "  {kind:<9}  {name:<24} {:>6.2}  used {}",
+                entry.decayed_score(now),
+                ago(now, entry.last_used_secs)
+            ));
+            if !entry.learned.is_empty() {
+                let learned: Vec<_> = entry
+                    .learned
+                    .iter()
+                    .map(|(alias, secs)| format!("{alias} ({})", ago(now, Some(*secs))))
+                    .collect();
+                output.push_str(&format!("  learned: {}", learned.join(", ")));
+            }
+            output.push('\n');
+        }
+        output
     }
 
     // -------------------------------------------------------------------------
     // Store management
     // -------------------------------------------------------------------------
 
+    /// Add or update a provider; `alias` counts as learned and may be pruned when stale.
     pub fn add_provider(&mut self, name: &str, alias: Option<&str>) -> Result<()> {
-        let key = name.to_lowercase();
-        if key.is_empty() {
-            bail!("Provider name cannot be empty");
-        }
-        let entry = self
-            .store
-            .providers
-            .entry(key.clone())
-            .or_insert_with(|| ProviderEntry::new(vec![key.clone()]));
-        if let Some(a) = alias {
-            let a_lower = a.to_lowercase();
-            if !a_lower.is_empty() && !entry.alias.aliases.contains(&a_lower) {
-                entry.alias.aliases.push(a_lower);
-            }
-        }
-        Ok(())
+        let ret = self.layer_mut().upsert_provider(name, alias, true);
+        self.refresh();
+        ret
     }
 
+    /// Add or update a workspace; `alias` counts as learned and may be pruned when stale.
     pub fn add_workspace(
         &mut self,
         provider: &str,
@@ -367,71 +932,122 @@ impl Resolver {
         target_profile: Option<&str>,
         alias: Option<&str>,
     ) -> Result<()> {
-        let prov_key = provider.to_lowercase();
-        let prov = self.store.providers.get_mut(&prov_key).ok_or_else(|| {
-            anyhow!(
-                "Provider '{}' not found. Add it first with `/resolver learn provider {}`",
-                provider,
-                provider
-            )
-        })?;
-        let ws_key = name.to_lowercase();
-        if ws_key.is_empty() {
-            bail!("Workspace name cannot be empty");
-        }
-        let ws_entry = prov.workspaces.entry(ws_key.clone()).or_insert_with(|| {
-            WorkspaceEntry::new(
-                name,
-                target_profile.map(str::to_string),
-                vec![ws_key.clone()],
-            )
-        });
-        if let Some(profile) = target_profile.map(str::trim).filter(|s| !s.is_empty()) {
-            ws_entry.target_profile = Some(profile.to_string());
-        }
-        if let Some(a) = alias {
-            let a_lower = a.to_lowercase();
-            if !a_lower.is_empty() && !ws_entry.alias.aliases.contains(&a_lower) {
-                ws_entry.alias.aliases.push(a_lower);
-            }
-        }
-        Ok(())
+        self.layer_provider(provider);
+        let ret = self
+            .layer_mut()
+            .upsert_workspace(provider, name, target_profile, alias, true);
+        self.refresh();
+        ret
     }
 
+    /// Add an action alias; it counts as learned and may be pruned when stale.
     pub fn add_action(&mut self, name: &str, alias: &str) -> Result<()> {
-        if name.is_empty() {
-            bail!("Action name cannot be empty");
-        }
-        if alias.is_empty() {
-            bail!("Action alias cannot be empty");
-        }
-        let entry = self
-            .store
-            .actions
-            .entry(name.to_string())
-            .or_insert_with(|| AliasEntry::new(vec![name.to_lowercase()]));
-        let a_lower = alias.to_lowercase();
-        if !entry.aliases.contains(&a_lower) {
-            entry.aliases.push(a_lower);
-        }
-        Ok(())
+        let ret = self.layer_mut().upsert_action(name, alias, true);
+        self.refresh();
+        ret
     }
 
+    /// The `remove_*` methods only remove from the written layer: with an overlay
+    /// attached, base entries stay.
     pub fn remove_provider(&mut self, name: &str) -> bool {
-        self.store.providers.remove(&name.to_lowercase()).is_some()
+        let removed = self
+            .layer_mut()
+            .providers
+            .remove(&name.to_lowercase())
+            .is_some();
+        self.refresh();
+        removed
     }
 
     pub fn remove_workspace(&mut self, provider: &str, name: &str) -> Result<bool> {
         let prov = self
-            .store
+            .layer_mut()
             .providers
             .get_mut(&provider.to_lowercase())
             .ok_or_else(|| anyhow!("Provider '{}' not found", provider))?;
-        Ok(prov.workspaces.remove(&name.to_lowercase()).is_some())
+        let removed = prov.workspaces.remove(&name.to_lowercase()).is_some();
+        self.refresh();
+        Ok(removed)
     }
 
     pub fn remove_action(&mut self, name: &str) -> bool {
-        self.store.actions.remove(name).is_some()
+        let removed = self.layer_mut().actions.remove(name).is_some();
+        self.refresh();
+        removed
+    }
+
+    /// Add a labelled example utterance. Call [`Resolver::embed_examples`] to index it.
+    pub fn add_example(
+        &mut self,
+        text: &str,
+        provider: &str,
+        workspace: Option<&str>,
+        action: Option<&str>,
+    ) -> Result<()> {
+        if text.trim().is_empty() {
+            bail!("Example text cannot be empty");
+        }
+        let prov = self
+            .store
+            .providers
+            .get(&provider.to_lowercase())
+            .ok_or_else(|| anyhow!("Provider '{}' not found", provider))?;
+        let workspace = match workspace {
+            Some(ws) => Some(
+                prov.workspaces
+                    .get(&ws.to_lowercase())
+                    .map(|v| v.name.clone())
+                    .ok_or_else(|| anyhow!("Workspace '{provider}/{ws}' not found"))?,
+            ),
+            None => None,
+        };
+        if let Some(action) = action {
+            if !self.store.actions.contains_key(action) {
+                bail!("Action '{}' not found", action);
+            }
+        }
+        self.layer_mut().examples.add(IntentExample::new(
+            text,
+            provider,
+            workspace.as_deref(),
+            action,
+        ));
+        self.refresh();
+        Ok(())
+    }
+
+    /// The written layer's vocabulary, for `fio resolver export`.
+    pub fn export(&self) -> Vocabulary {
+        Vocabulary::from_store(self.layer())
+    }
+
+    /// Import a vocabulary into the written layer after checking it against the
+    /// configured MCP `servers`. Returns the change as `- fact` / `+ fact` lines; call
+    /// [`Resolver::save`] to keep it.
+    pub fn import(
+        &mut self,
+        vocabulary: &Vocabulary,
+        mode: ImportMode,
+        servers: &[String],
+    ) -> Result<Vec<String>> {
+        let mut store = self.layer().clone();
+        vocabulary.apply(&mut store, mode)?;
+        let problems = vocabulary.problems(&store, servers);
+        if !problems.is_empty() {
+            bail!("Invalid resolver vocabulary:\n  {}", problems.join("\n  "));
+        }
+        let diff = Vocabulary::diff(&self.export(), &Vocabulary::from_store(&store));
+        *self.layer_mut() = store;
+        self.refresh();
+        Ok(diff)
+    }
+
+    pub fn remove_example(&mut self, text: &str) -> bool {
+        let removed = self.layer_mut().examples.remove(text);
+        if removed {
+            self.refresh();
+        }
+        removed
     }
 }
 
@@ -439,6 +1055,84 @@ impl Resolver {
 // Helpers
 // -------------------------------------------------------------------------
 
+/// How a resolved field was matched.
+enum Hit {
+    Exact,
+    /// Approximately: the words typed and the alias they were taken for.
+    Fuzzy {
+        typed: String,
+        alias: String,
+    },
+}
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+fn ago(now: u64, secs: Option<u64>) -> String {
+    let Some(secs) = secs else {
+        return "never".to_string();
+    };
+    match now.saturating_sub(secs) {
+        v if v >= DAY_SECS => format!("{}d ago", v / DAY_SECS),
+        v if v >= 3600 => format!("{}h ago", v / 3600),
+        _ => "just now".to_string(),
+    }
+}
+
+/// Order `(key, match length)` candidates for `max_by`: the longer match wins, then the
+/// higher decayed usage score, then the alphabetically first key.
+fn rank_longest(
+    a: &(String, usize),
+    b: &(String, usize),
+    score: impl Fn(&str) -> f32,
+) -> std::cmp::Ordering {
+    a.1.cmp(&b.1)
+        .then_with(|| score(&a.0).total_cmp(&score(&b.0)))
+        .then_with(|| b.0.cmp(&a.0))
+}
+
+/// Pick the closest approximate match among `(key, phrase, alias length)` candidates.
+/// The allowed edit distance scales with the alias length, so a short alias inside a
+/// longer phrase (e.g. "in sam") still needs to be typed exactly.
+fn best_fuzzy(
+    text: &str,
+    candidates: impl Iterator<Item = (String, String, usize)>,
+    min_similarity: f32,
+    score: impl Fn(&str) -> f32,
+) -> Option<(String, Hit)> {
+    candidates
+        .filter(|(_, _, len)| *len >= FUZZY_MIN_LEN)
+        .filter_map(|(key, phrase, len)| {
+            let max_distance = (len as f32 * (1.0 - min_similarity)).floor() as usize;
+            let (distance, typed) = fuzzy_find(text, &phrase)?;
+            (distance <= max_distance).then_some((distance, key, phrase, typed))
+        })
+        .min_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then_with(|| b.2.len().cmp(&a.2.len()))
+                .then_with(|| score(&b.1).total_cmp(&score(&a.1)))
+                .then_with(|| a.1.cmp(&b.1))
+        })
+        .map(|(_, key, alias, typed)| (key, Hit::Fuzzy { typed, alias }))
+}
+
+/// Share of a field's weight that the best of `names` earns in `text`: 1 for an exact
+/// match, [`W_FUZZY`] for an approximate one, 0 for none.
+fn field_hit(text: &str, names: impl Iterator<Item = String>, min_similarity: f32) -> f32 {
+    let mut hit = 0.0;
+    for name in names {
+        if word_boundary_match(text, &name) {
+            return 1.0;
+        }
+        if hit == 0.0 && name.len() >= FUZZY_MIN_LEN {
+            let max_distance = (name.len() as f32 * (1.0 - min_similarity)).floor() as usize;
+            if fuzzy_find(text, &name).is_some_and(|(distance, _)| distance <= max_distance) {
+                hit = W_FUZZY;
+            }
+        }
+    }
+    hit
+}
+
 /// Extract the first `{...}` block from an LLM response.
 pub fn extract_json_object(text: &str) -> Option<&str> {
     let start = text.find('{')?;
@@ -450,15 +1144,6 @@ pub fn extract_json_object(text: &str) -> Option<&str> {
     }
 }
 
-fn infer_linear_workspace_name(server_name: &str) -> Option<String> {
-    let suffix = server_name.strip_prefix("linear-")?;
-    let suffix = suffix.trim();
-    if suffix.is_empty() {
-        return None;
-    }
-    Some(suffix.to_ascii_uppercase())
-}
-
 pub(crate) fn infer_linear_workspace_target(text: &str) -> Option<(String, String)> {
     if let Some(slug) = extract_linear_workspace_slug_from_url(text) {
         return Some((slug.to_ascii_uppercase(), format!("linear-{slug}")));
@@ -695,6 +1380,104 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fuzzy_provider_resolves_with_lower_confidence() {
+        let r = setup();
+        let ResolutionOutcome::Resolved(exact) = r.resolve("linear: create tickets in SAM") else {
+            panic!("Expected Resolved");
+        };
+        let ResolutionOutcome::Resolved(intent) = r.resolve("lineer: create tickets in SAM") else {
+            panic!("Expected Resolved");
+        };
+        assert_eq!(intent.provider, "linear");
+        assert_eq!(intent.workspace.as_deref(), Some("SAM"));
+        assert!(intent.confidence < exact.confidence);
+        assert!(
+            intent
+                .reason
+                .contains("provider=linear (fuzzy: 'lineer' ~ 'linear')"),
+            "reason={}",
+            intent.reason
+        );
+    }
+
+    #[test]
+    fn fuzzy_action_matches_typo() {
+        let r = setup();
+        match r.resolve("linear creat tickets in SAM") {
+            ResolutionOutcome::Resolved(intent) => {
+                assert_eq!(intent.action.as_deref(), Some("create_tickets"));
+                assert!(intent
+                    .reason
+                    .contains("action=create_tickets (fuzzy: 'creat tickets' ~ 'create tickets')"));
+            }
+            other => panic!(
+                "Expected Resolved, got {:?}",
+                std::mem::discriminant(&other)
+            ),
+        }
+        // Two approximate fields are not enough on their own.
+        assert!(matches!(
+            r.resolve("lineer creat tickets in SAM"),
+            ResolutionOutcome::NeedsAi
+        ));
+    }
+
+    #[test]
+    fn candidates_rank_matching_combinations() {
+        let mut r = setup();
+        r.add_workspace("linear", "OPS", Some("linear-ops"), Some("ops"))
+            .unwrap();
+        let labels = |text: &str| {
+            r.candidates(text, 3)
+                .iter()
+                .map(|v| v.label())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            labels("lineer creat tickets in SAM"),
+            ["linear / SAM / create_tickets"]
+        );
+        // No workspace named: each is offered.
+        assert_eq!(
+            labels("linear create tickets"),
+            [
+                "linear / OPS / create_tickets",
+                "linear / SAM / create_tickets"
+            ]
+        );
+        assert!(r.candidates("create tickets", 3).is_empty());
+    }
+
+    #[test]
+    fn fuzzy_skips_short_aliases() {
+        let r = setup();
+        // "lm" is one edit from "ln", and "sma" a transposition of "sam"; both too short.
+        assert!(matches!(
+            r.resolve("lm create tickets in sma"),
+            ResolutionOutcome::PassThrough
+        ));
+        assert!(matches!(
+            r.resolve("linear create tickets in sma"),
+            ResolutionOutcome::NeedsAi
+        ));
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions() {
+        use types::{edit_distance, fuzzy_find};
+
+        assert_eq!(edit_distance("linear", "linear"), 0);
+        assert_eq!(edit_distance("lineer", "linear"), 1);
+        assert_eq!(edit_distance("craete", "create"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(
+            fuzzy_find("please creat ticket, thanks", "create tickets"),
+            Some((2, "creat ticket".to_string()))
+        );
+        assert_eq!(fuzzy_find("one", "two words"), None);
+    }
+
     #[test]
     fn learn_bumps_scores() {
         let mut r = setup();
@@ -702,10 +1485,172 @@ mod tests {
         let ResolutionOutcome::Resolved(intent) = r.resolve(text) else {
             panic!("Expected Resolved");
         };
-        r.learn(&intent);
-        assert_eq!(r.store.providers["linear"].alias.score, 1);
-        assert_eq!(r.store.providers["linear"].workspaces["sam"].alias.score, 1);
-        assert_eq!(r.store.actions["create_tickets"].score, 1);
+        r.learn(&intent, text);
+        assert_eq!(r.store.providers["linear"].alias.score, 1.0);
+        assert_eq!(
+            r.store.providers["linear"].workspaces["sam"].alias.score,
+            1.0
+        );
+        assert_eq!(r.store.actions["create_tickets"].score, 1.0);
+    }
+
+    #[test]
+    fn decayed_score_breaks_ties() {
+        let mut r = setup();
+        r.add_workspace("linear", "OPS", None, Some("platform"))
+            .unwrap();
+        r.add_workspace("linear", "SAM", None, Some("platform"))
+            .unwrap();
+        let now = now_secs();
+        let half_life = types::SCORE_HALF_LIFE_SECS;
+        let workspace = |r: &Resolver| match r.resolve("linear create tickets in platform") {
+            ResolutionOutcome::Resolved(intent) => intent.workspace.unwrap(),
+            _ => panic!("Expected Resolved"),
+        };
+        assert_eq!(workspace(&r), "OPS");
+
+        // Used more, but long ago: 8 * 2^-4 = 0.5 against 1 recent use.
+        let prov = r.store.providers.get_mut("linear").unwrap();
+        let sam = &mut prov.workspaces.get_mut("sam").unwrap().alias;
+        sam.score = 8.0;
+        sam.last_used_secs = Some(now - 4 * half_life);
+        assert!((sam.decayed_score(now) - 0.5).abs() < 1e-3);
+        let ops = &mut prov.workspaces.get_mut("ops").unwrap().alias;
+        ops.score = 1.0;
+        ops.last_used_secs = Some(now);
+        assert_eq!(workspace(&r), "OPS");
+
+        let prov = r.store.providers.get_mut("linear").unwrap();
+        prov.workspaces.get_mut("sam").unwrap().alias.last_used_secs = Some(now - half_life);
+        assert_eq!(workspace(&r), "SAM");
+    }
+
+    #[test]
+    fn prunes_only_stale_learned_aliases() {
+        let mut r = make_resolver();
+        r.sync_builtin_profiles(&[]);
+        r.add_workspace("linear", "SAM", None, Some("sam")).unwrap();
+        r.add_workspace("linear", "SAM", None, Some("samwise"))
+            .unwrap();
+        r.add_action("create_tickets", "open a bug").unwrap();
+        // Re-adding a built-in alias never marks it as learned.
+        r.add_action("list_issues", "list issues").unwrap();
+        assert!(r.store.actions["list_issues"].learned.is_empty());
+
+        let now = now_secs();
+        let stale = now - types::STALE_ALIAS_SECS;
+        let ws = &mut r.store.providers.get_mut("linear").unwrap().workspaces;
+        for secs in ws.get_mut("sam").unwrap().alias.learned.values_mut() {
+            *secs = stale;
+        }
+        for secs in r
+            .store
+            .actions
+            .get_mut("create_tickets")
+            .unwrap()
+            .learned
+            .values_mut()
+        {
+            *secs = stale;
+        }
+
+        // A confirmed resolution refreshes the learned aliases it used.
+        let text = "linear: create tickets for samwise";
+        let ResolutionOutcome::Resolved(intent) = r.resolve(text) else {
+            panic!("Expected Resolved");
+        };
+        r.learn(&intent, text);
+        let sam = &r.store.providers["linear"].workspaces["sam"].alias;
+        assert_eq!(sam.aliases, ["sam", "samwise"]);
+        assert_eq!(sam.learned.keys().collect::<Vec<_>>(), ["samwise"]);
+        let create = &r.store.actions["create_tickets"];
+        assert!(!create.aliases.contains(&"open a bug".to_string()));
+        assert!(create.aliases.contains(&"create tickets".to_string()));
+        assert!(r.prune_stale_aliases(now).is_empty());
+
+        let stats = r.stats_at(now);
+        assert!(stats.starts_with("Usage scores (half-life 14d;"), "{stats}");
+        assert!(stats.contains("learned: samwise (just now)"), "{stats}");
+        assert!(stats.contains("used never"), "{stats}");
+    }
+
+    #[test]
+    fn overlay_learning_stays_out_of_base() {
+        let mut r = setup();
+        r.save().unwrap();
+        let base_json = std::fs::read_to_string(r.path()).unwrap();
+        let workspace = |r: &Resolver| match r.resolve("linear: create tickets in core") {
+            ResolutionOutcome::Resolved(intent) => intent.workspace,
+            _ => None,
+        };
+
+        r.set_overlay(Some("alice")).unwrap();
+        assert_eq!(r.overlay_namespace(), Some("alice"));
+        r.add_workspace("linear", "SAM", None, Some("core"))
+            .unwrap();
+        let text = "linear: create tickets in core";
+        let ResolutionOutcome::Resolved(intent) = r.resolve(text) else {
+            panic!("Expected Resolved");
+        };
+        r.learn(&intent, text);
+        r.save().unwrap();
+        assert_eq!(std::fs::read_to_string(r.path()).unwrap(), base_json);
+        assert_eq!(r.store.actions["create_tickets"].score, 1.0);
+        assert_eq!(
+            r.store.providers["linear"].workspaces["sam"]
+                .target_profile
+                .as_deref(),
+            Some("linear-sam")
+        );
+
+        // Another user doesn't see alice's alias; alice's overlay is reloaded from disk.
+        r.set_overlay(Some("bob")).unwrap();
+        assert_eq!(workspace(&r), None);
+        r.set_overlay(Some("alice")).unwrap();
+        assert_eq!(workspace(&r).as_deref(), Some("SAM"));
+
+        // The base store's owner promotes it for everybody.
+        let mut base = Resolver::load(r.path().parent().unwrap()).unwrap();
+        let overlays = base.overlays().unwrap();
+        assert_eq!(overlays.len(), 1);
+        assert_eq!(overlays[0].1, ["workspace linear/SAM: 'core'"]);
+        assert!(base.promote("bob", None).is_err());
+        assert_eq!(
+            base.promote("alice", None).unwrap(),
+            ["workspace linear/SAM: 'core'"]
+        );
+        base.save().unwrap();
+        assert_eq!(workspace(&base).as_deref(), Some("SAM"));
+        assert!(base.overlays().unwrap()[0].1.is_empty());
+    }
+
+    #[test]
+    fn overlay_save_keeps_other_chats_aliases() {
+        let mut base = setup();
+        base.save().unwrap();
+        let mut first = base.clone();
+        let mut second = base;
+        first.set_overlay(Some("team chat")).unwrap();
+        second.set_overlay(Some("team chat")).unwrap();
+
+        first.add_action("list_issues", "whats open").unwrap();
+        first.save().unwrap();
+        second.add_action("create_tickets", "file it").unwrap();
+        second.save().unwrap();
+        assert!(matches!(
+            second.resolve("linear: whats open in sam"),
+            ResolutionOutcome::Resolved(_)
+        ));
+
+        let mut reloaded = Resolver::load(second.path().parent().unwrap()).unwrap();
+        reloaded.set_overlay(Some("team chat")).unwrap();
+        let actions = &reloaded.layer().actions;
+        assert!(actions["list_issues"]
+            .aliases
+            .contains(&"whats open".into()));
+        assert!(actions["create_tickets"]
+            .aliases
+            .contains(&"file it".into()));
     }
 
     #[test]
@@ -886,6 +1831,27 @@ mod tests {
         assert!(result.confidence < 0.40);
     }
 
+    #[test]
+    fn parse_ai_output_validates_and_thresholds() {
+        let r = setup();
+        let intent = r
+            .parse_ai_output(
+                r#"Sure: {"provider":"Linear","workspace":"sam","action":"list_issues","confidence":0.9,"reason":"x"}"#,
+            )
+            .unwrap();
+        assert_eq!(intent.provider, "linear");
+        assert_eq!(intent.target_profile.as_deref(), Some("linear-sam"));
+        assert_eq!(intent.reason, "AI: x");
+        // An unknown action costs a 0.6 penalty, dropping below the threshold.
+        assert!(r
+            .parse_ai_output(
+                r#"{"provider":"linear","workspace":null,"action":"nope","confidence":0.9,"reason":"x"}"#
+            )
+            .is_none());
+        assert!(r.parse_ai_output("no idea").is_none());
+        assert!(r.ai_prompt("file a bug").contains("- action: list_issues"));
+    }
+
     #[test]
     fn sync_builtin_profiles_adds_linear_workspace_profiles() {
         let mut r = make_resolver();
@@ -926,6 +1892,36 @@ mod tests {
         assert!(r.store.actions.contains_key("list_issues"));
     }
 
+    #[test]
+    fn configured_adapter_routes_discovered_workspaces() {
+        let mut r = setup();
+        let adapter: adapters::ProviderSyncConfig = serde_yaml::from_str(
+            "{provider: github, aliases: [gh], servers: 'github*', list_tool: list_repos, fields: {name: full_name, aliases: [name]}}",
+        )
+        .unwrap();
+        r.register_adapter(&adapter, &[]);
+        assert!(r.store.providers["github"]
+            .alias
+            .aliases
+            .contains(&"gh".to_string()));
+
+        let raw = serde_json::json!([{"full_name": "acme/api", "name": "api"}]);
+        let workspaces = adapters::discover(&adapter, &raw).unwrap();
+        let names = r
+            .add_discovered("github", "github-work", &workspaces)
+            .unwrap();
+        assert_eq!(names, ["acme/api"]);
+        let entry = &r.store.providers["github"].workspaces["acme/api"];
+        assert!(entry.alias.learned.is_empty());
+        r.add_action("open_issue", "open an issue").unwrap();
+        let ResolutionOutcome::Resolved(intent) = r.resolve("gh: open an issue in api") else {
+            panic!("Expected Resolved");
+        };
+        assert_eq!(intent.provider, "github");
+        assert_eq!(intent.workspace.as_deref(), Some("acme/api"));
+        assert_eq!(intent.target_profile.as_deref(), Some("github-work"));
+    }
+
     #[test]
     fn list_tickets_resolves_team_alias_to_profile() {
         let mut r = make_resolver();
@@ -1,8 +1,16 @@
+use super::examples::ExampleSet;
+
+use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-fn now_secs() -> u64 {
+/// Usage scores halve after two weeks without use.
+pub const SCORE_HALF_LIFE_SECS: u64 = 14 * 24 * 60 * 60;
+/// Learned aliases that go this long without matching a confirmed resolution are pruned.
+pub const STALE_ALIAS_SECS: u64 = 90 * 24 * 60 * 60;
+
+pub(crate) fn now_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -30,32 +38,122 @@ pub(crate) fn word_boundary_match(text: &str, alias: &str) -> bool {
     false
 }
 
+/// Find the run of words in `text` closest to `alias`, comparing runs with as many words
+/// as the alias has. Returns the edit distance and the words that matched.
+/// Both inputs must already be lowercased.
+pub(crate) fn fuzzy_find(text: &str, alias: &str) -> Option<(usize, String)> {
+    let width = alias.split_whitespace().count();
+    if width == 0 {
+        return None;
+    }
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|w| w.trim_matches(|ch: char| !ch.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .collect();
+    words
+        .windows(width)
+        .map(|window| {
+            let phrase = window.join(" ");
+            (edit_distance(&phrase, alias), phrase)
+        })
+        .min_by_key(|(distance, _)| *distance)
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        prev2 = std::mem::replace(&mut prev, cur);
+    }
+    prev[b.len()]
+}
+
 /// A named thing with aliases, a usage score, and a last-used timestamp.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AliasEntry {
     pub aliases: Vec<String>,
-    pub score: u32,
+    /// Usage score as of `last_used_secs`; see [`AliasEntry::decayed_score`].
+    pub score: f32,
     pub last_used_secs: Option<u64>,
+    /// Aliases added with `/resolver learn`, and when each was learned or last matched a
+    /// confirmed resolution. Only these are ever pruned.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub learned: BTreeMap<String, u64>,
 }
 
 impl AliasEntry {
     pub fn new(aliases: Vec<String>) -> Self {
         Self {
             aliases,
-            score: 0,
+            score: 0.0,
             last_used_secs: None,
+            learned: BTreeMap::new(),
+        }
+    }
+
+    /// Add a lowercased alias. Built-in aliases (`learned == false`) are never pruned,
+    /// even if they were learned before.
+    pub fn add_alias(&mut self, alias: &str, learned: bool) {
+        let alias = alias.to_lowercase();
+        if alias.is_empty() {
+            return;
+        }
+        if !self.aliases.contains(&alias) {
+            self.aliases.push(alias.clone());
+            if learned {
+                self.learned.insert(alias, now_secs());
+            }
+        } else if !learned {
+            self.learned.remove(&alias);
+        } else if let Some(secs) = self.learned.get_mut(&alias) {
+            *secs = now_secs();
         }
     }
 
-    /// True if any alias appears in `text` at a word boundary (case-insensitive).
-    pub fn matches(&self, text: &str) -> bool {
-        let lower = text.to_lowercase();
-        self.aliases.iter().any(|a| word_boundary_match(&lower, a))
+    /// The usage score decayed exponentially from `last_used_secs` to `now`.
+    pub fn decayed_score(&self, now: u64) -> f32 {
+        let age = now.saturating_sub(self.last_used_secs.unwrap_or(now));
+        self.score * 0.5f32.powf(age as f32 / SCORE_HALF_LIFE_SECS as f32)
     }
 
-    pub fn bump(&mut self) {
-        self.score = self.score.saturating_add(1);
-        self.last_used_secs = Some(now_secs());
+    /// Record a confirmed use of the entry for `text` (lowercased), refreshing the
+    /// learned aliases it contains.
+    pub fn bump(&mut self, text: &str) {
+        let now = now_secs();
+        self.score = self.decayed_score(now) + 1.0;
+        self.last_used_secs = Some(now);
+        for (alias, secs) in &mut self.learned {
+            if word_boundary_match(text, alias) {
+                *secs = now;
+            }
+        }
+    }
+
+    /// Remove learned aliases unused for [`STALE_ALIAS_SECS`], returning them.
+    pub fn prune_learned(&mut self, now: u64) -> Vec<String> {
+        let stale: Vec<String> = self
+            .learned
+            .iter()
+            .filter(|(_, secs)| now.saturating_sub(**secs) >= STALE_ALIAS_SECS)
+            .map(|(alias, _)| alias.clone())
+            .collect();
+        for alias in &stale {
+            self.learned.remove(alias);
+        }
+        self.aliases.retain(|v| !stale.contains(v));
+        stale
     }
 }
 
@@ -108,10 +206,86 @@ pub struct ResolverStore {
     /// Actions keyed by canonical snake_case name (e.g. "create_tickets").
     #[serde(default)]
     pub actions: HashMap<String, AliasEntry>,
+    /// Labelled utterances for the nearest-neighbour stage.
+    #[serde(default, skip_serializing_if = "ExampleSet::is_empty")]
+    pub examples: ExampleSet,
+}
+
+impl ResolverStore {
+    pub(crate) fn upsert_provider(
+        &mut self,
+        name: &str,
+        alias: Option<&str>,
+        learned: bool,
+    ) -> Result<()> {
+        let key = name.to_lowercase();
+        if key.is_empty() {
+            bail!("Provider name cannot be empty");
+        }
+        let entry = self
+            .providers
+            .entry(key.clone())
+            .or_insert_with(|| ProviderEntry::new(vec![key.clone()]));
+        if let Some(a) = alias {
+            entry.alias.add_alias(a, learned);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn upsert_workspace(
+        &mut self,
+        provider: &str,
+        name: &str,
+        target_profile: Option<&str>,
+        alias: Option<&str>,
+        learned: bool,
+    ) -> Result<()> {
+        let prov_key = provider.to_lowercase();
+        let prov = self.providers.get_mut(&prov_key).ok_or_else(|| {
+            anyhow!(
+                "Provider '{}' not found. Add it first with `/resolver learn provider {}`",
+                provider,
+                provider
+            )
+        })?;
+        let ws_key = name.to_lowercase();
+        if ws_key.is_empty() {
+            bail!("Workspace name cannot be empty");
+        }
+        let ws_entry = prov.workspaces.entry(ws_key.clone()).or_insert_with(|| {
+            WorkspaceEntry::new(
+                name,
+                target_profile.map(str::to_string),
+                vec![ws_key.clone()],
+            )
+        });
+        if let Some(profile) = target_profile.map(str::trim).filter(|s| !s.is_empty()) {
+            ws_entry.target_profile = Some(profile.to_string());
+        }
+        if let Some(a) = alias {
+            ws_entry.alias.add_alias(a, learned);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn upsert_action(&mut self, name: &str, alias: &str, learned: bool) -> Result<()> {
+        if name.is_empty() {
+            bail!("Action name cannot be empty");
+        }
+        if alias.is_empty() {
+            bail!("Action alias cannot be empty");
+        }
+        let entry = self
+            .actions
+            .entry(name.to_string())
+            .or_insert_with(|| AliasEntry::new(vec![name.to_lowercase()]));
+        entry.add_alias(alias, learned);
+        Ok(())
+    }
 }
 
 /// The result of a successful resolution.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ResolvedIntent {
     pub provider: String,
     pub workspace: Option<String>,
@@ -123,6 +297,13 @@ pub struct ResolvedIntent {
 }
 
 impl ResolvedIntent {
+    /// `provider / workspace / action`, with `-` for what is missing.
+    pub fn label(&self) -> String {
+        let workspace = self.workspace.as_deref().unwrap_or("-");
+        let action = self.action.as_deref().unwrap_or("-");
+        format!("{} / {workspace} / {action}", self.provider)
+    }
+
     /// Build the context preamble that is prepended to the user message.
     pub fn to_preamble(&self) -> String {
         let workspace = self.workspace.as_deref().unwrap_or("-");
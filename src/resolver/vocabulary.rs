@@ -0,0 +1,445 @@
+//! Resolver vocabulary documents for `fio resolver export` / `import`.
+//!
+//! A [`Vocabulary`] is the curated part of a store — providers, workspaces, actions,
+//! their aliases and the labelled examples — without usage scores or embeddings, sorted so
+//! it diffs cleanly under version control.
+
+use super::examples::IntentExample;
+use super::types::{AliasEntry, ResolverStore};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Vocabulary {
+    /// Providers keyed by canonical name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<String, ProviderVocabulary>,
+    /// Action aliases keyed by action name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub actions: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<IntentExample>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderVocabulary {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Workspaces keyed by display name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workspaces: BTreeMap<String, WorkspaceVocabulary>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceVocabulary {
+    /// MCP server the workspace is scoped to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add to the store; nothing is removed.
+    Merge,
+    /// Make the store match the document. Entries that survive keep their usage scores.
+    Replace,
+}
+
+impl Vocabulary {
+    pub fn from_store(store: &ResolverStore) -> Self {
+        let providers = store
+            .providers
+            .iter()
+            .map(|(key, prov)| {
+                let workspaces = prov
+                    .workspaces
+                    .values()
+                    .map(|ws| {
+                        let vocabulary = WorkspaceVocabulary {
+                            target_profile: ws.target_profile.clone(),
+                            aliases: explicit_aliases(&ws.alias, &ws.name),
+                        };
+                        (ws.name.clone(), vocabulary)
+                    })
+                    .collect();
+                let vocabulary = ProviderVocabulary {
+                    aliases: explicit_aliases(&prov.alias, key),
+                    workspaces,
+                };
+                (key.clone(), vocabulary)
+            })
+            .collect();
+        let actions = store
+            .actions
+            .iter()
+            .map(|(key, entry)| (key.clone(), explicit_aliases(entry, key)))
+            .collect();
+        let examples = store
+            .examples
+            .items
+            .iter()
+            .map(|v| IntentExample {
+                vector: vec![],
+                ..v.clone()
+            })
+            .collect();
+        Self {
+            providers,
+            actions,
+            examples,
+        }
+    }
+
+    /// Read a document, as JSON when the file ends in `.json` and as YAML otherwise.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        let vocabulary = if is_json(path) {
+            serde_json::from_str(&data).map_err(anyhow::Error::from)
+        } else {
+            serde_yaml::from_str(&data).map_err(anyhow::Error::from)
+        };
+        vocabulary.with_context(|| format!("Invalid resolver vocabulary at '{}'", path.display()))
+    }
+
+    pub fn to_text(&self, json: bool) -> Result<String> {
+        if json {
+            Ok(serde_json::to_string_pretty(self)? + "\n")
+        } else {
+            Ok(serde_yaml::to_string(self)?)
+        }
+    }
+
+    /// Apply the document to `store`. Imported aliases are curated, so they are never
+    /// pruned as stale.
+    pub fn apply(&self, store: &mut ResolverStore, mode: ImportMode) -> Result<()> {
+        if mode == ImportMode::Replace {
+            self.retain_in(store);
+        }
+        for (provider, prov) in &self.providers {
+            store.upsert_provider(provider, None, false)?;
+            for alias in &prov.aliases {
+                store.upsert_provider(provider, Some(alias), false)?;
+            }
+            for (name, ws) in &prov.workspaces {
+                let target_profile = ws.target_profile.as_deref();
+                store.upsert_workspace(provider, name, target_profile, None, false)?;
+                for alias in &ws.aliases {
+                    store.upsert_workspace(provider, name, None, Some(alias), false)?;
+                }
+            }
+        }
+        for (action, aliases) in &self.actions {
+            store.upsert_action(action, &action.to_lowercase(), false)?;
+            for alias in aliases {
+                store.upsert_action(action, alias, false)?;
+            }
+        }
+        for example in &self.examples {
+            if !store
+                .examples
+                .items
+                .iter()
+                .any(|v| same_example(v, example))
+            {
+                store.examples.add(IntentExample::new(
+                    &example.text,
+                    &example.provider,
+                    example.workspace.as_deref(),
+                    example.action.as_deref(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop whatever in `store` the document doesn't mention.
+    fn retain_in(&self, store: &mut ResolverStore) {
+        let provider = |key: &str| {
+            self.providers
+                .iter()
+                .find(|(name, _)| name.to_lowercase() == key)
+                .map(|(_, v)| v)
+        };
+        store.providers.retain(|key, _| provider(key).is_some());
+        for (key, prov) in &mut store.providers {
+            let Some(doc) = provider(key) else {
+                continue;
+            };
+            retain_aliases(&mut prov.alias, key, &doc.aliases);
+            let workspace = |ws_key: &str| {
+                doc.workspaces
+                    .iter()
+                    .find(|(name, _)| name.to_lowercase() == ws_key)
+                    .map(|(_, v)| v)
+            };
+            prov.workspaces
+                .retain(|ws_key, _| workspace(ws_key).is_some());
+            for (ws_key, ws) in &mut prov.workspaces {
+                let Some(doc) = workspace(ws_key) else {
+                    continue;
+                };
+                ws.target_profile = doc.target_profile.clone();
+                retain_aliases(&mut ws.alias, ws_key, &doc.aliases);
+            }
+        }
+        store
+            .actions
+            .retain(|key, _| self.actions.contains_key(key));
+        for (key, entry) in &mut store.actions {
+            retain_aliases(entry, &key.to_lowercase(), &self.actions[key]);
+        }
+        store
+            .examples
+            .items
+            .retain(|v| self.examples.iter().any(|e| same_example(v, e)));
+    }
+
+    /// What is wrong with the document, given the store it produced and the configured
+    /// MCP server names. Empty when it can be imported.
+    pub fn problems(&self, store: &ResolverStore, servers: &[String]) -> Vec<String> {
+        let mut problems = vec![];
+        let mut check_aliases = |what: &str, aliases: &[String]| {
+            if aliases.iter().any(|v| v.trim().is_empty()) {
+                problems.push(format!("{what}: empty alias"));
+            }
+        };
+        for (provider, prov) in &self.providers {
+            check_aliases(&format!("provider {provider}"), &prov.aliases);
+            for (name, ws) in &prov.workspaces {
+                check_aliases(&format!("workspace {provider}/{name}"), &ws.aliases);
+            }
+        }
+        for (action, aliases) in &self.actions {
+            check_aliases(&format!("action {action}"), aliases);
+        }
+
+        for (provider, prov) in &self.providers {
+            for (name, ws) in &prov.workspaces {
+                if let Some(server) = &ws.target_profile {
+                    if !servers.contains(server) {
+                        problems.push(format!(
+                            "workspace {provider}/{name}: target_profile '{server}' is not a configured MCP server"
+                        ));
+                    }
+                }
+            }
+        }
+        for example in &self.examples {
+            let prov = store.providers.get(&example.provider.to_lowercase());
+            let known = prov.is_some_and(|prov| {
+                example
+                    .workspace
+                    .as_ref()
+                    .is_none_or(|ws| prov.workspaces.contains_key(&ws.to_lowercase()))
+            }) && example
+                .action
+                .as_ref()
+                .is_none_or(|v| store.actions.contains_key(v));
+            if !known {
+                problems.push(format!(
+                    "example '{}': unknown {}",
+                    example.text,
+                    label(example)
+                ));
+            }
+        }
+        problems
+    }
+
+    /// One line per fact, e.g. `workspace linear/SAM alias 'sam'`.
+    fn facts(&self) -> BTreeSet<String> {
+        let mut facts = BTreeSet::new();
+        for (provider, prov) in &self.providers {
+            facts.insert(format!("provider {provider}"));
+            for alias in &prov.aliases {
+                facts.insert(format!("provider {provider} alias '{alias}'"));
+            }
+            for (name, ws) in &prov.workspaces {
+                facts.insert(format!("workspace {provider}/{name}"));
+                if let Some(server) = &ws.target_profile {
+                    facts.insert(format!(
+                        "workspace {provider}/{name} target_profile {server}"
+                    ));
+                }
+                for alias in &ws.aliases {
+                    facts.insert(format!("workspace {provider}/{name} alias '{alias}'"));
+                }
+            }
+        }
+        for (action, aliases) in &self.actions {
+            facts.insert(format!("action {action}"));
+            for alias in aliases {
+                facts.insert(format!("action {action} alias '{alias}'"));
+            }
+        }
+        for example in &self.examples {
+            facts.insert(format!("example '{}' -> {}", example.text, label(example)));
+        }
+        facts
+    }
+
+    /// `- fact` / `+ fact` lines turning `before` into `after`, ordered by fact.
+    pub fn diff(before: &Self, after: &Self) -> Vec<String> {
+        let (before, after) = (before.facts(), after.facts());
+        let mut lines: Vec<(&String, char)> = before
+            .difference(&after)
+            .map(|v| (v, '-'))
+            .chain(after.difference(&before).map(|v| (v, '+')))
+            .collect();
+        lines.sort();
+        lines
+            .into_iter()
+            .map(|(fact, sign)| format!("{sign} {fact}"))
+            .collect()
+    }
+}
+
+pub fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|v| v.eq_ignore_ascii_case("json"))
+}
+
+/// Aliases besides the one every entry gets from its name, sorted.
+fn explicit_aliases(entry: &AliasEntry, name: &str) -> Vec<String> {
+    let implicit = name.to_lowercase();
+    let mut aliases: Vec<String> = entry
+        .aliases
+        .iter()
+        .filter(|v| **v != implicit)
+        .cloned()
+        .collect();
+    aliases.sort();
+    aliases
+}
+
+fn retain_aliases(entry: &mut AliasEntry, implicit: &str, aliases: &[String]) {
+    let keep = |v: &String| v == implicit || aliases.iter().any(|a| a.to_lowercase() == *v);
+    entry.aliases.retain(keep);
+    entry.learned.retain(|v, _| keep(v));
+}
+
+fn same_example(a: &IntentExample, b: &IntentExample) -> bool {
+    a.text.eq_ignore_ascii_case(b.text.trim())
+        && a.provider.eq_ignore_ascii_case(&b.provider)
+        && a.workspace == b.workspace
+        && a.action == b.action
+}
+
+fn label(example: &IntentExample) -> String {
+    let workspace = example
+        .workspace
+        .as_deref()
+        .map(|v| format!("/{v}"))
+        .unwrap_or_default();
+    format!(
+        "{}{workspace} {}",
+        example.provider,
+        example.action.as_deref().unwrap_or("-")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"
+providers:
+  linear:
+    aliases: [ln]
+    workspaces:
+      SAM: { target_profile: linear-sam, aliases: [samwise] }
+actions:
+  create_tickets: [create tickets, open a bug]
+examples:
+  - { text: file a bug for sam, provider: linear, workspace: SAM, action: create_tickets }
+"#;
+
+    fn store() -> ResolverStore {
+        let mut store = ResolverStore::default();
+        store.upsert_provider("linear", None, false).unwrap();
+        store
+            .upsert_workspace("linear", "OPS", None, Some("platform"), true)
+            .unwrap();
+        store
+            .upsert_action("list_issues", "list issues", false)
+            .unwrap();
+        store.actions.get_mut("list_issues").unwrap().score = 3.0;
+        store
+    }
+
+    #[test]
+    fn merge_and_replace_with_diff() {
+        let vocabulary: Vocabulary = serde_yaml::from_str(DOCUMENT).unwrap();
+        let servers = vec!["linear-sam".to_string()];
+
+        let mut merged = store();
+        vocabulary.apply(&mut merged, ImportMode::Merge).unwrap();
+        assert!(vocabulary.problems(&merged, &servers).is_empty());
+        assert_eq!(merged.actions["list_issues"].score, 3.0);
+        assert!(merged.providers["linear"].workspaces["sam"]
+            .alias
+            .learned
+            .is_empty());
+        let diff = Vocabulary::diff(
+            &Vocabulary::from_store(&store()),
+            &Vocabulary::from_store(&merged),
+        );
+        assert!(diff.contains(&"+ workspace linear/SAM target_profile linear-sam".into()));
+        assert!(diff.contains(&"+ action create_tickets alias 'open a bug'".into()));
+        assert!(diff.iter().all(|v| v.starts_with('+')), "{diff:?}");
+
+        let mut replaced = merged.clone();
+        vocabulary
+            .apply(&mut replaced, ImportMode::Replace)
+            .unwrap();
+        assert_eq!(Vocabulary::from_store(&replaced), vocabulary);
+        let diff = Vocabulary::diff(
+            &Vocabulary::from_store(&merged),
+            &Vocabulary::from_store(&replaced),
+        );
+        assert_eq!(
+            diff,
+            [
+                "- action list_issues",
+                "- action list_issues alias 'list issues'",
+                "- workspace linear/OPS",
+                "- workspace linear/OPS alias 'platform'",
+            ]
+        );
+
+        let json = Vocabulary::from_store(&replaced).to_text(true).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vocabulary>(&json).unwrap(),
+            vocabulary
+        );
+    }
+
+    #[test]
+    fn problems_name_unknown_servers_and_labels() {
+        let mut vocabulary: Vocabulary = serde_yaml::from_str(DOCUMENT).unwrap();
+        vocabulary.examples[0].action = Some("close_tickets".into());
+        vocabulary
+            .actions
+            .insert("list_issues".into(), vec![" ".into()]);
+        let mut store = ResolverStore::default();
+        vocabulary.apply(&mut store, ImportMode::Merge).unwrap();
+        assert_eq!(
+            vocabulary.problems(&store, &[]),
+            [
+                "action list_issues: empty alias",
+                "workspace linear/SAM: target_profile 'linear-sam' is not a configured MCP server",
+                "example 'file a bug for sam': unknown linear/SAM close_tickets",
+            ]
+        );
+        assert!(serde_yaml::from_str::<Vocabulary>("actons: {}").is_err());
+    }
+}
@@ -1,12 +1,23 @@
 use crate::client::call_chat_completions;
 use crate::config::{GlobalConfig, Input, Role, RoleLike};
-use crate::resolver::{extract_json_object, ResolutionOutcome, ResolvedIntent, Resolver};
-use crate::utils::{dimmed_text, AbortSignal};
+use crate::resolver::{ResolutionOutcome, ResolvedIntent, Resolver};
+use crate::telemetry::{in_span, set_attribute, SpanKind};
+use crate::utils::{dimmed_text, AbortSignal, IS_STDOUT_TERMINAL};
 
 use anyhow::Result;
+use serde::Deserialize;
+
+mod clarify;
+mod rules;
+mod split;
+
+pub use self::clarify::Clarification;
+use self::rules::RuleContext;
+pub use self::rules::{ModelSlot, RoutingRules};
 
 /// Execution policy for a single turn.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TurnPolicy {
     Auto,
     Chat,
@@ -22,6 +33,8 @@ pub enum TurnOperation {
 
 /// The result of routing a single user turn.
 pub struct TurnRoute {
+    /// The user's text as typed.
+    pub input: String,
     /// Enriched text (with preamble if resolved).
     pub text: String,
     /// Model override (None = use current).
@@ -32,112 +45,392 @@ pub struct TurnRoute {
     pub operation: Option<TurnOperation>,
     /// How to fulfill this turn.
     pub policy: TurnPolicy,
+    /// Always ask before running commands or tools.
+    pub confirm: bool,
+    /// Name of the routing rule that fired.
+    pub rule: String,
     /// For post-turn learning.
     pub intent: Option<ResolvedIntent>,
+    /// The intent was picked in a clarification and is already learned.
+    pub learned: bool,
+    /// Set in serve mode when the resolver could not decide; the turn should ask the
+    /// user instead of running.
+    pub clarification: Option<Clarification>,
 }
 
-/// Central per-turn routing. Both one-shot and interactive surfaces call this.
+/// What the resolver stages made of a request.
+pub enum TurnIntent {
+    Resolved(ResolvedIntent),
+    /// Not even the AI fallback could decide; the options may be empty.
+    Ambiguous(Clarification),
+    None,
+}
+
+/// The requests in a turn that may hold several, e.g. "restart nginx and then open a
+/// Linear ticket in SAM" (see `split`); parts only split where the resolver
+/// resolves one deterministically. Every surface routes each part with [`route_turn`]
+/// right before running it, so a part is routed (and any question about it asked) only
+/// after the ones before it ran, each with its own policy, tool scope and confirmation.
+pub fn split_turn(config: &GlobalConfig, text: &str) -> Vec<String> {
+    let resolver = config.read().resolver.clone();
+    let parts = split::split_intents(text, |part| {
+        resolver
+            .as_ref()
+            .is_some_and(|v| matches!(v.resolve(part), ResolutionOutcome::Resolved(_)))
+    });
+    if parts.len() > 1 {
+        debug!("split turn into {} parts: {parts:?}", parts.len());
+    }
+    parts
+}
+
+/// Central per-turn routing of a single request. Both one-shot and interactive surfaces
+/// call this for each part [`split_turn`] finds.
 ///
 /// Logic:
 /// 1. Run resolver (if available and non-empty)
-///    - Resolved → thinking model, Chat policy, enriched text
-///    - NeedsAi → AI fallback → if resolved: same; else fall through
-///    - PassThrough → fall through
-/// 2. Fall back to `looks_operational_prompt()` heuristic
-///    - Operational → thinking model, Plan policy, original text
-///    - Not operational → fast model, Chat policy, original text
+///    - Resolved → intent
+///    - NeedsAi → nearest labelled example → intent if close enough, otherwise
+///      AI fallback → intent if resolved, otherwise the user picks among
+///      the resolver's candidates (see `clarify`); serve mode leaves the question on
+///      the route
+///    - PassThrough → no intent
+/// 2. Evaluate the routing rules (see `rules`); the first match picks policy, model,
+///    tool scope and confirmation, and an intent-matching rule adds the preamble
 /// 3. Return TurnRoute
 pub async fn route_turn(
     config: &GlobalConfig,
     abort_signal: AbortSignal,
     text: &str,
+) -> Result<TurnRoute> {
+    let attributes = vec![("fiochat.input_chars", (text.chars().count() as u64).into())];
+    in_span(
+        "route_turn",
+        SpanKind::Internal,
+        attributes,
+        route_turn_inner(config, abort_signal, text),
+    )
+    .await
+}
+
+async fn route_turn_inner(
+    config: &GlobalConfig,
+    abort_signal: AbortSignal,
+    text: &str,
 ) -> Result<TurnRoute> {
     // Step 1: resolver
-    let resolver = config.read().resolver.clone();
-    if let Some(ref resolver) = resolver {
-        if !resolver.is_empty() {
-            match resolver.resolve(text) {
-                ResolutionOutcome::Resolved(intent) => {
-                    let intent = apply_linear_profile_default(config, intent);
-                    let preamble = intent.to_preamble();
-                    println!("{}", dimmed_text(&preamble));
-                    let model_id = config.read().model_thinking.clone();
-                    return Ok(TurnRoute {
-                        text: format!("{preamble}\n{text}"),
-                        model_id,
-                        use_tools: scoped_use_tools(config, &intent),
-                        operation: routed_operation(&intent),
-                        policy: TurnPolicy::Chat,
-                        intent: Some(intent),
-                    });
+    let mut intent = None;
+    let mut learned = false;
+    let mut clarification = None;
+    match resolve_turn_intent(config, abort_signal, text).await {
+        TurnIntent::Resolved(resolved) => intent = Some(resolved),
+        TurnIntent::Ambiguous(asked) if !asked.options.is_empty() => {
+            if config.read().working_mode.is_serve() {
+                clarification = Some(asked);
+            } else if *IS_STDOUT_TERMINAL {
+                if let Some(picked) = asked.ask().await? {
+                    learn_intent(config, &picked, text).await;
+                    learned = true;
+                    intent = Some(picked);
                 }
-                ResolutionOutcome::NeedsAi => {
-                    match resolver_ai_fallback(resolver, config, abort_signal, text).await {
-                        Ok(Some(intent)) => {
-                            let intent = apply_linear_profile_default(config, intent);
-                            let preamble = intent.to_preamble();
-                            println!("{}", dimmed_text(&preamble));
-                            let model_id = config.read().model_thinking.clone();
-                            return Ok(TurnRoute {
-                                text: format!("{preamble}\n{text}"),
-                                model_id,
-                                use_tools: scoped_use_tools(config, &intent),
-                                operation: routed_operation(&intent),
-                                policy: TurnPolicy::Chat,
-                                intent: Some(intent),
-                            });
-                        }
-                        Ok(None) => {
-                            println!(
-                                "Ambiguous intent — not sure which provider/workspace/action you mean.\n\
-                                 Use `/resolver learn` to teach me, or be more explicit."
-                            );
-                            // Fall through to heuristic
-                        }
-                        Err(e) => {
-                            warn!("Resolver AI fallback failed: {e}");
-                            // Fall through to heuristic
-                        }
-                    }
+            } else {
+                print_ambiguous();
+            }
+        }
+        TurnIntent::Ambiguous(_) => print_ambiguous(),
+        TurnIntent::None => {}
+    }
+    let mut route = finish_route(config, text, intent);
+    route.learned = learned;
+    route.clarification = clarification;
+    Ok(route)
+}
+
+/// Route `text` with the intent the user picked for `clarification`, which is learned.
+pub async fn route_clarified(
+    config: &GlobalConfig,
+    clarification: &Clarification,
+    intent: ResolvedIntent,
+) -> TurnRoute {
+    learn_intent(config, &intent, &clarification.input).await;
+    let mut route = finish_route(config, &clarification.input, Some(intent));
+    route.learned = true;
+    route
+}
+
+/// Step 1 of routing: the deterministic resolver, then the nearest labelled example,
+/// then the AI fallback; candidates to ask about when none of them decides.
+pub async fn resolve_turn_intent(
+    config: &GlobalConfig,
+    abort_signal: AbortSignal,
+    text: &str,
+) -> TurnIntent {
+    let resolver = config.read().resolver.clone();
+    let Some(resolver) = resolver.filter(|v| !v.is_empty()) else {
+        return TurnIntent::None;
+    };
+    let outcome = resolver.resolve(text);
+    let label = outcome_label(&outcome);
+    debug!(resolver = label; "resolver outcome: {label}");
+    set_attribute("fiochat.resolver.outcome", label);
+    crate::metrics::record_resolver_outcome(label);
+    // Only requests that already hint at a provider or action reach the example
+    // stage; pass-through chatter never pays for an embedding lookup.
+    let outcome = match outcome {
+        ResolutionOutcome::NeedsAi => match resolve_by_example(&resolver, config, text).await {
+            Some(resolved) => ResolutionOutcome::Resolved(resolved),
+            None => outcome,
+        },
+        _ => outcome,
+    };
+    match outcome {
+        ResolutionOutcome::Resolved(resolved) => TurnIntent::Resolved(resolved),
+        ResolutionOutcome::NeedsAi => {
+            let fallback = in_span(
+                "resolver_ai_fallback",
+                SpanKind::Internal,
+                vec![],
+                resolver_ai_fallback(&resolver, config, abort_signal, text),
+            );
+            match fallback.await {
+                Ok(Some(resolved)) => TurnIntent::Resolved(resolved),
+                Ok(None) => {
+                    TurnIntent::Ambiguous(Clarification::offered(&resolver, text))
                 }
-                ResolutionOutcome::PassThrough => {
-                    // Fall through to heuristic
+                Err(e) => {
+                    warn!("Resolver AI fallback failed: {e}");
+                    TurnIntent::None
                 }
             }
         }
+        ResolutionOutcome::PassThrough => TurnIntent::None,
     }
+}
 
-    // Step 2: heuristic fallback
-    if looks_operational_prompt(text) {
-        let model_id = config.read().model_thinking.clone();
-        Ok(TurnRoute {
-            text: text.to_string(),
-            model_id,
-            use_tools: None,
-            operation: None,
-            policy: TurnPolicy::Plan,
-            intent: None,
-        })
-    } else {
-        let model_id = config.read().model_fast.clone();
-        Ok(TurnRoute {
+fn print_ambiguous() {
+    println!(
+        "Ambiguous intent — not sure which provider/workspace/action you mean.\n\
+         Use `/resolver learn` to teach me, or be more explicit."
+    );
+}
+
+/// Record a confirmed intent for `utterance` and save the resolver.
+pub async fn learn_intent(config: &GlobalConfig, intent: &ResolvedIntent, utterance: &str) {
+    let cloned = config.read().resolver.clone();
+    if let Some(mut r) = cloned {
+        r.learn(intent, utterance);
+        if let Err(e) = r.embed_examples(config).await {
+            warn!("Resolver: failed to embed examples: {e}");
+        }
+        if let Err(e) = r.save() {
+            warn!("Resolver: failed to save after learning: {e}");
+        } else {
+            config.write().resolver = Some(r);
+        }
+    }
+}
+
+/// Step 2 of routing: apply the routing rules to the resolved intent.
+fn finish_route(config: &GlobalConfig, text: &str, intent: Option<ResolvedIntent>) -> TurnRoute {
+    let intent = intent.map(|v| apply_linear_profile_default(config, v));
+    let route = apply_rules(config, text, intent);
+    debug!(rule = route.rule.as_str(); "routing rule: {}", route.rule);
+    set_attribute("fiochat.route.rule", route.rule.clone());
+    if let Some(intent) = &route.intent {
+        println!("{}", dimmed_text(&intent.to_preamble()));
+    }
+    route
+}
+
+/// The nearest-neighbour stage between alias matching and the LLM fallback. Errors
+/// (e.g. an unreachable embedding API) fall through to the next stage.
+async fn resolve_by_example(
+    resolver: &Resolver,
+    config: &GlobalConfig,
+    text: &str,
+) -> Option<ResolvedIntent> {
+    if !resolver.has_examples_index() {
+        return None;
+    }
+    let ret = in_span(
+        "resolver_examples",
+        SpanKind::Client,
+        vec![],
+        resolver.resolve_by_example(config, text),
+    )
+    .await;
+    match ret {
+        Ok(Some(intent)) => {
+            debug!(resolver = "example"; "resolver outcome: example");
+            crate::metrics::record_resolver_outcome("example");
+            Some(intent)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Resolver example lookup failed: {e}");
+            None
+        }
+    }
+}
+
+/// Evaluate the routing rules against a turn and build its route. The intent is kept
+/// only when the rule that fired matched on it.
+fn apply_rules(config: &GlobalConfig, text: &str, intent: Option<ResolvedIntent>) -> TurnRoute {
+    let (rules, role, agent, model_fast, model_thinking) = {
+        let cfg = config.read();
+        (
+            cfg.routing_rules.clone(),
+            cfg.role.as_ref().map(|v| v.name().to_string()),
+            cfg.agent.as_ref().map(|v| v.name().to_string()),
+            cfg.model_fast.clone(),
+            cfg.model_thinking.clone(),
+        )
+    };
+    let ctx = RuleContext {
+        text,
+        intent: intent.as_ref(),
+        role: role.as_deref(),
+        agent: agent.as_deref(),
+    };
+    let Some((_, rule)) = rules.evaluate(&ctx) else {
+        return TurnRoute {
+            input: text.to_string(),
             text: text.to_string(),
-            model_id,
+            model_id: model_fast,
             use_tools: None,
             operation: None,
             policy: TurnPolicy::Chat,
+            confirm: false,
+            rule: "-".to_string(),
             intent: None,
-        })
+            learned: false,
+            clarification: None,
+        };
+    };
+    let intent = intent.filter(|_| rule.matcher.intent.is_some());
+    let route = &rule.route;
+    let model_id = match route.model {
+        Some(ModelSlot::Current) => None,
+        Some(ModelSlot::Fast) => model_fast,
+        Some(ModelSlot::Thinking) => model_thinking,
+        None => select_route_model(
+            route.policy,
+            model_fast.as_deref(),
+            model_thinking.as_deref(),
+        ),
+    };
+    let use_tools = match route.use_tools.as_deref() {
+        Some("intent") => intent
+            .as_ref()
+            .and_then(|intent| scoped_use_tools(config, intent)),
+        Some(use_tools) => Some(use_tools.to_string()),
+        None => None,
+    };
+    let enriched = match &intent {
+        Some(intent) => format!("{}\n{text}", intent.to_preamble()),
+        None => text.to_string(),
+    };
+    TurnRoute {
+        input: text.to_string(),
+        text: enriched,
+        model_id,
+        use_tools,
+        operation: intent.as_ref().and_then(routed_operation),
+        policy: route.policy,
+        confirm: route.confirm,
+        rule: rule.name.clone(),
+        intent,
+        learned: false,
+        clarification: None,
+    }
+}
+
+/// Describe how `text` would be routed, without calling the AI resolver fallback.
+/// A compound turn is explained part by part.
+pub fn explain_route(config: &GlobalConfig, text: &str) -> String {
+    let parts = split_turn(config, text);
+    if parts.len() == 1 {
+        return explain_part(config, text);
+    }
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| format!("part {}:     {part}\n{}", i + 1, explain_part(config, part)))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn explain_part(config: &GlobalConfig, text: &str) -> String {
+    let resolver = config.read().resolver.clone();
+    let outcome = resolver
+        .as_ref()
+        .filter(|v| !v.is_empty())
+        .map(|v| v.resolve(text));
+    let mut lines = vec![];
+    let (resolver_line, intent) = match outcome {
+        Some(ResolutionOutcome::Resolved(intent)) => {
+            let intent = apply_linear_profile_default(config, intent);
+            (format!("resolved ({})", intent.reason), Some(intent))
+        }
+        Some(ResolutionOutcome::NeedsAi) => ("needs_ai (AI fallback not run)".to_string(), None),
+        Some(ResolutionOutcome::PassThrough) => ("pass_through".to_string(), None),
+        None => ("-".to_string(), None),
+    };
+    let rules = config.read().routing_rules.clone();
+    let source = match &rules.source {
+        Some(path) => path.display().to_string(),
+        None => "built-in".to_string(),
+    };
+    lines.push(format!("rules:      {source}"));
+    lines.push(format!("resolver:   {resolver_line}"));
+    for name in rules.classifiers.keys() {
+        let score = rules.score(name, text).unwrap_or_default();
+        lines.push(format!("classifier: {name}={score:.2}"));
+    }
+    let route = apply_rules(config, text, intent);
+    let index = rules.rules.iter().position(|v| v.name == route.rule);
+    match index {
+        Some(index) => lines.push(format!("rule:       #{} {}", index + 1, route.rule)),
+        None => lines.push("rule:       - (no rule matched)".to_string()),
+    }
+    lines.push(format!("policy:     {:?}", route.policy).to_lowercase());
+    lines.push(format!(
+        "model:      {}",
+        route.model_id.as_deref().unwrap_or("(current)")
+    ));
+    lines.push(format!(
+        "use_tools:  {}",
+        route.use_tools.as_deref().unwrap_or("-")
+    ));
+    lines.push(format!("confirm:    {}", route.confirm));
+    lines.join("\n")
+}
+
+fn outcome_label(outcome: &ResolutionOutcome) -> &'static str {
+    match outcome {
+        ResolutionOutcome::Resolved(_) => "resolved",
+        ResolutionOutcome::NeedsAi => "needs_ai",
+        ResolutionOutcome::PassThrough => "pass_through",
     }
 }
 
 pub fn role_for_route(config: &GlobalConfig, route: &TurnRoute) -> Option<Role> {
-    let use_tools = route.use_tools.clone()?;
+    if route.use_tools.is_none() && !route.confirm {
+        return None;
+    }
     let mut role = config.read().extract_role();
     let model = role.model().clone();
     let temperature = role.temperature();
     let top_p = role.top_p();
-    role.batch_set(&model, temperature, top_p, Some(use_tools));
+    let tool_call_permission = route.confirm.then(|| "ask".to_string());
+    role.batch_set_with_permissions(
+        &model,
+        temperature,
+        top_p,
+        route.use_tools.clone(),
+        tool_call_permission,
+        None,
+    );
     Some(role)
 }
 
@@ -154,102 +447,6 @@ pub fn select_route_model(
     }
 }
 
-pub fn looks_operational_prompt(text: &str) -> bool {
-    let text = text.trim().to_ascii_lowercase();
-    if text.is_empty() {
-        return false;
-    }
-
-    let explanatory_prefixes = [
-        "what ",
-        "why ",
-        "how ",
-        "how do ",
-        "how to ",
-        "explain ",
-        "tell me ",
-        "describe ",
-        "can you explain ",
-    ];
-    if explanatory_prefixes
-        .iter()
-        .any(|prefix| text.starts_with(prefix))
-    {
-        return false;
-    }
-
-    let command_prefixes = [
-        "git ",
-        "docker ",
-        "kubectl ",
-        "terraform ",
-        "ansible ",
-        "helm ",
-        "npm ",
-        "pnpm ",
-        "yarn ",
-        "cargo ",
-        "make ",
-        "systemctl ",
-        "brew ",
-        "apt ",
-        "yum ",
-        "dnf ",
-        "ssh ",
-        "scp ",
-        "rsync ",
-    ];
-    if command_prefixes
-        .iter()
-        .any(|prefix| text.starts_with(prefix))
-    {
-        return true;
-    }
-
-    let operational_keywords = [
-        "commit",
-        "push",
-        "deploy",
-        "release",
-        "restart",
-        "start",
-        "stop",
-        "install",
-        "uninstall",
-        "remove",
-        "delete",
-        "create",
-        "run",
-        "execute",
-        "build",
-        "test",
-        "lint",
-        "format",
-        "rollback",
-        "migrate",
-        "kill",
-        "tail",
-        "grep",
-        "checkout",
-        "rebase",
-        "merge",
-        "cherry-pick",
-    ];
-    let first_word = text.split_whitespace().next().unwrap_or_default();
-    if operational_keywords.contains(&first_word) {
-        return true;
-    }
-
-    operational_keywords
-        .iter()
-        .any(|keyword| text.contains(keyword))
-        && (text.contains("please")
-            || text.contains("can you")
-            || text.contains("could you")
-            || text.contains(" and ")
-            || text.contains(" then "))
-}
-
 /// Call the configured LLM to resolve ambiguous intent into structured JSON.
 /// Returns `None` when the AI confidence is below threshold.
 async fn resolver_ai_fallback(
@@ -258,85 +455,26 @@ async fn resolver_ai_fallback(
     abort_signal: AbortSignal,
     text: &str,
 ) -> Result<Option<ResolvedIntent>> {
-    let mut options = String::new();
-    for (prov_key, prov_entry) in &resolver.store.providers {
-        let aliases = prov_entry.alias.aliases.join(", ");
-        options.push_str(&format!("- provider: {prov_key} (aliases: {aliases})\n"));
-        for (ws_key, ws_entry) in &prov_entry.workspaces {
-            let ws_aliases = ws_entry.alias.aliases.join(", ");
-            options.push_str(&format!(
-                "  - workspace: {ws_key} / {} (aliases: {ws_aliases})\n",
-                ws_entry.name
-            ));
-        }
-    }
-    for (action_key, action_entry) in &resolver.store.actions {
-        let aliases = action_entry.aliases.join(", ");
-        options.push_str(&format!("- action: {action_key} (aliases: {aliases})\n"));
-    }
-
-    let prompt = format!(
-        r#"You are an intent resolver. Extract provider/workspace/action from the user request.
-
-Available entries:
-{options}
-User request: "{text}"
-
-Respond with ONLY valid JSON on a single line. No markdown, no explanation.
-Fields: provider (string|null), workspace (string|null), action (string|null), confidence (0.0-1.0), reason (string).
-Example: {{"provider":"linear","workspace":"SAM","action":"create_tickets","confidence":0.95,"reason":"matched all fields"}}"#
-    );
+    let output = resolver_ai_output(resolver, config, abort_signal, text).await?;
+    Ok(resolver.parse_ai_output(&output))
+}
 
+/// The raw LLM answer to the resolver prompt for `text`.
+pub async fn resolver_ai_output(
+    resolver: &Resolver,
+    config: &GlobalConfig,
+    abort_signal: AbortSignal,
+    text: &str,
+) -> Result<String> {
     // Bare role: uses global model, no session, no tools.
     let model = config.read().model.clone();
     let mut ai_role = Role::new("__resolver__", "");
     ai_role.batch_set(&model, None, None, Some("none".to_string()));
-    let input = Input::from_str(config, &prompt, Some(ai_role));
+    let input = Input::from_str(config, &resolver.ai_prompt(text), Some(ai_role));
     let client = input.create_client()?;
     let (output, _) =
         call_chat_completions(&input, false, false, client.as_ref(), abort_signal).await?;
-
-    let json_str = match extract_json_object(&output) {
-        Some(s) => s,
-        None => return Ok(None),
-    };
-
-    #[derive(serde::Deserialize)]
-    struct AiOut {
-        provider: Option<String>,
-        workspace: Option<String>,
-        action: Option<String>,
-        confidence: f32,
-        reason: String,
-    }
-    let out: AiOut = match serde_json::from_str(json_str) {
-        Ok(v) => v,
-        Err(_) => return Ok(None),
-    };
-
-    const AI_THRESHOLD: f32 = 0.70;
-    if out.confidence < AI_THRESHOLD {
-        return Ok(None);
-    }
-    let Some(provider) = out.provider else {
-        return Ok(None);
-    };
-    let intent = ResolvedIntent {
-        provider,
-        workspace: out.workspace,
-        target_profile: None,
-        action: out.action,
-        confidence: out.confidence,
-        reason: format!("AI: {}", out.reason),
-    };
-
-    let Some(intent) = resolver.validate_ai_intent(intent) else {
-        return Ok(None);
-    };
-    if intent.confidence < AI_THRESHOLD {
-        return Ok(None);
-    }
-    Ok(Some(intent))
+    Ok(output)
 }
 
 fn scoped_use_tools(config: &GlobalConfig, intent: &ResolvedIntent) -> Option<String> {
@@ -397,20 +535,56 @@ fn scoped_tool_names_for_profile<'a>(
 mod tests {
     use super::*;
 
+    fn default_config() -> GlobalConfig {
+        std::sync::Arc::new(parking_lot::RwLock::new(crate::config::Config::default()))
+    }
+
     #[test]
     fn operational_prompt_is_detected() {
-        assert!(looks_operational_prompt("git commit and push the changes"));
-        assert!(looks_operational_prompt(
-            "can you restart nginx and tail logs"
-        ));
+        let config = default_config();
+        for text in [
+            "git commit and push the changes",
+            "can you restart nginx and tail logs",
+        ] {
+            assert_eq!(apply_rules(&config, text, None).policy, TurnPolicy::Plan);
+        }
     }
 
     #[test]
     fn explanatory_prompt_is_not_operational() {
-        assert!(!looks_operational_prompt(
-            "how do I commit and push safely?"
-        ));
-        assert!(!looks_operational_prompt("explain git rebase"));
+        let config = default_config();
+        for text in ["how do I commit and push safely?", "explain git rebase"] {
+            let route = apply_rules(&config, text, None);
+            assert_eq!(route.policy, TurnPolicy::Chat);
+            assert_eq!(route.rule, "explanatory");
+        }
+    }
+
+    #[test]
+    fn intent_rule_scopes_route_and_keeps_intent() {
+        let config = default_config();
+        config.write().model_thinking = Some("openai:o1".to_string());
+        let intent = ResolvedIntent {
+            provider: "linear".to_string(),
+            workspace: Some("JOON-ACA".to_string()),
+            target_profile: Some("linear-joon-aca".to_string()),
+            action: Some("connect_workspace".to_string()),
+            confidence: 1.0,
+            reason: "test".to_string(),
+        };
+        let route = apply_rules(&config, "connect to linear joon-aca", Some(intent));
+        assert_eq!(route.rule, "resolved-intent");
+        assert_eq!(route.model_id.as_deref(), Some("openai:o1"));
+        assert!(route.text.starts_with("[Resolver: provider=linear"));
+        assert!(route.intent.is_some());
+        assert!(route.operation.is_some());
+
+        let explained = explain_route(&config, "git status");
+        assert!(
+            explained.contains("rule:       #3 shell-command"),
+            "{explained}"
+        );
+        assert!(explained.contains("policy:     plan"), "{explained}");
     }
 
     #[test]
@@ -503,7 +677,7 @@ mod tests {
 
     #[test]
     fn apply_linear_profile_default_sets_missing_target_profile() {
-        let config = std::sync::Arc::new(parking_lot::RwLock::new(crate::config::Config::default()));
+        let config = default_config();
         config
             .write()
             .set_current_linear_profile(Some("linear-risk-flow".to_string()));
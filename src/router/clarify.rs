@@ -0,0 +1,145 @@
+//! Disambiguation when neither the resolver nor its AI fallback can decide what a turn
+//! is about. The user is offered the most likely provider/workspace/action combinations
+//! (see `Resolver::candidates`); what they pick is learned, so the same phrasing
+//! resolves on its own next time.
+//!
+//! The REPL and one-shot prompts ask right away with a select list. Serve-mode surfaces
+//! get the [`Clarification`] on the route instead and answer it later with
+//! [`Clarification::pick`].
+
+use crate::resolver::{ResolvedIntent, Resolver};
+
+use anyhow::Result;
+use inquire::Select;
+use serde::Serialize;
+
+/// How many candidates are offered.
+pub const MAX_OPTIONS: usize = 5;
+
+const NONE_OF_THESE: &str = "None of these";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Clarification {
+    /// The request being clarified, as typed.
+    pub input: String,
+    pub question: String,
+    /// Candidates, best first.
+    pub options: Vec<ResolvedIntent>,
+}
+
+impl Clarification {
+    pub fn new(input: &str, options: Vec<ResolvedIntent>) -> Self {
+        Self {
+            input: input.to_string(),
+            question: "Which provider / workspace / action do you mean?".to_string(),
+            options,
+        }
+    }
+
+    /// What `resolver` offers for `text`.
+    pub fn offered(resolver: &Resolver, text: &str) -> Self {
+        Self::new(text, resolver.candidates(text, MAX_OPTIONS))
+    }
+
+    /// Ask on the terminal. `None` when the user picks none or dismisses the prompt.
+    pub async fn ask(&self) -> Result<Option<ResolvedIntent>> {
+        let question = self.question.clone();
+        let mut labels: Vec<String> = self.options.iter().map(|v| v.label()).collect();
+        labels.push(NONE_OF_THESE.to_string());
+        let choice = tokio::task::spawn_blocking(move || {
+            Select::new(&question, labels)
+                .with_help_message("The pick is learned for this phrasing")
+                .raw_prompt()
+                .ok()
+                .map(|v| v.index)
+        })
+        .await?;
+        Ok(choice.and_then(|index| self.option(index)))
+    }
+
+    /// The option a chat reply picks by its number, e.g. `2`.
+    pub fn pick(&self, reply: &str) -> Option<ResolvedIntent> {
+        let number: usize = reply.trim().trim_end_matches('.').parse().ok()?;
+        self.option(number.checked_sub(1)?)
+    }
+
+    /// The option an API client posts back by its fields; names compare case-insensitively.
+    pub fn pick_fields(
+        &self,
+        provider: &str,
+        workspace: Option<&str>,
+        action: Option<&str>,
+    ) -> Option<ResolvedIntent> {
+        let same = |a: Option<&str>, b: Option<&str>| match (a, b) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b,
+        };
+        let index = self.options.iter().position(|v| {
+            v.provider.eq_ignore_ascii_case(provider)
+                && same(v.workspace.as_deref(), workspace)
+                && same(v.action.as_deref(), action)
+        })?;
+        self.option(index)
+    }
+
+    /// The question and numbered options, for chat platforms.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!("Not sure what \"{}\" is about.", self.input)];
+        lines.push(format!("{} Reply with a number:", self.question));
+        for (i, option) in self.options.iter().enumerate() {
+            lines.push(format!("{}. {}", i + 1, option.label()));
+        }
+        lines.join("\n")
+    }
+
+    fn option(&self, index: usize) -> Option<ResolvedIntent> {
+        let mut intent = self.options.get(index)?.clone();
+        intent.confidence = 1.0;
+        intent.reason = "picked by the user".to_string();
+        Some(intent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(workspace: &str) -> ResolvedIntent {
+        ResolvedIntent {
+            provider: "linear".into(),
+            workspace: Some(workspace.into()),
+            target_profile: None,
+            action: Some("create_tickets".into()),
+            confidence: 0.65,
+            reason: "candidate".into(),
+        }
+    }
+
+    #[test]
+    fn replies_pick_options_by_number() {
+        let clarification =
+            Clarification::new("linear tickets", vec![intent("OPS"), intent("SAM")]);
+        let picked = clarification.pick(" 2. ").unwrap();
+        assert_eq!(picked.workspace.as_deref(), Some("SAM"));
+        assert_eq!(picked.confidence, 1.0);
+        assert!(clarification.pick("0").is_none());
+        assert!(clarification.pick("3").is_none());
+        assert!(clarification.pick("the second one").is_none());
+        assert!(clarification
+            .to_text()
+            .ends_with("1. linear / OPS / create_tickets\n2. linear / SAM / create_tickets"));
+    }
+
+    #[test]
+    fn api_picks_must_be_offered() {
+        let clarification = Clarification::new("linear tickets", vec![intent("OPS")]);
+        let picked = clarification.pick_fields("Linear", Some("ops"), Some("create_tickets"));
+        assert_eq!(picked.unwrap().workspace.as_deref(), Some("OPS"));
+        assert!(clarification
+            .pick_fields("linear", Some("SAM"), Some("create_tickets"))
+            .is_none());
+        assert!(clarification
+            .pick_fields("linear", Some("OPS"), None)
+            .is_none());
+    }
+}
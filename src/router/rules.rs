@@ -0,0 +1,325 @@
+//! Data-driven turn routing rules, loaded from `routing.yaml` in the config directory.
+//!
+//! Rules are evaluated in order and the first match decides the turn's policy, model
+//! slot, tool scope and whether confirmation is forced. Without a file the built-in
+//! rules in `default_rules.yaml` apply, which mirror the original keyword heuristics.
+
+use super::TurnPolicy;
+use crate::function::glob_match;
+use crate::resolver::types::word_boundary_match;
+use crate::resolver::ResolvedIntent;
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_RULES: &str = include_str!("default_rules.yaml");
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingRules {
+    #[serde(default)]
+    pub classifiers: IndexMap<String, Classifier>,
+    pub rules: Vec<RoutingRule>,
+    /// The file the rules came from; `None` for the built-in rules.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Classifier {
+    pub features: Vec<ClassifierFeature>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassifierFeature {
+    pub weight: f32,
+    pub terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingRule {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub matcher: RuleMatch,
+    pub route: RuleRoute,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleMatch {
+    pub regex: Option<String>,
+    pub intent: Option<IntentMatch>,
+    pub role: Option<String>,
+    pub agent: Option<String>,
+    pub classifier: Option<ClassifierMatch>,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntentMatch {
+    pub provider: Option<String>,
+    pub workspace: Option<String>,
+    pub action: Option<String>,
+    pub min_confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassifierMatch {
+    pub name: String,
+    pub min: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleRoute {
+    pub policy: TurnPolicy,
+    pub model: Option<ModelSlot>,
+    /// Tools for the turn; `intent` scopes them to the resolved intent's MCP profile.
+    pub use_tools: Option<String>,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelSlot {
+    Fast,
+    Thinking,
+    /// Keep whatever model is active.
+    Current,
+}
+
+/// What a turn is matched against.
+pub struct RuleContext<'a> {
+    pub text: &'a str,
+    pub intent: Option<&'a ResolvedIntent>,
+    pub role: Option<&'a str>,
+    pub agent: Option<&'a str>,
+}
+
+impl Default for RoutingRules {
+    fn default() -> Self {
+        Self::parse(DEFAULT_RULES).expect("built-in routing rules should be valid")
+    }
+}
+
+impl RoutingRules {
+    /// Load rules from `path`, or the built-in rules when it does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read routing rules '{}'", path.display()))?;
+        let mut rules = Self::parse(&data)
+            .with_context(|| format!("Invalid routing rules at '{}'", path.display()))?;
+        rules.source = Some(path.to_path_buf());
+        Ok(rules)
+    }
+
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut rules: Self = serde_yaml::from_str(data)?;
+        for rule in &mut rules.rules {
+            if rule.route.policy == TurnPolicy::Auto {
+                bail!("Rule '{}': policy must be chat, plan or execute", rule.name);
+            }
+            if let Some(regex) = &rule.matcher.regex {
+                let compiled = RegexBuilder::new(regex)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Rule '{}': invalid regex", rule.name))?;
+                rule.matcher.compiled = Some(compiled);
+            }
+            if let Some(classifier) = &rule.matcher.classifier {
+                if !rules.classifiers.contains_key(&classifier.name) {
+                    bail!(
+                        "Rule '{}': unknown classifier '{}'",
+                        rule.name,
+                        classifier.name
+                    );
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// The first matching rule and its position.
+    pub fn evaluate(&self, ctx: &RuleContext) -> Option<(usize, &RoutingRule)> {
+        let text = ctx.text.trim();
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| self.matches(&rule.matcher, text, ctx))
+    }
+
+    /// Score `text` with a named classifier: the sum of the weights of the features
+    /// with a term present, capped at 1.
+    pub fn score(&self, name: &str, text: &str) -> Option<f32> {
+        let classifier = self.classifiers.get(name)?;
+        let text = text.trim().to_lowercase();
+        let score: f32 = classifier
+            .features
+            .iter()
+            .filter(|feature| {
+                feature
+                    .terms
+                    .iter()
+                    .any(|term| word_boundary_match(&text, &term.to_lowercase()))
+            })
+            .map(|feature| feature.weight)
+            .sum();
+        Some(score.min(1.0))
+    }
+
+    fn matches(&self, matcher: &RuleMatch, text: &str, ctx: &RuleContext) -> bool {
+        if let Some(regex) = &matcher.compiled {
+            if !regex.is_match(text) {
+                return false;
+            }
+        }
+        if let Some(want) = &matcher.intent {
+            let Some(intent) = ctx.intent else {
+                return false;
+            };
+            if !want.matches(intent) {
+                return false;
+            }
+        }
+        for (pattern, value) in [(&matcher.role, ctx.role), (&matcher.agent, ctx.agent)] {
+            if let Some(pattern) = pattern {
+                if !value.is_some_and(|v| glob_match(v, pattern)) {
+                    return false;
+                }
+            }
+        }
+        if let Some(classifier) = &matcher.classifier {
+            if self.score(&classifier.name, text).unwrap_or(0.0) < classifier.min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl IntentMatch {
+    fn matches(&self, intent: &ResolvedIntent) -> bool {
+        let field = |pattern: &Option<String>, value: Option<&str>| match pattern {
+            Some(pattern) => {
+                value.is_some_and(|v| glob_match(&v.to_lowercase(), &pattern.to_lowercase()))
+            }
+            None => true,
+        };
+        field(&self.provider, Some(&intent.provider))
+            && field(&self.workspace, intent.workspace.as_deref())
+            && field(&self.action, intent.action.as_deref())
+            && self
+                .min_confidence
+                .is_none_or(|min| intent.confidence >= min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(text: &str) -> RuleContext<'_> {
+        RuleContext {
+            text,
+            intent: None,
+            role: None,
+            agent: None,
+        }
+    }
+
+    fn fired<'a>(rules: &'a RoutingRules, ctx: &RuleContext) -> &'a str {
+        rules.evaluate(ctx).map(|(_, v)| v.name.as_str()).unwrap()
+    }
+
+    #[test]
+    fn default_rules_mirror_heuristics() {
+        let rules = RoutingRules::default();
+        assert_eq!(
+            fired(&rules, &ctx("git commit and push the changes")),
+            "shell-command"
+        );
+        assert_eq!(
+            fired(&rules, &ctx("can you restart nginx and tail logs")),
+            "operational-request"
+        );
+        assert_eq!(
+            fired(&rules, &ctx("How do I commit and push safely?")),
+            "explanatory"
+        );
+        assert_eq!(fired(&rules, &ctx("deploy")), "operational-verb");
+        assert_eq!(fired(&rules, &ctx("tell me a joke")), "explanatory");
+        assert_eq!(fired(&rules, &ctx("the restart button")), "default");
+        assert_eq!(rules.score("operational", "please restart it"), Some(1.0));
+    }
+
+    #[test]
+    fn rules_match_intent_role_and_agent() {
+        let rules = RoutingRules::parse(
+            r#"
+rules:
+  - name: linear-writes
+    match:
+      intent: { provider: linear, action: "create_*", min_confidence: 0.8 }
+    route: { policy: execute, confirm: true }
+  - name: coder
+    match: { role: "coder*", regex: '\bfix\b' }
+    route: { policy: plan, model: current }
+  - name: agent
+    match: { agent: "*" }
+    route: { policy: chat, use_tools: "fs_*" }
+"#,
+        )
+        .unwrap();
+        let intent = ResolvedIntent {
+            provider: "linear".to_string(),
+            workspace: Some("SAM".to_string()),
+            target_profile: None,
+            action: Some("create_tickets".to_string()),
+            confidence: 0.9,
+            reason: "test".to_string(),
+        };
+        let mut c = ctx("file a ticket");
+        c.intent = Some(&intent);
+        let (index, rule) = rules.evaluate(&c).unwrap();
+        assert_eq!((index, rule.name.as_str()), (0, "linear-writes"));
+        assert!(rule.route.confirm);
+
+        let mut c = ctx("Fix the build");
+        c.role = Some("coder-rust");
+        assert_eq!(fired(&rules, &c), "coder");
+        c.role = Some("writer");
+        assert!(rules.evaluate(&c).is_none());
+        c.agent = Some("todo");
+        assert_eq!(fired(&rules, &c), "agent");
+    }
+
+    #[test]
+    fn parse_rejects_invalid_rules() {
+        let err =
+            RoutingRules::parse("rules:\n  - name: x\n    route: { policy: auto }\n").unwrap_err();
+        assert!(err.to_string().contains("policy must be"), "{err}");
+        let err = RoutingRules::parse(
+            "rules:\n  - name: x\n    match: { regex: '(' }\n    route: { policy: chat }\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid regex"), "{err}");
+        let err = RoutingRules::parse(
+            "rules:\n  - name: x\n    match: { classifier: { name: nope, min: 1 } }\n    route: { policy: chat }\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown classifier"), "{err}");
+    }
+}
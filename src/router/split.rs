@@ -0,0 +1,219 @@
+//! Splitting compound turns ("restart nginx and then open a Linear ticket in SAM") into
+//! the requests they hold, so each is resolved and routed on its own.
+//!
+//! A turn is only split when at least one of its parts resolves to an intent by itself;
+//! otherwise "pull, then rebase" stays one shell turn. Within such a turn sequencing words
+//! split, while a bare "and" only splits when what follows it resolves, so "restart nginx
+//! and tail logs" stays together. Nothing inside quotes or backticks is split, and a part
+//! that refers back ("... about it") stays with the part before it, since it would run
+//! without knowing what "it" is.
+
+use std::cmp::Reverse;
+
+/// Lowercase separators that start a new request in a turn holding an intent.
+const SEQUENCE_MARKERS: &[&str] = &[
+    " and then ",
+    ", and then ",
+    ", then ",
+    "; then ",
+    ". then ",
+    " and after that ",
+    ", after that ",
+    ". after that ",
+    " and afterwards ",
+    ", afterwards ",
+];
+
+/// Separators that split only before a part that resolves on its own.
+const CONJUNCTIONS: &[&str] = &[" and ", ", and "];
+
+/// Lowercase words that refer to something earlier in the turn.
+const BACK_REFERENCES: &[&str] = &["it", "its", "that", "this", "them", "those", "these"];
+
+/// The requests in `text`, in order; `[text]` when it holds only one. `is_intent` says
+/// whether a part resolves to an intent without help from the rest of the turn.
+pub fn split_intents(text: &str, is_intent: impl Fn(&str) -> bool) -> Vec<String> {
+    let lower = text.to_ascii_lowercase();
+    let quoted = quoted_mask(text);
+    let find = |patterns: &[&str]| {
+        let mut found: Vec<(usize, usize)> = patterns
+            .iter()
+            .flat_map(|pattern| {
+                lower
+                    .match_indices(pattern)
+                    .map(|(start, v)| (start, start + v.len()))
+            })
+            .filter(|(start, _)| !quoted[*start])
+            .collect();
+        // The longest separator at each position, without overlaps.
+        found.sort_by_key(|(start, end)| (*start, Reverse(*end)));
+        let mut cuts: Vec<(usize, usize)> = vec![];
+        for (start, end) in found {
+            if cuts.last().is_none_or(|(_, last_end)| start >= *last_end) {
+                cuts.push((start, end));
+            }
+        }
+        cuts
+    };
+
+    let mut cuts = find(SEQUENCE_MARKERS);
+    // Right to left, so each conjunction is judged by the part up to the next cut.
+    for (start, end) in find(CONJUNCTIONS).into_iter().rev() {
+        if cuts.iter().any(|(s, e)| start < *e && *s < end) {
+            continue;
+        }
+        let next = cuts
+            .iter()
+            .map(|(s, _)| *s)
+            .filter(|s| *s >= end)
+            .min()
+            .unwrap_or(text.len());
+        if is_intent(text[end..next].trim()) {
+            cuts.push((start, end));
+        }
+    }
+    cuts.sort();
+    // A part that refers back ("open a ticket about it") stays with the one before it.
+    let nexts: Vec<usize> = cuts
+        .iter()
+        .skip(1)
+        .map(|(s, _)| *s)
+        .chain([text.len()])
+        .collect();
+    let cuts: Vec<(usize, usize)> = cuts
+        .into_iter()
+        .zip(nexts)
+        .filter(|((_, end), next)| !refers_back(&text[*end..*next]))
+        .map(|(cut, _)| cut)
+        .collect();
+
+    let mut parts = vec![];
+    let mut from = 0;
+    for (start, end) in cuts.into_iter().chain([(text.len(), text.len())]) {
+        let part = text[from..start].trim_matches(|ch: char| ch.is_whitespace() || ch == ',');
+        if !part.is_empty() {
+            parts.push(part.to_string());
+        }
+        from = end;
+    }
+    if parts.len() <= 1 || !parts.iter().any(|v| is_intent(v)) {
+        return vec![text.to_string()];
+    }
+    parts
+}
+
+/// Whether `part` mentions something from earlier in the turn, e.g. "about it".
+fn refers_back(part: &str) -> bool {
+    part.split(|ch: char| !ch.is_ascii_alphanumeric() && ch != '\'')
+        .any(|word| BACK_REFERENCES.contains(&word.to_ascii_lowercase().as_str()))
+}
+
+/// Whether each byte of `text` is inside quotes or backticks. A single quote only opens
+/// at the start of a word and closes at its end, so apostrophes ("don't") are text.
+fn quoted_mask(text: &str) -> Vec<bool> {
+    let bytes = text.as_bytes();
+    let is_word = |i: Option<usize>| {
+        i.and_then(|i| bytes.get(i))
+            .is_some_and(|b| b.is_ascii_alphanumeric())
+    };
+    let mut open: Option<u8> = None;
+    (0..bytes.len())
+        .map(|i| {
+            let b = bytes[i];
+            let inside = open.is_some();
+            match open {
+                Some(b'\'') if b == b'\'' && !is_word(Some(i + 1)) => open = None,
+                Some(quote) if quote == b && quote != b'\'' => open = None,
+                None if b == b'"' || b == b'`' => open = Some(b),
+                None if b == b'\'' && !is_word(i.checked_sub(1)) => open = Some(b),
+                _ => {}
+            }
+            inside
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<String> {
+        split_intents(text, |part| part.to_lowercase().contains("linear"))
+    }
+
+    #[test]
+    fn sequencing_words_split_turns_holding_an_intent() {
+        assert_eq!(
+            split("restart nginx and then open a Linear ticket in SAM"),
+            ["restart nginx", "open a Linear ticket in SAM"]
+        );
+        assert_eq!(
+            split("pull main, then deploy. After that list linear issues"),
+            ["pull main", "deploy", "list linear issues"]
+        );
+        assert_eq!(
+            split("pull main; run the tests, then deploy. After that tail the logs"),
+            ["pull main; run the tests, then deploy. After that tail the logs"]
+        );
+        assert_eq!(
+            split("if the build fails then roll back"),
+            ["if the build fails then roll back"]
+        );
+    }
+
+    #[test]
+    fn bare_and_splits_only_before_an_intent() {
+        assert_eq!(
+            split("restart nginx and tail logs"),
+            ["restart nginx and tail logs"]
+        );
+        assert_eq!(
+            split("restart nginx and tail logs and open a linear ticket in SAM"),
+            ["restart nginx and tail logs", "open a linear ticket in SAM"]
+        );
+        assert_eq!(
+            split("create linear tickets for SAM and OPS"),
+            ["create linear tickets for SAM and OPS"]
+        );
+    }
+
+    #[test]
+    fn quoted_text_is_never_split() {
+        assert_eq!(
+            split("run `make; make install` and then list linear issues"),
+            ["run `make; make install`", "list linear issues"]
+        );
+        assert_eq!(
+            split(r#"commit with message "fix a; and then b""#),
+            [r#"commit with message "fix a; and then b""#]
+        );
+        assert_eq!(
+            split("commit -m 'fix a and then linear b' and then list linear issues"),
+            ["commit -m 'fix a and then linear b'", "list linear issues"]
+        );
+        assert_eq!(
+            split("don't restart nginx and then open a linear ticket"),
+            ["don't restart nginx", "open a linear ticket"]
+        );
+        assert_eq!(split("and then"), ["and then"]);
+    }
+
+    #[test]
+    fn parts_referring_back_stay_together() {
+        assert_eq!(
+            split("restart nginx and then open a Linear ticket in SAM about it"),
+            ["restart nginx and then open a Linear ticket in SAM about it"]
+        );
+        assert_eq!(
+            split("list linear issues, then close those that are done and then deploy"),
+            [
+                "list linear issues, then close those that are done",
+                "deploy"
+            ]
+        );
+        assert_eq!(
+            split("deploy and then list linear issues. After that restart nginx"),
+            ["deploy", "list linear issues", "restart nginx"]
+        );
+    }
+}
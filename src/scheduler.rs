@@ -0,0 +1,554 @@
+//! Scheduled jobs for `fio daemon` and `--serve`.
+//!
+//! Each job runs a prompt or macro on a cron schedule with the same tool restrictions as
+//! alert triage, records the run, and delivers the output when it differs from the
+//! previous run.
+
+use crate::client::call_chat_completions_headless;
+use crate::config::{ensure_parent_exists, macro_run, Config, GlobalConfig, Input, Macro, Role};
+use crate::notify::{deliver, DeliveryConfig, NotifySinks};
+use crate::utils::{create_abort_signal, new_turn_id, with_turn_id, AbortSignal};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use cron::Schedule;
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::LazyLock,
+};
+use tokio::task::JoinHandle;
+
+const JOBS_DIR_NAME: &str = "jobs";
+/// Older runs are dropped from a job's record file beyond this many.
+const MAX_RECORDED_RUNS: usize = 100;
+const DEFAULT_MESSAGE: &str = "[{{job}}] {{status}}\n\n{{output}}";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JobConfig {
+    /// Cron expression (`min hour day month weekday`, optionally with leading seconds) or `@daily`.
+    pub schedule: String,
+    pub enabled: bool,
+    /// Prompt sent to the model; `{{job}}`, `{{date}}` and `{{previous}}` are interpolated.
+    pub prompt: Option<String>,
+    /// Macro to run instead of a prompt; its last reply is the job output.
+    #[serde(rename = "macro")]
+    pub macro_name: Option<String>,
+    pub macro_args: Option<String>,
+    pub role: Option<String>,
+    pub agent: Option<String>,
+    /// Tools offered to the model; defaults to the read-only built-in tools.
+    pub use_tools: Option<String>,
+    /// Extra tools (patterns) the job may call without confirmation.
+    pub allowed_tools: Vec<String>,
+    pub notify_on: NotifyOn,
+    pub output: DeliveryConfig,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            schedule: String::new(),
+            enabled: true,
+            prompt: None,
+            macro_name: None,
+            macro_args: None,
+            role: None,
+            agent: None,
+            use_tools: None,
+            allowed_tools: vec![],
+            notify_on: NotifyOn::default(),
+            output: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    /// Deliver only when the output differs from the previous run.
+    #[default]
+    Change,
+    Always,
+    /// Only record the run.
+    Never,
+}
+
+impl JobConfig {
+    pub fn validate(&self) -> Result<Schedule> {
+        match (&self.prompt, &self.macro_name) {
+            (Some(_), Some(_)) => bail!("Set either 'prompt' or 'macro', not both"),
+            (None, None) => bail!("Missing 'prompt' or 'macro'"),
+            (None, Some(_)) if self.agent.is_some() => {
+                bail!("'agent' cannot be combined with 'macro'")
+            }
+            _ => {}
+        }
+        parse_schedule(&self.schedule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct JobRun {
+    pub job: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub success: bool,
+    pub output: String,
+    pub changed: bool,
+}
+
+/// Parse a cron expression; classic five-field crontab lines run at second zero.
+pub fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let expr = expr.trim();
+    if expr.is_empty() {
+        bail!("Missing 'schedule'");
+    }
+    let normalized = if !expr.starts_with('@') && expr.split_whitespace().count() == 5 {
+        format!("0 {expr}")
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&normalized).map_err(|err| anyhow!("Invalid schedule '{expr}': {err}"))
+}
+
+/// Start a background loop for every enabled job. Invalid jobs are reported and skipped.
+pub fn spawn(config: &GlobalConfig) -> Vec<JoinHandle<()>> {
+    let jobs = config.read().jobs.clone();
+    let mut handles = vec![];
+    for (name, job) in jobs {
+        if !job.enabled {
+            continue;
+        }
+        let schedule = match job.validate() {
+            Ok(schedule) => schedule,
+            Err(err) => {
+                error!("Skipping job '{name}': {err}");
+                continue;
+            }
+        };
+        let store = RunStore::new(&Config::local_path(JOBS_DIR_NAME), &name);
+        info!("Scheduled job '{name}' ({})", job.schedule);
+        handles.push(tokio::spawn(job_loop(
+            config.clone(),
+            name,
+            job,
+            schedule,
+            store,
+        )));
+    }
+    handles
+}
+
+async fn job_loop(
+    config: GlobalConfig,
+    name: String,
+    job: JobConfig,
+    schedule: Schedule,
+    store: RunStore,
+) {
+    while let Some(next) = schedule.upcoming(Local).next() {
+        let wait = (next - Local::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+        // Runs are awaited in place, so a slow job skips ticks instead of overlapping.
+        match with_turn_id(new_turn_id(), run_job(&config, &name, &job, &store)).await {
+            Ok(run) if run.success => info!("Job '{name}' finished (changed: {})", run.changed),
+            Ok(run) => error!("Job '{name}' failed: {}", run.output),
+            Err(err) => error!("Job '{name}' could not be recorded: {err}"),
+        }
+    }
+}
+
+/// Run a job once, record it and deliver the output if needed.
+pub async fn run_job(
+    config: &GlobalConfig,
+    name: &str,
+    job: &JobConfig,
+    store: &RunStore,
+) -> Result<JobRun> {
+    let started_at = Utc::now();
+    let previous = store.last()?;
+    let result = execute(config, name, job, previous.as_ref()).await;
+    let sinks = config.read().notify_sinks.clone();
+    finish_run(&sinks, store, name, job, previous, started_at, result).await
+}
+
+async fn execute(
+    config: &GlobalConfig,
+    name: &str,
+    job: &JobConfig,
+    previous: Option<&JobRun>,
+) -> Result<String> {
+    let abort_signal = create_abort_signal();
+    let (config, role) = job_config(config, job, abort_signal.clone()).await?;
+    if let Some(macro_name) = &job.macro_name {
+        config.write().use_role_obj(role)?;
+        let config =
+            macro_run(&config, macro_name, job.macro_args.as_deref(), abort_signal).await?;
+        let output = config
+            .read()
+            .last_message
+            .as_ref()
+            .map(|v| v.output.clone())
+            .unwrap_or_default();
+        return Ok(output.trim().to_string());
+    }
+    let mut variables = IndexMap::new();
+    variables.insert("job".to_string(), name.to_string());
+    variables.insert("date".to_string(), Local::now().to_rfc3339());
+    variables.insert(
+        "previous".to_string(),
+        previous.map(|v| v.output.clone()).unwrap_or_default(),
+    );
+    let prompt = Macro::interpolate_command(job.prompt.as_deref().unwrap_or_default(), &variables);
+    let input = Input::from_str(&config, &prompt, Some(role));
+    let output = call_chat_completions_headless(input, abort_signal).await?;
+    Ok(output.trim().to_string())
+}
+
+/// The unattended config and role a job runs with: read-only built-ins plus its
+/// `allowed_tools`, never prompting.
+async fn job_config(
+    config: &GlobalConfig,
+    job: &JobConfig,
+    abort_signal: AbortSignal,
+) -> Result<(GlobalConfig, Role)> {
+    Config::init_unattended(
+        config,
+        job.role.as_deref(),
+        job.agent.as_deref(),
+        job.use_tools.as_deref(),
+        &job.allowed_tools,
+        abort_signal,
+    )
+    .await
+}
+
+async fn finish_run(
+    sinks: &NotifySinks,
+    store: &RunStore,
+    name: &str,
+    job: &JobConfig,
+    previous: Option<JobRun>,
+    started_at: DateTime<Utc>,
+    result: Result<String>,
+) -> Result<JobRun> {
+    let (success, output) = match result {
+        Ok(output) => (true, output),
+        Err(err) => (false, format!("{err:#}")),
+    };
+    let changed = previous.as_ref().is_none_or(|v| {
+        v.success != success || normalize_output(&v.output) != normalize_output(&output)
+    });
+    let run = JobRun {
+        job: name.to_string(),
+        started_at: started_at.to_rfc3339(),
+        finished_at: Utc::now().to_rfc3339(),
+        success,
+        output,
+        changed,
+    };
+    store.append(&run)?;
+
+    let notify = match job.notify_on {
+        NotifyOn::Always => true,
+        NotifyOn::Change => changed,
+        NotifyOn::Never => false,
+    };
+    if notify {
+        let variables = run_variables(&run, previous.as_ref());
+        if let Err(err) = deliver(sinks, &job.output, variables, DEFAULT_MESSAGE).await {
+            error!("Failed to deliver job '{name}' output: {err}");
+        }
+    }
+    Ok(run)
+}
+
+/// Timestamps and other numbers, e.g. `2024-01-01T08:00:00Z`, `12:03`, `91.5`.
+static RE_NUMBERS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d+(?:[-:./T]\d+)*(?:Z|[+-]\d{2}:?\d{2})?").unwrap());
+
+/// The form job outputs are compared in: a rerun whose output only differs in case,
+/// spacing, timestamps or counts is not a change worth notifying about.
+fn normalize_output(output: &str) -> String {
+    let output = RE_NUMBERS.replace_all(output, "#").to_lowercase();
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn run_variables(run: &JobRun, previous: Option<&JobRun>) -> IndexMap<String, String> {
+    let previous_output = previous.map(|v| v.output.as_str()).unwrap_or_default();
+    let diff = TextDiff::from_lines(previous_output, &run.output)
+        .unified_diff()
+        .context_radius(2)
+        .header("previous", "current")
+        .to_string();
+    let mut variables = IndexMap::new();
+    for (key, value) in [
+        ("job", run.job.clone()),
+        (
+            "status",
+            if run.success { "ok" } else { "failed" }.to_string(),
+        ),
+        ("output", run.output.clone()),
+        ("previous", previous_output.to_string()),
+        ("diff", diff),
+        ("changed", run.changed.to_string()),
+        ("started_at", run.started_at.clone()),
+        ("finished_at", run.finished_at.clone()),
+        (
+            "previous_at",
+            previous.map(|v| v.finished_at.clone()).unwrap_or_default(),
+        ),
+    ] {
+        variables.insert(key.to_string(), value);
+    }
+    variables
+}
+
+/// Run records of one job, one JSON object per line.
+#[derive(Debug, Clone)]
+pub struct RunStore {
+    path: PathBuf,
+}
+
+impl RunStore {
+    pub fn new(dir: &Path, job: &str) -> Self {
+        Self {
+            path: dir.join(format!("{job}.jsonl")),
+        }
+    }
+
+    pub fn runs(&self) -> Result<Vec<JobRun>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn last(&self) -> Result<Option<JobRun>> {
+        Ok(self.runs()?.pop())
+    }
+
+    pub fn append(&self, run: &JobRun) -> Result<()> {
+        ensure_parent_exists(&self.path)?;
+        let runs = self.runs()?;
+        if runs.len() >= MAX_RECORDED_RUNS {
+            let keep = &runs[runs.len() + 1 - MAX_RECORDED_RUNS..];
+            let mut content = String::new();
+            for item in keep.iter().chain([run]) {
+                content.push_str(&serde_json::to_string(item)?);
+                content.push('\n');
+            }
+            fs::write(&self.path, content)
+                .with_context(|| format!("Failed to write {}", self.path.display()))?;
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(run)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fio-jobs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = parse_schedule("30 8 * * *").unwrap();
+        let next = schedule.upcoming(Utc).next().unwrap();
+        assert_eq!(next.format("%H:%M:%S").to_string(), "08:30:00");
+        assert!(parse_schedule("0 */5 * * * *").is_ok());
+        assert!(parse_schedule("@daily").is_ok());
+        assert!(parse_schedule("").is_err());
+        assert!(parse_schedule("every morning").is_err());
+    }
+
+    #[test]
+    fn test_validate_job() {
+        let job = JobConfig {
+            schedule: "@hourly".into(),
+            prompt: Some("Summarize failed units".into()),
+            ..Default::default()
+        };
+        assert!(job.validate().is_ok());
+        let both = JobConfig {
+            macro_name: Some("health".into()),
+            ..job.clone()
+        };
+        assert!(both.validate().is_err());
+        let macro_with_agent = JobConfig {
+            prompt: None,
+            macro_name: Some("health".into()),
+            agent: Some("ops".into()),
+            ..job
+        };
+        assert!(macro_with_agent.validate().is_err());
+    }
+
+    #[test]
+    fn test_run_store_keeps_recent_runs() {
+        let dir = temp_dir("store");
+        let store = RunStore::new(&dir, "health");
+        assert!(store.last().unwrap().is_none());
+        for i in 0..MAX_RECORDED_RUNS + 5 {
+            let run = JobRun {
+                job: "health".into(),
+                started_at: String::new(),
+                finished_at: String::new(),
+                success: true,
+                output: format!("run {i}"),
+                changed: true,
+            };
+            store.append(&run).unwrap();
+        }
+        let runs = store.runs().unwrap();
+        assert_eq!(runs.len(), MAX_RECORDED_RUNS);
+        assert_eq!(runs[0].output, "run 5");
+        assert_eq!(
+            store.last().unwrap().unwrap().output,
+            format!("run {}", MAX_RECORDED_RUNS + 4)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_finish_run_notifies_on_change() {
+        let dir = temp_dir("notify");
+        let store = RunStore::new(&dir, "disk");
+        let job = JobConfig {
+            schedule: "@daily".into(),
+            prompt: Some("Check disks".into()),
+            output: DeliveryConfig {
+                file: Some(dir.join("out.log")),
+                message: Some("{{status}}: {{output}}".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let run = |output: Result<String>| {
+            let store = store.clone();
+            let job = job.clone();
+            async move {
+                let previous = store.last().unwrap();
+                let sinks = NotifySinks::new();
+                finish_run(&sinks, &store, "disk", &job, previous, Utc::now(), output)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert!(run(Ok("/ at 91%".into())).await.changed);
+        assert!(!run(Ok("/ at 91%".into())).await.changed);
+        let failed = run(Err(anyhow!("model unavailable"))).await;
+        assert!(failed.changed && !failed.success);
+        assert!(run(Ok("/ at 91%".into())).await.changed);
+
+        assert_eq!(
+            fs::read_to_string(dir.join("out.log")).unwrap(),
+            "ok: / at 91%\nfailed: model unavailable\nok: / at 91%\n"
+        );
+        assert_eq!(store.runs().unwrap().len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_job_can_call_read_only_tools() {
+        use crate::config::RoleLike;
+        use crate::function::{eval_tool_calls, BuiltinTools, Functions, ToolCall};
+
+        let config: GlobalConfig = Default::default();
+        let tools = BuiltinTools::new(&Default::default(), &Default::default());
+        config.write().functions = Functions::default().with_builtin_tools(tools);
+        let (config, role) = job_config(&config, &JobConfig::default(), create_abort_signal())
+            .await
+            .unwrap();
+        assert!(role.use_tools().unwrap().contains("fio_system_info"));
+
+        let call = ToolCall::new("fio_system_info".into(), serde_json::json!({}), None);
+        let results = eval_tool_calls(
+            &config,
+            vec![call],
+            role.tool_call_permission(),
+            role.tool_permissions(),
+        )
+        .await
+        .unwrap();
+        assert!(results[0].output.get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finish_run_ignores_equivalent_output() {
+        let dir = temp_dir("equivalent");
+        let store = RunStore::new(&dir, "health");
+        let job = JobConfig {
+            schedule: "@hourly".into(),
+            prompt: Some("Check health".into()),
+            ..Default::default()
+        };
+        let run = |previous: Option<JobRun>, output: &str| {
+            let sinks = NotifySinks::new();
+            let output = Ok(output.to_string());
+            let (store, job) = (store.clone(), job.clone());
+            async move {
+                finish_run(&sinks, &store, "health", &job, previous, Utc::now(), output)
+                    .await
+                    .unwrap()
+            }
+        };
+        let first = run(
+            None,
+            "All 12 services running.\nChecked at 2024-01-01T08:00:00Z",
+        )
+        .await;
+        let second = run(
+            Some(first),
+            "  all 13 services   running.\n\nChecked at 2024-01-01T09:00:07+00:00\n",
+        )
+        .await;
+        assert!(!second.changed);
+        assert!(run(Some(second), "nginx failed").await.changed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_variables_diff() {
+        let previous = JobRun {
+            job: "disk".into(),
+            started_at: String::new(),
+            finished_at: "2024-01-01T08:00:00Z".into(),
+            success: true,
+            output: "/ 80%\n/home 40%\n".into(),
+            changed: true,
+        };
+        let run = JobRun {
+            output: "/ 91%\n/home 40%\n".into(),
+            ..previous.clone()
+        };
+        let variables = run_variables(&run, Some(&previous));
+        assert_eq!(variables["status"], "ok");
+        assert_eq!(variables["previous_at"], "2024-01-01T08:00:00Z");
+        assert!(variables["diff"].contains("-/ 80%\n+/ 91%\n"));
+    }
+}
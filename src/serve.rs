@@ -1,4 +1,5 @@
-use crate::{client::*, config::*, function::*, rag::*, utils::*};
+use crate::router::{learn_intent, resolve_turn_intent, Clarification, TurnIntent};
+use crate::{client::*, config::*, function::*, hooks::*, rag::*, utils::*};
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
@@ -21,6 +22,7 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Instant,
 };
 use tokio::{
     net::TcpListener,
@@ -33,11 +35,16 @@ use tokio_graceful::Shutdown;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const DEFAULT_MODEL_NAME: &str = "default";
+const REQUEST_ID_HEADER: &str = "x-request-id";
 const PLAYGROUND_HTML: &[u8] = include_bytes!("../assets/playground.html");
 const ARENA_HTML: &[u8] = include_bytes!("../assets/arena.html");
 
 type AppResponse = Response<BoxBody<Bytes, Infallible>>;
 
+/// Response extension carrying the model a request ran against, for metrics.
+#[derive(Debug, Clone)]
+struct ModelLabel(String);
+
 pub async fn run(config: GlobalConfig, addr: Option<String>) -> Result<()> {
     let addr = match addr {
         Some(addr) => {
@@ -52,20 +59,35 @@ pub async fn run(config: GlobalConfig, addr: Option<String>) -> Result<()> {
         None => config.read().serve_addr(),
     };
     let server = Arc::new(Server::new(&config));
+    let jobs = crate::scheduler::spawn(&config);
     let listener = TcpListener::bind(&addr).await?;
     let stop_server = server.run(listener).await?;
     println!("Chat Completions API: http://{addr}/v1/chat/completions");
     println!("Embeddings API:       http://{addr}/v1/embeddings");
     println!("Rerank API:           http://{addr}/v1/rerank");
+    println!("Resolve API:          http://{addr}/v1/resolve");
     println!("LLM Playground:       http://{addr}/playground");
     println!("LLM Arena:            http://{addr}/arena?num=2");
+    println!("Metrics:              http://{addr}/metrics");
+    println!("Health Checks:        http://{addr}/healthz, http://{addr}/readyz");
+    for name in config.read().hooks.keys() {
+        println!("Alert Webhook:        http://{addr}/v1/hooks/{name}");
+    }
+    if !jobs.is_empty() {
+        println!("Scheduled Jobs:       {}", jobs.len());
+    }
+    crate::health::start_sd_notify();
     shutdown_signal().await;
+    crate::health::sd_notify("STOPPING=1");
     let _ = stop_server.send(());
     Ok(())
 }
 
 struct Server {
     config: Config,
+    /// Unlike `config`, keeps the tools that webhook triage runs with.
+    global_config: GlobalConfig,
+    incidents: IncidentQueue,
     models: Vec<Value>,
     roles: Vec<Role>,
     rags: Vec<String>,
@@ -73,6 +95,7 @@ struct Server {
 
 impl Server {
     fn new(config: &GlobalConfig) -> Self {
+        let global_config = config.clone();
         let mut config = config.read().clone();
         config.functions = Functions::default();
         let mut models = list_all_models(&config);
@@ -100,6 +123,8 @@ impl Server {
             .collect();
         Self {
             config,
+            global_config,
+            incidents: IncidentQueue::new(MAX_RUNNING_TRIAGES),
             models,
             roles: Config::all_roles(),
             rags: Config::list_rags(),
@@ -139,13 +164,34 @@ impl Server {
         Ok(tx)
     }
 
+    /// Serve one request as its own turn, echoing the id in `X-Request-Id`.
+    /// A caller-supplied `X-Request-Id` is reused.
     async fn handle(
         self: Arc<Self>,
         req: hyper::Request<Incoming>,
+    ) -> std::result::Result<AppResponse, hyper::Error> {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty() && v.len() <= 128)
+            .map(|v| v.to_string())
+            .unwrap_or_else(new_turn_id);
+        let mut res = with_turn_id(request_id.clone(), self.dispatch(req)).await?;
+        if let Ok(value) = request_id.parse() {
+            res.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        Ok(res)
+    }
+
+    async fn dispatch(
+        &self,
+        req: hyper::Request<Incoming>,
     ) -> std::result::Result<AppResponse, hyper::Error> {
         let method = req.method().clone();
         let uri = req.uri().clone();
         let path = uri.path();
+        let start = Instant::now();
 
         if method == Method::OPTIONS {
             let mut res = Response::default();
@@ -155,8 +201,16 @@ impl Server {
         }
 
         let mut status = StatusCode::OK;
-        let res = if path == "/v1/chat/completions" {
-            self.chat_completions(req).await
+        let user = if path.starts_with("/v1/") && !path.starts_with("/v1/hooks/") {
+            self.authenticate(&req)
+        } else {
+            Ok(None)
+        };
+        let res = if let Err(err) = &user {
+            status = StatusCode::UNAUTHORIZED;
+            Err(anyhow!("{err}"))
+        } else if path == "/v1/chat/completions" {
+            self.chat_completions(req, user.ok().flatten()).await
         } else if path == "/v1/embeddings" {
             self.embeddings(req).await
         } else if path == "/v1/rerank" {
@@ -164,37 +218,86 @@ impl Server {
         } else if path == "/v1/models" {
             self.list_models()
         } else if path == "/v1/roles" {
-            self.list_roles()
+            self.list_roles(user.ok().flatten())
         } else if path == "/v1/rags" {
             self.list_rags()
         } else if path == "/v1/rags/search" {
             self.search_rag(req).await
+        } else if path == "/v1/resolve" {
+            self.resolve(req, user.ok().flatten()).await
         } else if path == "/playground" || path == "/playground.html" {
             self.playground_page()
         } else if path == "/arena" || path == "/arena.html" {
             self.arena_page()
+        } else if path == "/metrics" {
+            self.metrics().await
+        } else if path == "/healthz" {
+            self.healthz()
+        } else if path == "/readyz" {
+            let (ready_status, res) = self.readyz().await;
+            status = ready_status;
+            res
+        } else if let Some(name) = path.strip_prefix("/v1/hooks/") {
+            let (hook_status, res) = self.webhook(name, req).await;
+            status = hook_status;
+            res
         } else {
             status = StatusCode::NOT_FOUND;
             Err(anyhow!("Not Found"))
         };
+        let model = match &res {
+            Ok(res) => res.extensions().get::<ModelLabel>().map(|v| v.0.clone()),
+            Err(_) => None,
+        };
+        let model = model.unwrap_or_default();
+        let route = crate::metrics::route_label(path);
+        let duration = start.elapsed();
+        let duration_ms = duration.as_millis() as u64;
         let mut res = match res {
             Ok(res) => {
-                info!("{method} {uri} {}", status.as_u16());
+                info!(
+                    method = method.as_str(), route = route.as_str(), status = status.as_u16(),
+                    model = model.as_str(), duration_ms = duration_ms, outcome = "ok";
+                    "{method} {uri} {}", status.as_u16()
+                );
                 res
             }
             Err(err) => {
                 if status == StatusCode::OK {
                     status = StatusCode::BAD_REQUEST;
                 }
-                error!("{method} {uri} {} {err}", status.as_u16());
+                error!(
+                    method = method.as_str(), route = route.as_str(), status = status.as_u16(),
+                    model = model.as_str(), duration_ms = duration_ms, outcome = "error";
+                    "{method} {uri} {} {err}", status.as_u16()
+                );
                 ret_err(err)
             }
         };
         *res.status_mut() = status;
         set_cors_header(&mut res);
+        crate::metrics::observe_http_request(&route, &model, status.as_u16(), duration);
         Ok(res)
     }
 
+    /// Once any user profile has API keys, `/v1` requests must present one of them.
+    fn authenticate(&self, req: &hyper::Request<Incoming>) -> Result<Option<UserIdentity>> {
+        if !self.config.requires_api_key() {
+            return Ok(None);
+        }
+        let key = req
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or_else(|| anyhow!("Missing API key"))?;
+        match self.config.user_by_api_key(key) {
+            Some(user) => Ok(Some(user)),
+            None => bail!("Invalid API key"),
+        }
+    }
+
     fn playground_page(&self) -> Result<AppResponse> {
         let res = Response::builder()
             .header("Content-Type", "text/html; charset=utf-8")
@@ -209,6 +312,47 @@ impl Server {
         Ok(res)
     }
 
+    async fn metrics(&self) -> Result<AppResponse> {
+        let manager = self.global_config.read().mcp_manager.clone();
+        let mcp_servers: Vec<(String, bool)> = match manager {
+            Some(manager) => manager
+                .list_servers()
+                .await
+                .into_iter()
+                .map(|(name, connected, _)| (name, connected))
+                .collect(),
+            None => vec![],
+        };
+        let res = Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+            .body(Full::new(Bytes::from(crate::metrics::render(&mcp_servers))).boxed())?;
+        Ok(res)
+    }
+
+    fn healthz(&self) -> Result<AppResponse> {
+        let data = json!({ "status": "ok" });
+        let res = Response::builder()
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(Full::new(Bytes::from(data.to_string())).boxed())?;
+        Ok(res)
+    }
+
+    async fn readyz(&self) -> (StatusCode, Result<AppResponse>) {
+        let checks = crate::health::readiness_checks(&self.global_config).await;
+        let ready = checks.iter().all(|v| v.ok);
+        let (status, label) = if ready {
+            (StatusCode::OK, "ready")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+        };
+        let data = json!({ "status": label, "checks": checks });
+        let res = Response::builder()
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(Full::new(Bytes::from(data.to_string())).boxed())
+            .map_err(Into::into);
+        (status, res)
+    }
+
     fn list_models(&self) -> Result<AppResponse> {
         let data = json!({ "data": self.models });
         let res = Response::builder()
@@ -217,8 +361,13 @@ impl Server {
         Ok(res)
     }
 
-    fn list_roles(&self) -> Result<AppResponse> {
-        let data = json!({ "data": self.roles });
+    fn list_roles(&self, user: Option<UserIdentity>) -> Result<AppResponse> {
+        let roles: Vec<&Role> = self
+            .roles
+            .iter()
+            .filter(|v| user.as_ref().is_none_or(|user| user.allows_role(v.name())))
+            .collect();
+        let data = json!({ "data": roles });
         let res = Response::builder()
             .header("Content-Type", "application/json; charset=utf-8")
             .body(Full::new(Bytes::from(data.to_string())).boxed())?;
@@ -258,7 +407,132 @@ impl Server {
         Ok(res)
     }
 
-    async fn chat_completions(&self, req: hyper::Request<Incoming>) -> Result<AppResponse> {
+    /// Resolve a request to a provider/workspace/action, or return the candidates to ask
+    /// the user about as `clarification`. Posting the user's choice back as `pick`
+    /// learns it for the request's text in the user's overlay; the pick must be one of
+    /// the candidates, and without a user API key nothing is learned.
+    async fn resolve(
+        &self,
+        req: hyper::Request<Incoming>,
+        user: Option<UserIdentity>,
+    ) -> Result<AppResponse> {
+        let req_body = req.collect().await?.to_bytes();
+        let req_body: Value = serde_json::from_slice(&req_body)
+            .map_err(|err| anyhow!("Invalid request json, {err}"))?;
+
+        debug!("resolve request: {req_body}");
+        let ResolveReqBody { text, pick } = serde_json::from_value(req_body)
+            .map_err(|err| anyhow!("Invalid request body, {err}"))?;
+
+        let config = Arc::new(RwLock::new(self.global_config.read().clone()));
+        if let Some(resolver) = config.write().resolver.as_mut() {
+            resolver.set_overlay(user.as_ref().map(|v| v.name.as_str()))?;
+        }
+
+        let data = match pick {
+            Some(pick) => {
+                let resolver = config.read().resolver.clone();
+                let intent = resolver
+                    .and_then(|v| {
+                        Clarification::offered(&v, &text).pick_fields(
+                            &pick.provider,
+                            pick.workspace.as_deref(),
+                            pick.action.as_deref(),
+                        )
+                    })
+                    .ok_or_else(|| anyhow!("The pick is not one of the candidates for '{text}'"))?;
+                // Anonymous picks would change routing for everybody.
+                if user.is_some() {
+                    learn_intent(&config, &intent, &text).await;
+                }
+                json!({ "intent": intent, "learned": user.is_some() })
+            }
+            None => match resolve_turn_intent(&config, create_abort_signal(), &text).await {
+                TurnIntent::Resolved(intent) => json!({ "intent": intent }),
+                TurnIntent::Ambiguous(clarification) if !clarification.options.is_empty() => {
+                    json!({ "intent": null, "clarification": clarification })
+                }
+                _ => json!({ "intent": null }),
+            },
+        };
+        let res = Response::builder()
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(Full::new(Bytes::from(data.to_string())).boxed())?;
+        Ok(res)
+    }
+
+    async fn webhook(
+        &self,
+        name: &str,
+        req: hyper::Request<Incoming>,
+    ) -> (StatusCode, Result<AppResponse>) {
+        let Some(hook) = self.config.hooks.get(name).cloned() else {
+            return (StatusCode::NOT_FOUND, Err(anyhow!("Unknown hook '{name}'")));
+        };
+        if req.method() != Method::POST {
+            return (
+                StatusCode::METHOD_NOT_ALLOWED,
+                Err(anyhow!("Method not allowed")),
+            );
+        }
+        let authorization = req
+            .headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok());
+        if !hook.authorize(authorization, req.uri().query()) {
+            return (StatusCode::UNAUTHORIZED, Err(anyhow!("Invalid hook token")));
+        }
+        let incident = async {
+            let req_body = req.collect().await?.to_bytes();
+            let req_body: Value = serde_json::from_slice(&req_body)
+                .map_err(|err| anyhow!("Invalid request json, {err}"))?;
+            debug!("hook '{name}' request: {req_body}");
+            Incident::parse(name, hook.format, req_body)
+                .map_err(|err| anyhow!("Invalid {} payload, {err}", hook.format.name()))
+        }
+        .await;
+        let incident = match incident {
+            Ok(incident) => incident,
+            Err(err) => return (StatusCode::BAD_REQUEST, Err(err)),
+        };
+        let mut data = json!({
+            "id": incident.id,
+            "hook": name,
+            "alerts": incident.alerts.len(),
+        });
+        let status = match self.incidents.admit(&incident) {
+            Admission::Accepted(permit) => {
+                let config = self.global_config.clone();
+                tokio::spawn(in_current_turn(async move {
+                    handle_incident(config, hook, incident).await;
+                    drop(permit);
+                }));
+                data["status"] = "accepted".into();
+                StatusCode::ACCEPTED
+            }
+            Admission::Duplicate => {
+                data["status"] = "duplicate".into();
+                StatusCode::OK
+            }
+            Admission::Busy => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Err(anyhow!("Too many incidents being triaged, retry later")),
+                );
+            }
+        };
+        let res = Response::builder()
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(Full::new(Bytes::from(data.to_string())).boxed())
+            .map_err(Into::into);
+        (status, res)
+    }
+
+    async fn chat_completions(
+        &self,
+        req: hyper::Request<Incoming>,
+        user: Option<UserIdentity>,
+    ) -> Result<AppResponse> {
         let req_body = req.collect().await?.to_bytes();
         let req_body: Value = serde_json::from_slice(&req_body)
             .map_err(|err| anyhow!("Invalid request json, {err}"))?;
@@ -282,14 +556,16 @@ impl Server {
 
         let functions = parse_tools(tools).map_err(|err| anyhow!("Invalid request body, {err}"))?;
 
-        let config = self.config.clone();
+        let mut config = self.config.clone();
+        config.user = user;
 
         let default_model = config.model.clone();
+        let user_model = config.user.as_ref().and_then(|v| v.profile.model.clone());
 
         let config = Arc::new(RwLock::new(config));
 
         let (model_name, change) = if model == DEFAULT_MODEL_NAME {
-            (default_model.id(), true)
+            (user_model.unwrap_or_else(|| default_model.id()), true)
         } else if default_model.id() == model {
             (model, false)
         } else {
@@ -309,6 +585,7 @@ impl Server {
 
         let completion_id = generate_completion_id();
         let created = Utc::now().timestamp();
+        let model_label = ModelLabel(client.model().id());
 
         patch_messages(&mut messages, client.model());
 
@@ -322,7 +599,7 @@ impl Server {
 
         if stream {
             let (tx, mut rx) = unbounded_channel();
-            tokio::spawn(async move {
+            tokio::spawn(in_current_turn(async move {
                 let is_first = Arc::new(AtomicBool::new(true));
                 let (sse_tx, sse_rx) = unbounded_channel();
                 let mut handler = SseHandler::new(sse_tx, abort_signal);
@@ -360,6 +637,11 @@ impl Server {
                         let ret = client.chat_completions_inner(http_client, data).await;
                         match ret {
                             Ok(output) => {
+                                crate::metrics::record_tokens(
+                                    &client.model().id(),
+                                    output.input_tokens,
+                                    output.output_tokens,
+                                );
                                 let ChatCompletionsOutput {
                                     text, tool_calls, ..
                                 } = output;
@@ -380,7 +662,15 @@ impl Server {
                             .chat_completions_streaming_inner(http_client, handler, data)
                             .await;
                         let first = match ret {
-                            Ok(()) => None,
+                            Ok(()) => {
+                                let (input_tokens, output_tokens) = handler.tokens();
+                                crate::metrics::record_tokens(
+                                    &client.model().id(),
+                                    input_tokens,
+                                    output_tokens,
+                                );
+                                None
+                            }
                             Err(err) => Some(format!("{err:?}")),
                         };
                         if is_first.load(Ordering::SeqCst) {
@@ -405,7 +695,7 @@ impl Server {
                         is_first
                     ),
                 );
-            });
+            }));
 
             let first_event = rx.recv().await;
 
@@ -448,12 +738,19 @@ impl Server {
                 .header("Content-Type", "text/event-stream")
                 .header("Cache-Control", "no-cache")
                 .header("Connection", "keep-alive")
+                .extension(model_label)
                 .body(BodyExt::boxed(StreamBody::new(stream)))?;
             Ok(res)
         } else {
             let output = client.chat_completions_inner(&http_client, data).await?;
+            crate::metrics::record_tokens(
+                &model_label.0,
+                output.input_tokens,
+                output.output_tokens,
+            );
             let res = Response::builder()
                 .header("Content-Type", "application/json")
+                .extension(model_label)
                 .body(
                     Full::new(ret_non_stream(
                         &completion_id,
@@ -511,7 +808,7 @@ impl Server {
         let output = json!({
             "object": "list",
             "data": data,
-            "model": embedding_model_id,
+            "model": &embedding_model_id,
             "usage": {
                 "prompt_tokens": 0,
                 "total_tokens": 0,
@@ -519,6 +816,7 @@ impl Server {
         });
         let res = Response::builder()
             .header("Content-Type", "application/json")
+            .extension(ModelLabel(embedding_model_id))
             .body(Full::new(Bytes::from(output.to_string())).boxed())?;
         Ok(res)
     }
@@ -571,6 +869,7 @@ impl Server {
         });
         let res = Response::builder()
             .header("Content-Type", "application/json")
+            .extension(ModelLabel(reranker_model_id))
             .body(Full::new(Bytes::from(output.to_string())).boxed())?;
         Ok(res)
     }
@@ -582,6 +881,20 @@ struct SearchRagReqBody {
     input: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct ResolveReqBody {
+    text: String,
+    pick: Option<ResolvePick>,
+}
+
+/// The candidate a user picked from a `clarification`.
+#[derive(Debug, Deserialize)]
+struct ResolvePick {
+    provider: String,
+    workspace: Option<String>,
+    action: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ChatCompletionsReqBody {
     model: String,
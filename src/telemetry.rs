@@ -0,0 +1,393 @@
+//! OpenTelemetry trace export over OTLP/HTTP (JSON encoding).
+//!
+//! Spans are opened with [`in_span`] and nest through the task-local current span, the
+//! same way log records pick up the turn id. Finished spans are buffered and posted to
+//! `<endpoint>/v1/traces` in batches. Nothing is recorded until [`init`] is called with
+//! an endpoint.
+
+use crate::utils::current_turn_id;
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    env,
+    future::Future,
+    sync::{Arc, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const DEFAULT_SERVICE_NAME: &str = "fiochat";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BATCH: usize = 512;
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+tokio::task_local! {
+    static CURRENT_SPAN: Arc<Mutex<Span>>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OtelConfig {
+    /// Collector base URL, e.g. `http://127.0.0.1:4318`. Falls back to
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT`; export is off when neither is set.
+    pub endpoint: Option<String>,
+    /// Defaults to `OTEL_SERVICE_NAME`, then `fiochat`.
+    pub service_name: Option<String>,
+    /// Extra HTTP headers, e.g. an auth token for a hosted collector.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub headers: IndexMap<String, String>,
+}
+
+impl OtelConfig {
+    fn traces_url(&self) -> Option<String> {
+        let endpoint = self
+            .endpoint
+            .clone()
+            .or_else(|| env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+            .filter(|v| !v.trim().is_empty())?;
+        Some(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+    }
+
+    fn service_name(&self) -> String {
+        self.service_name
+            .clone()
+            .or_else(|| env::var("OTEL_SERVICE_NAME").ok())
+            .unwrap_or_else(|| DEFAULT_SERVICE_NAME.into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for AttrValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<u64> for AttrValue {
+    fn from(value: u64) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<bool> for AttrValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl AttrValue {
+    fn to_otlp(&self) -> Value {
+        match self {
+            AttrValue::String(v) => json!({ "stringValue": v }),
+            // OTLP/JSON encodes 64-bit integers as strings.
+            AttrValue::Int(v) => json!({ "intValue": v.to_string() }),
+            AttrValue::Bool(v) => json!({ "boolValue": v }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    name: &'static str,
+    kind: SpanKind,
+    start: u128,
+    attributes: Vec<(&'static str, AttrValue)>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Internal = 1,
+    Client = 3,
+}
+
+impl Span {
+    fn to_otlp(&self, end: u128) -> Value {
+        let attributes: Vec<Value> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value.to_otlp() }))
+            .collect();
+        let status = match &self.error {
+            Some(message) => json!({ "code": 2, "message": message }),
+            None => json!({ "code": 1 }),
+        };
+        let mut span = json!({
+            "traceId": self.trace_id,
+            "spanId": self.span_id,
+            "name": self.name,
+            "kind": self.kind as u8,
+            "startTimeUnixNano": self.start.to_string(),
+            "endTimeUnixNano": end.to_string(),
+            "attributes": attributes,
+            "status": status,
+        });
+        if let Some(parent) = &self.parent_span_id {
+            span["parentSpanId"] = parent.clone().into();
+        }
+        span
+    }
+}
+
+struct Tracer {
+    url: String,
+    service_name: String,
+    headers: IndexMap<String, String>,
+    client: reqwest::Client,
+    spans: Mutex<Vec<Value>>,
+}
+
+impl Tracer {
+    /// A tracer exporting to the configured endpoint, if any.
+    fn new(config: &OtelConfig) -> Result<Option<Self>> {
+        let Some(url) = config.traces_url() else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        Ok(Some(Self {
+            url,
+            service_name: config.service_name(),
+            headers: config.headers.clone(),
+            client,
+            spans: Default::default(),
+        }))
+    }
+
+    async fn in_span<F, T>(
+        &self,
+        name: &'static str,
+        kind: SpanKind,
+        mut attributes: Vec<(&'static str, AttrValue)>,
+        fut: F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let parent = CURRENT_SPAN
+            .try_with(|v| {
+                let v = v.lock();
+                (v.trace_id.clone(), v.span_id.clone())
+            })
+            .ok();
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => (random_hex(16), None),
+        };
+        if let Some(id) = current_turn_id() {
+            attributes.push(("fiochat.turn_id", id.into()));
+        }
+        let span = Arc::new(Mutex::new(Span {
+            trace_id,
+            span_id: random_hex(8),
+            parent_span_id,
+            name,
+            kind,
+            start: now_nanos(),
+            attributes,
+            error: None,
+        }));
+        let ret = CURRENT_SPAN.scope(span.clone(), fut).await;
+        let mut span = span.lock();
+        if let Err(err) = &ret {
+            span.error = Some(format!("{err:#}"));
+        }
+        self.push(span.to_otlp(now_nanos()));
+        ret
+    }
+
+    fn push(&self, span: Value) {
+        let mut spans = self.spans.lock();
+        if spans.len() < MAX_BATCH * 8 {
+            spans.push(span);
+        }
+    }
+
+    async fn flush(&self) -> Result<()> {
+        loop {
+            let batch: Vec<Value> = {
+                let mut spans = self.spans.lock();
+                let n = spans.len().min(MAX_BATCH);
+                spans.drain(..n).collect()
+            };
+            if batch.is_empty() {
+                return Ok(());
+            }
+            let body = json!({
+                "resourceSpans": [{
+                    "resource": {
+                        "attributes": [
+                            { "key": "service.name", "value": { "stringValue": self.service_name } },
+                            { "key": "service.version", "value": { "stringValue": env!("CARGO_PKG_VERSION") } },
+                        ]
+                    },
+                    "scopeSpans": [{
+                        "scope": { "name": env!("CARGO_CRATE_NAME") },
+                        "spans": batch,
+                    }]
+                }]
+            });
+            let mut builder = self.client.post(&self.url).json(&body);
+            for (key, value) in &self.headers {
+                builder = builder.header(key, value);
+            }
+            let res = builder
+                .send()
+                .await
+                .with_context(|| format!("Failed to export spans to {}", self.url))?;
+            let status = res.status();
+            if !status.is_success() {
+                anyhow::bail!("Failed to export spans to {}: {status}", self.url);
+            }
+        }
+    }
+}
+
+/// Start exporting spans if an endpoint is configured. Call once, inside the runtime.
+pub fn init(config: &OtelConfig) -> Result<()> {
+    let Some(tracer) = Tracer::new(config)? else {
+        return Ok(());
+    };
+    if TRACER.set(tracer).is_err() {
+        return Ok(());
+    }
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(FLUSH_INTERVAL).await;
+            flush().await;
+        }
+    });
+    Ok(())
+}
+
+/// Export the spans buffered so far, e.g. before the process exits.
+pub async fn flush() {
+    if let Some(tracer) = TRACER.get() {
+        if let Err(err) = tracer.flush().await {
+            warn!("{err:#}");
+        }
+    }
+}
+
+/// Run `fut` inside a new span, a child of the current one. An `Err` output marks the
+/// span as failed.
+pub async fn in_span<F, T>(
+    name: &'static str,
+    kind: SpanKind,
+    attributes: Vec<(&'static str, AttrValue)>,
+    fut: F,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    match TRACER.get() {
+        // Boxed so the untraced path doesn't carry a second copy of `fut` on the stack.
+        Some(tracer) => Box::pin(tracer.in_span(name, kind, attributes, fut)).await,
+        None => fut.await,
+    }
+}
+
+/// Add an attribute to the current span, e.g. token usage known only once a call returns.
+/// Without a tracer no span is ever current, so this is a no-op.
+pub fn set_attribute(key: &'static str, value: impl Into<AttrValue>) {
+    let _ = CURRENT_SPAN.try_with(|v| v.lock().attributes.push((key, value.into())));
+}
+
+fn random_hex(bytes: usize) -> String {
+    let uuid = uuid::Uuid::new_v4().simple().to_string();
+    uuid[..bytes * 2].to_string()
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_export_to_collector() {
+        let mut server = mockito::Server::new_async().await;
+        let collector = server
+            .mock("POST", "/v1/traces")
+            .match_header("x-token", "secret")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+        let config = OtelConfig {
+            endpoint: Some(format!("{}/", server.url())),
+            service_name: Some("fiochat-test".into()),
+            headers: [("x-token".to_string(), "secret".to_string())].into(),
+        };
+        let tracer = Tracer::new(&config).unwrap().unwrap();
+
+        let ret: Result<()> = tracer
+            .in_span(
+                "route_turn",
+                SpanKind::Internal,
+                vec![("fiochat.text_len", 5u64.into())],
+                async {
+                    tracer
+                        .in_span::<_, ()>(
+                            "mcp_call",
+                            SpanKind::Client,
+                            vec![("mcp.server", "github".into())],
+                            async {
+                                set_attribute("mcp.tool", "list_issues");
+                                anyhow::bail!("boom")
+                            },
+                        )
+                        .await
+                        .unwrap_err();
+                    Ok(())
+                },
+            )
+            .await;
+        ret.unwrap();
+
+        // A span is recorded when it ends, so the child comes first.
+        let spans = tracer.spans.lock().clone();
+        let [child, parent] = spans.as_slice() else {
+            panic!("expected two spans, got {spans:?}");
+        };
+        assert_eq!(child["name"], "mcp_call");
+        assert_eq!(child["traceId"], parent["traceId"]);
+        assert_eq!(child["parentSpanId"], parent["spanId"]);
+        assert!(parent.get("parentSpanId").is_none());
+        assert_eq!(child["status"], json!({ "code": 2, "message": "boom" }));
+        assert_eq!(
+            child["attributes"][1],
+            json!({ "key": "mcp.tool", "value": { "stringValue": "list_issues" } })
+        );
+        assert_eq!(parent["attributes"][0]["value"], json!({ "intValue": "5" }));
+
+        tracer.flush().await.unwrap();
+        collector.assert_async().await;
+        assert!(tracer.spans.lock().is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use log::{
+    kv::{self, Key, Value as KvValue, VisitSource},
+    LevelFilter, Log, Metadata, Record,
+};
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::{future::Future, io::Write};
+
+tokio::task_local! {
+    static TURN_ID: String;
+}
+
+/// A fresh id for one turn or HTTP request.
+pub fn new_turn_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// The id of the turn or request the current task is working on.
+pub fn current_turn_id() -> Option<String> {
+    TURN_ID.try_with(|v| v.clone()).ok()
+}
+
+/// Run `fut` as turn `id`; logs written while it runs carry `turn_id`.
+pub async fn with_turn_id<F: Future>(id: String, fut: F) -> F::Output {
+    TURN_ID.scope(id, fut).await
+}
+
+/// Run `fut` under the current turn id, or a new one when there is none.
+/// For work spawned on behalf of a turn, call it before `tokio::spawn`.
+pub fn in_current_turn<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    let id = current_turn_id().unwrap_or_else(new_turn_id);
+    TURN_ID.scope(id, fut)
+}
+
+/// Writes one JSON object per record: `ts`, `level`, `target`, `msg`, `turn_id` (when
+/// inside a turn) and the record's key-values, e.g. `info!(model = id; "...")`.
+pub struct JsonLogger {
+    level: LevelFilter,
+    filters: Vec<String>,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLogger {
+    pub fn init(
+        level: LevelFilter,
+        filters: Vec<String>,
+        writer: Box<dyn Write + Send>,
+    ) -> Result<(), log::SetLoggerError> {
+        log::set_max_level(level);
+        log::set_boxed_logger(Box::new(Self {
+            level,
+            filters,
+            writer: Mutex::new(writer),
+        }))
+    }
+
+    fn format(&self, record: &Record) -> Value {
+        let mut data = Map::new();
+        data.insert(
+            "ts".into(),
+            chrono::Utc::now()
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string()
+                .into(),
+        );
+        data.insert(
+            "level".into(),
+            record.level().as_str().to_lowercase().into(),
+        );
+        data.insert("target".into(), record.target().into());
+        data.insert("msg".into(), record.args().to_string().into());
+        if let Some(id) = current_turn_id() {
+            data.insert("turn_id".into(), id.into());
+        }
+        let _ = record.key_values().visit(&mut Fields(&mut data));
+        Value::Object(data)
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && (self.filters.is_empty()
+                || self
+                    .filters
+                    .iter()
+                    .any(|v| metadata.target().starts_with(v)))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.format(record).to_string();
+        let mut writer = self.writer.lock();
+        let _ = writeln!(writer, "{line}");
+        let _ = writer.flush();
+    }
+
+    fn flush(&self) {
+        let _ = self.writer.lock().flush();
+    }
+}
+
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: KvValue<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(v) = value.to_bool() {
+            v.into()
+        } else if let Some(v) = value.to_u64() {
+            v.into()
+        } else if let Some(v) = value.to_i64() {
+            v.into()
+        } else if let Some(v) = value.to_f64() {
+            v.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_format() {
+        let logger = JsonLogger {
+            level: LevelFilter::Info,
+            filters: vec!["fiochat::serve".into()],
+            writer: Mutex::new(Box::new(std::io::sink())),
+        };
+        let metadata = Metadata::builder()
+            .level(log::Level::Info)
+            .target("fiochat::serve")
+            .build();
+        assert!(logger.enabled(&metadata));
+        let kvs = [
+            ("status", KvValue::from(200u64)),
+            ("model", "openai:gpt-4o".into()),
+        ];
+        let data = with_turn_id("abc".into(), async {
+            logger.format(
+                &Record::builder()
+                    .metadata(metadata)
+                    .args(format_args!("GET /v1/models 200"))
+                    .key_values(&kvs)
+                    .build(),
+            )
+        })
+        .await;
+        assert_eq!(data["level"], "info");
+        assert_eq!(data["msg"], "GET /v1/models 200");
+        assert_eq!(data["turn_id"], "abc");
+        assert_eq!(data["status"], 200);
+        assert_eq!(data["model"], "openai:gpt-4o");
+        assert!(current_turn_id().is_none());
+
+        let metadata = Metadata::builder()
+            .level(log::Level::Info)
+            .target("fiochat::interactive")
+            .build();
+        assert!(!logger.enabled(&metadata));
+    }
+}
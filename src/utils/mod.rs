@@ -5,6 +5,7 @@ mod crypto;
 mod html_to_md;
 mod input;
 mod loader;
+mod logger;
 mod path;
 mod render_prompt;
 mod request;
@@ -18,6 +19,7 @@ pub use self::crypto::*;
 pub use self::html_to_md::*;
 pub use self::input::*;
 pub use self::loader::*;
+pub use self::logger::*;
 pub use self::path::*;
 pub use self::render_prompt::render_prompt;
 pub use self::request::*;